@@ -77,3 +77,41 @@ pub fn rim_config_dir() -> &'static Path {
         config_root
     })
 }
+
+/// Cache directory to store data that can be safely removed, such as downloaded packages.
+///
+/// Unlike [`rim_config_dir`], this dir will not be removed after uninstallation,
+/// and it will be stored under OS's `cache_dir`, which can be:
+/// - `$HOME/.cache/rim` on Linux
+/// - `$HOME/Library/Caches/rim` on macOS
+/// - `$HOME\AppData\Local\rim\cache` on Windows
+///
+/// # Panic
+/// Panic if the OS's cache directory cannot be determined, which typically meaning
+/// that the `HOME` env var is missing and the current OS is not support by the [`dirs`] crate.
+pub fn rim_cache_dir() -> &'static Path {
+    get_path_and_create!(RIM_CACHE_DIR, {
+        let mut cache_root = home_dir();
+
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "linux")] {
+                cache_root.push(".cache");
+                cache_root.push("rim");
+            } else if #[cfg(windows)] {
+                cache_root.push("AppData");
+                cache_root.push("Local");
+                cache_root.push("rim");
+                cache_root.push("cache");
+            } else if #[cfg(target_os = "macos")] {
+                cache_root.push("Library");
+                cache_root.push("Caches");
+                cache_root.push("rim");
+            } else {
+                cache_root = dirs::cache_dir()
+                    .expect("unable to determine cache directory, maybe your OS is not supported")
+                    .join("rim");
+            }
+        }
+        cache_root
+    })
+}
@@ -23,6 +23,12 @@ pub struct BuildConfig {
     #[serde(flatten)]
     config: SourceConfig,
     pub registry: CargoRegistry,
+    /// Base64 encoded ed25519 public keys that are used to verify
+    /// the detached signatures of manifests downloaded from server.
+    ///
+    /// Signature verification is disabled if this is empty.
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
     pub locale: HashMap<String, LocaleMap>,
 }
 
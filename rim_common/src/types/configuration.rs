@@ -2,16 +2,22 @@
 //! when the updates are checked, how long until next updates will be checked etc.
 
 use crate::setter;
+use crate::utils::RetryPolicy;
 use crate::{dirs::rim_config_dir, types::TomlParser};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Serialize, Default, PartialEq, Eq, Clone)]
 pub struct Configuration {
     pub language: Option<Language>,
     pub update: UpdateConfig,
+    #[serde(default)]
+    pub download: DownloadConfig,
+    #[serde(default)]
+    pub extract: ExtractConfig,
 }
 
 impl TomlParser for Configuration {
@@ -54,6 +60,11 @@ impl Configuration {
         self.update.auto_check_toolkit_updates,
         bool
     ));
+    setter!(set_pinned_toolkit(self.update.pinned_toolkit, Option<String>));
+    setter!(set_max_concurrent_downloads(
+        self.download.max_concurrent,
+        usize
+    ));
 }
 
 #[derive(Debug, Deserialize, Serialize, Default, PartialEq, Eq, Clone, Copy)]
@@ -122,6 +133,9 @@ pub struct UpdateConfig {
     /// Automatically checking for toolkit updates.
     #[serde(default = "bool_true")]
     pub auto_check_toolkit_updates: bool,
+    /// The toolkit version to stay on, toolkit updates will not be offered if this is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_toolkit: Option<String>,
 }
 
 // Return `true` for the serde default arg.
@@ -136,6 +150,7 @@ impl Default for UpdateConfig {
             manager_update_channel: ReleaseChannel::default(),
             auto_check_manager_updates: true,
             auto_check_toolkit_updates: true,
+            pinned_toolkit: None,
         }
     }
 }
@@ -157,6 +172,124 @@ impl UpdateConfig {
         self.auto_check_toolkit_updates,
         bool
     ));
+    setter!(pinned_toolkit(self.pinned_toolkit, Option<String>));
+}
+
+/// Representing the configuration for downloading packages.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct DownloadConfig {
+    /// The maximum number of packages to download at the same time.
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// The number of times to retry a failed download.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Seconds to wait before the first retry, which doubles after each retry.
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: u64,
+    /// Seconds to wait for the server to respond or to send more data.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Keep the downloaded packages in [`DownloadCache`](crate::utils::DownloadCache),
+    /// so they don't need to be downloaded again.
+    ///
+    /// Note that only the packages with a known sha256 checksum are cached.
+    #[serde(default = "bool_true")]
+    pub cache: bool,
+}
+
+fn default_max_concurrent() -> usize {
+    4
+}
+
+fn default_retries() -> u32 {
+    RetryPolicy::default().retries
+}
+
+fn default_retry_backoff() -> u64 {
+    RetryPolicy::default().backoff.as_secs()
+}
+
+fn default_timeout() -> u64 {
+    RetryPolicy::default().timeout.as_secs()
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+            retries: default_retries(),
+            retry_backoff: default_retry_backoff(),
+            timeout: default_timeout(),
+            cache: true,
+        }
+    }
+}
+
+impl DownloadConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    setter!(max_concurrent(self.max_concurrent, usize));
+    setter!(retries(self.retries, u32));
+    setter!(retry_backoff(self.retry_backoff, u64));
+    setter!(timeout(self.timeout, u64));
+    setter!(cache(self.cache, bool));
+
+    /// Get the policy of retrying failed downloads.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            retries: self.retries,
+            backoff: Duration::from_secs(self.retry_backoff),
+            timeout: Duration::from_secs(self.timeout),
+        }
+    }
+}
+
+/// Representing the limits of extracting packages, which prevent malicious
+/// archives (such as zip bombs) from exhausting the disk space.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtractConfig {
+    /// The maximum size (in MiB) of the files extracted from a single package.
+    #[serde(default = "default_max_extract_size")]
+    pub max_size: u64,
+    /// The maximum number of entries extracted from a single package.
+    #[serde(default = "default_max_extract_entries")]
+    pub max_entries: u64,
+}
+
+fn default_max_extract_size() -> u64 {
+    16 * 1024
+}
+
+fn default_max_extract_entries() -> u64 {
+    1_000_000
+}
+
+impl Default for ExtractConfig {
+    fn default() -> Self {
+        Self {
+            max_size: default_max_extract_size(),
+            max_entries: default_max_extract_entries(),
+        }
+    }
+}
+
+impl ExtractConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    setter!(max_size(self.max_size, u64));
+    setter!(max_entries(self.max_entries, u64));
+
+    /// Get the maximum size of the files extracted from a single package in bytes.
+    pub fn max_size_in_bytes(&self) -> u64 {
+        self.max_size.saturating_mul(1024 * 1024)
+    }
 }
 
 #[cfg(test)]
@@ -183,6 +316,17 @@ toolkit = { skip = "1.0.0", last-run = "1970-01-01T00:00:00" }"#;
 manager-update-channel = "stable"
 auto-check-manager-updates = true
 auto-check-toolkit-updates = true
+
+[download]
+max-concurrent = 4
+retries = 3
+retry-backoff = 1
+timeout = 30
+cache = true
+
+[extract]
+max-size = 16384
+max-entries = 1000000
 "#
         );
     }
@@ -193,7 +337,8 @@ auto-check-toolkit-updates = true
             .set_language(Language::CN)
             .set_manager_update_channel(ReleaseChannel::Beta)
             .set_auto_check_manager_updates(false)
-            .set_auto_check_toolkit_updates(false);
+            .set_auto_check_toolkit_updates(false)
+            .set_max_concurrent_downloads(8);
 
         let expected = conf.to_toml().unwrap();
         assert_eq!(
@@ -204,10 +349,32 @@ auto-check-toolkit-updates = true
 manager-update-channel = "beta"
 auto-check-manager-updates = false
 auto-check-toolkit-updates = false
+
+[download]
+max-concurrent = 8
+retries = 3
+retry-backoff = 1
+timeout = 30
+cache = true
+
+[extract]
+max-size = 16384
+max-entries = 1000000
 "#
         );
     }
 
+    #[test]
+    fn pinned_toolkit() {
+        let conf = Configuration::new().set_pinned_toolkit(Some("1.0.0".into()));
+        let raw = conf.to_toml().unwrap();
+        assert!(raw.contains("pinned-toolkit = \"1.0.0\"\n"));
+        assert_eq!(Configuration::from_str(&raw).unwrap(), conf);
+
+        let unpinned = conf.set_pinned_toolkit(None);
+        assert!(!unpinned.to_toml().unwrap().contains("pinned-toolkit"));
+    }
+
     #[test]
     fn lang_config() {
         let input = "language = \"zh-CN\"\n[update]";
@@ -223,7 +390,16 @@ auto-check-toolkit-updates = false
             [update]\n\
             manager-update-channel = \"stable\"\n\
             auto-check-manager-updates = true\n\
-            auto-check-toolkit-updates = true\n"
+            auto-check-toolkit-updates = true\n\n\
+            [download]\n\
+            max-concurrent = 4\n\
+            retries = 3\n\
+            retry-backoff = 1\n\
+            timeout = 30\n\
+            cache = true\n\n\
+            [extract]\n\
+            max-size = 16384\n\
+            max-entries = 1000000\n"
         );
     }
 }
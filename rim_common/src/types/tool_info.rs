@@ -213,6 +213,26 @@ impl ToolInfo {
             .unwrap_or_default()
     }
 
+    /// Get the expected sha256 checksum of this tool's package.
+    ///
+    /// ```toml
+    /// hello = { url = "https://example.com/hello.zip", sha256 = "b94d27b9..." }
+    /// #                                                 ^^^^^^^^^^^^^^^^^^^^
+    /// ```
+    pub fn sha256(&self) -> Option<&str> {
+        self.details().and_then(|d| d.sha256.as_deref())
+    }
+
+    /// Get the estimated disk space (in bytes) required to install this tool.
+    ///
+    /// ```toml
+    /// hello = { url = "https://example.com/hello.zip", size = 10485760 }
+    /// #                                                 ^^^^^^^^^^^^^^^
+    /// ```
+    pub fn size(&self) -> Option<u64> {
+        self.details().and_then(|d| d.size)
+    }
+
     /// Get a designated filename for `Url` source.
     pub fn filename(&self) -> Option<&str> {
         if let Some(det) = self.details() {
@@ -257,6 +277,13 @@ pub struct ToolInfoDetails {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     /// A list of tools that this package conflicts with.
     pub conflicts: Vec<String>,
+    /// Expected sha256 checksum of the downloaded package,
+    /// which will be verified before installation if provided.
+    /// Packages without it are not kept in the download cache.
+    pub sha256: Option<String>,
+    /// Estimated disk space (in bytes) required to install this tool,
+    /// used to check if there's enough free space before installation.
+    pub size: Option<u64>,
 }
 
 impl ToolInfoDetails {
@@ -25,6 +25,8 @@ pub struct ToolkitManifest {
     pub toolchain: RustToolchain,
     #[serde(default)]
     pub tools: Tools,
+    #[serde(default, skip_serializing_if = "VendoredCrates::is_empty")]
+    pub crates: VendoredCrates,
 
     /// Path to the manifest file.
     #[serde(skip)]
@@ -62,6 +64,22 @@ impl ToolkitManifest {
             .collect()
     }
 
+    /// Get a list of additional compilation target names, along with a flag
+    /// indicating whether it is optional or not.
+    pub fn toolchain_targets(&self) -> Vec<(&str, bool)> {
+        self.toolchain
+            .targets
+            .iter()
+            .map(|s| (s.as_str(), false))
+            .chain(
+                self.toolchain
+                    .optional_targets
+                    .iter()
+                    .map(|s| (s.as_str(), true)),
+            )
+            .collect()
+    }
+
     /// Get the description of a specific tool.
     pub fn get_tool_description(&self, tool: &str) -> Option<&str> {
         self.tools.descriptions.get(tool).map(|s| s.as_str())
@@ -99,6 +117,10 @@ pub struct ToolkitConfig {
     /// This is the top priority when it comes to writing cargo config
     /// after installing toolchain.
     pub cargo_registry: Option<CargoRegistry>,
+    /// Base URLs of the mirror servers, which are tried in order
+    /// when a package cannot be downloaded from its original server.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<Url>,
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Default, Clone)]
@@ -121,6 +143,16 @@ pub struct RustToolchain {
     /// Optional components are only installed if user choose to.
     #[serde(default)]
     pub optional_components: Vec<String>,
+    /// Additional compilation targets, such as `wasm32-unknown-unknown`,
+    /// whose standard library are installed by default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// Additional compilation targets that are only installed if user choose to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub optional_targets: Vec<String>,
+    /// The tools required by each target, such as the linker used for cross-compilation.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub target_requires: IndexMap<String, Vec<String>>,
     /// Optional category (group) name for the rust toolchain,
     /// so you can group the toolchain components `Rust Toolchain` or something else.
     /// note that all optional components belong to this group as well.
@@ -129,7 +161,93 @@ pub struct RustToolchain {
     pub offline_dist_server: Option<String>,
     /// Contains target specific `rustup-init` binaries.
     #[serde(default)]
-    pub rustup: IndexMap<String, String>,
+    pub rustup: IndexMap<String, RustupInit>,
+    /// Estimated disk space (in bytes) required to install the toolchain profile.
+    pub size: Option<u64>,
+    /// Estimated disk space (in bytes) required to install each toolchain component or target.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub component_sizes: IndexMap<String, u64>,
+    /// Additional toolchains that are installed along with the default one,
+    /// check [`ExtraToolchain`] for more details.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub extra_toolchains: IndexMap<String, ExtraToolchain>,
+}
+
+/// An additional named toolchain, such as a dated nightly toolchain that is required by
+/// `miri` or `cargo-fuzz`, which will be installed next to the default toolchain
+/// without replacing it.
+///
+/// ```toml
+/// [rust.extra-toolchains.nightly]
+/// channel = "nightly-2024-10-01"
+/// profile = "minimal"
+/// components = ["miri", "rust-src"]
+/// # optional custom toolchain name, so that it can be used as `cargo +fuzz`
+/// alias = "fuzz"
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExtraToolchain {
+    /// Toolchain channel name, same as [`RustToolchain::channel`].
+    #[serde(alias = "version")]
+    pub channel: String,
+    /// Toolchain profile that rustup uses to install this toolchain.
+    pub profile: Option<String>,
+    /// Components to be installed with this toolchain.
+    #[serde(default)]
+    pub components: Vec<String>,
+    /// Custom toolchain name that links to this toolchain.
+    pub alias: Option<String>,
+}
+
+/// Target specific `rustup-init` binary, which is either a path to the bundled binary:
+///
+/// ```toml
+/// [rust.rustup]
+/// x86_64-unknown-linux-gnu = "tools/rustup-init"
+/// ```
+///
+/// Or a detailed table that contains an expected sha256 checksum,
+/// which will be verified before running it:
+///
+/// ```toml
+/// [rust.rustup]
+/// x86_64-unknown-linux-gnu = { path = "tools/rustup-init", sha256 = "..." }
+/// # the path can be omitted if `rustup-init` will be downloaded from server
+/// x86_64-pc-windows-msvc = { sha256 = "..." }
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum RustupInit {
+    Path(String),
+    Detailed {
+        path: Option<String>,
+        sha256: Option<String>,
+    },
+}
+
+impl RustupInit {
+    /// Relative path to the bundled `rustup-init` binary, if there is one.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::Path(path) => Some(path),
+            Self::Detailed { path, .. } => path.as_deref(),
+        }
+    }
+
+    /// The expected sha256 checksum of `rustup-init` binary.
+    pub fn sha256(&self) -> Option<&str> {
+        match self {
+            Self::Path(_) => None,
+            Self::Detailed { sha256, .. } => sha256.as_deref(),
+        }
+    }
+}
+
+impl<T: Into<String>> From<T> for RustupInit {
+    fn from(value: T) -> Self {
+        Self::Path(value.into())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
@@ -216,6 +334,65 @@ impl RustToolchain {
             .or(self.group.as_deref())
             .unwrap_or("Rust")
     }
+
+    /// Get the expected sha256 checksum of `rustup-init` for specific target.
+    pub fn rustup_init_sha256(&self, target: &str) -> Option<&str> {
+        self.rustup.get(target).and_then(|r| r.sha256())
+    }
+}
+
+/// A curated set of crates that are shipped along with offline packages,
+/// which can be used without network access once installed.
+///
+/// ```toml
+/// [crates]
+/// path = "crates"
+///
+/// [crates.packages]
+/// serde = "1.0.210"
+/// tokio = { version = "1.40", features = ["full"] }
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Default, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct VendoredCrates {
+    /// Relative path to the bundled directory of vendored crates, in the same layout as
+    /// the output of `cargo vendor`, which only present in offline manifests.
+    pub path: Option<String>,
+    /// The crates and their version requirements (and features),
+    /// their dependencies will be vendored as well.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub packages: IndexMap<String, VendoredCrate>,
+}
+
+impl VendoredCrates {
+    pub fn is_empty(&self) -> bool {
+        self.path.is_none() && self.packages.is_empty()
+    }
+}
+
+/// A crate listed in [`VendoredCrates`], which is written the same way as
+/// a dependency in `Cargo.toml`, so that optional dependencies can be vendored as well:
+///
+/// ```toml
+/// serde = "1.0.210"
+/// # or
+/// serde = { version = "1.0.210", features = ["derive"], default-features = false }
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum VendoredCrate {
+    Version(String),
+    Detailed {
+        version: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
+        #[serde(
+            default,
+            rename = "default-features",
+            skip_serializing_if = "Option::is_none"
+        )]
+        default_features: Option<bool>,
+    },
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Default, Clone)]
@@ -602,6 +779,146 @@ display-name = "Rust-lang"
         assert_eq!(expected.toolchain.name(), "Rust");
     }
 
+    #[test]
+    fn with_vendored_crates() {
+        let input = r#"
+[rust]
+version = "1.0.0"
+
+[crates]
+path = "crates"
+
+[crates.packages]
+serde = "1.0.210"
+tokio = { version = "1.40", features = ["full"] }
+"#;
+        let expected = ToolkitManifest::from_str(input).unwrap();
+        assert_eq!(expected.crates.path.as_deref(), Some("crates"));
+        assert_eq!(
+            expected.crates.packages.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "serde".to_string(),
+                    VendoredCrate::Version("1.0.210".to_string())
+                ),
+                (
+                    "tokio".to_string(),
+                    VendoredCrate::Detailed {
+                        version: "1.40".to_string(),
+                        features: vec!["full".to_string()],
+                        default_features: None,
+                    }
+                )
+            ]
+        );
+
+        let without_crates = ToolkitManifest::from_str("[rust]\nversion = \"1.0.0\"").unwrap();
+        assert!(without_crates.crates.is_empty());
+        assert!(!without_crates.to_toml().unwrap().contains("[crates]"));
+    }
+
+    #[test]
+    fn with_size_hints() {
+        let input = r#"
+[rust]
+version = "1.0.0"
+components = ["rustfmt", "clippy"]
+size = 300000000
+component-sizes = { rustfmt = 5000000 }
+
+[tools.target.all]
+a = { version = "0.1.0", size = 1024 }
+b = "0.1.0"
+"#;
+        let expected = ToolkitManifest::from_str(input).unwrap();
+        assert_eq!(expected.toolchain.size, Some(300000000));
+        assert_eq!(
+            expected.toolchain.component_sizes.get("rustfmt"),
+            Some(&5000000)
+        );
+        assert_eq!(expected.toolchain.component_sizes.get("clippy"), None);
+
+        let tools = expected.tools.target.get("all").unwrap();
+        assert_eq!(tools.get("a").unwrap().size(), Some(1024));
+        assert_eq!(tools.get("b").unwrap().size(), None);
+    }
+
+    #[test]
+    fn with_toolchain_targets() {
+        let input = r#"
+[rust]
+version = "1.0.0"
+targets = ["wasm32-unknown-unknown"]
+optional-targets = ["aarch64-unknown-linux-gnu", "thumbv7em-none-eabihf"]
+
+[rust.target-requires]
+aarch64-unknown-linux-gnu = ["gcc-aarch64"]
+"#;
+        let expected = ToolkitManifest::from_str(input).unwrap();
+        assert_eq!(
+            expected.toolchain_targets(),
+            [
+                ("wasm32-unknown-unknown", false),
+                ("aarch64-unknown-linux-gnu", true),
+                ("thumbv7em-none-eabihf", true)
+            ]
+        );
+        assert_eq!(
+            expected
+                .toolchain
+                .target_requires
+                .get("aarch64-unknown-linux-gnu")
+                .unwrap(),
+            &["gcc-aarch64"]
+        );
+
+        let without_targets = ToolkitManifest::from_str("[rust]\nversion = \"1.0.0\"").unwrap();
+        assert!(without_targets.toolchain_targets().is_empty());
+        assert!(!without_targets.to_toml().unwrap().contains("targets"));
+    }
+
+    #[test]
+    fn with_extra_toolchains() {
+        let input = r#"
+[rust]
+version = "1.0.0"
+
+[rust.extra-toolchains.nightly]
+channel = "nightly-2024-10-01"
+profile = "minimal"
+components = ["miri", "rust-src"]
+alias = "fuzz"
+
+[rust.extra-toolchains.old]
+version = "1.70.0"
+"#;
+        let expected = ToolkitManifest::from_str(input).unwrap();
+        let extra = &expected.toolchain.extra_toolchains;
+        assert_eq!(
+            extra.get("nightly").unwrap(),
+            &ExtraToolchain {
+                channel: "nightly-2024-10-01".into(),
+                profile: Some("minimal".into()),
+                components: vec!["miri".into(), "rust-src".into()],
+                alias: Some("fuzz".into()),
+            }
+        );
+        assert_eq!(
+            extra.get("old").unwrap(),
+            &ExtraToolchain {
+                channel: "1.70.0".into(),
+                ..Default::default()
+            }
+        );
+
+        let without_extra = ToolkitManifest::from_str("[rust]\nversion = \"1.0.0\"").unwrap();
+        assert!(without_extra.toolchain.extra_toolchains.is_empty());
+        assert!(!without_extra
+            .to_toml()
+            .unwrap()
+            .contains("extra-toolchains"));
+    }
+
     #[test]
     fn detailed_profile() {
         let basic = r#"
@@ -835,4 +1152,41 @@ channel = "1.0.0"
         assert_eq!(registry.index, "https://www.example.com/index");
         assert!(expected.config.proxy.is_none());
     }
+
+    #[test]
+    fn rustup_init_with_checksum() {
+        let raw = r#"
+[toolchain]
+channel = "1.0.0"
+
+[toolchain.rustup]
+x86_64-pc-windows-msvc = "tools/rustup-init.exe"
+x86_64-unknown-linux-gnu = { path = "tools/rustup-init", sha256 = "abc" }
+aarch64-unknown-linux-gnu = { sha256 = "def" }
+"#;
+        let expected = ToolkitManifest::from_str(raw).unwrap();
+        let rustup = &expected.toolchain.rustup;
+
+        assert_eq!(
+            rustup.get("x86_64-pc-windows-msvc"),
+            Some(&RustupInit::Path("tools/rustup-init.exe".into()))
+        );
+        let linux = rustup.get("x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(linux.path(), Some("tools/rustup-init"));
+        assert_eq!(linux.sha256(), Some("abc"));
+        let aarch64 = rustup.get("aarch64-unknown-linux-gnu").unwrap();
+        assert_eq!(aarch64.path(), None);
+        assert_eq!(
+            expected
+                .toolchain
+                .rustup_init_sha256("aarch64-unknown-linux-gnu"),
+            Some("def")
+        );
+        assert_eq!(
+            expected
+                .toolchain
+                .rustup_init_sha256("x86_64-pc-windows-msvc"),
+            None
+        );
+    }
 }
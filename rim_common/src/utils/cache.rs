@@ -0,0 +1,294 @@
+//! A persistent cache of downloaded packages, shared across installations and updates.
+//!
+//! Packages are looked up by their url and sha256 checksum, thus only the ones with
+//! a checksum specified in the toolkit manifest are cached.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::dirs::rim_cache_dir;
+use crate::types::TomlParser;
+
+/// Information about a cached package, stored alongside with the package.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheEntry {
+    /// The url that the package was downloaded from.
+    pub url: String,
+    /// The sha256 checksum of the package.
+    pub sha256: String,
+    /// Name of the cached file.
+    pub filename: String,
+    /// Size of the cached file in bytes.
+    pub size: u64,
+    pub created: DateTime<Utc>,
+    pub last_used: DateTime<Utc>,
+    /// The directory containing this entry.
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl TomlParser for CacheEntry {
+    const FILENAME: &'static str = "entry.toml";
+}
+
+impl CacheEntry {
+    /// Path to the cached file.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(&self.filename)
+    }
+
+    fn load_from_entry_dir(dir: &Path) -> Result<Self> {
+        let mut entry = Self::load_from_dir(dir)?;
+        entry.dir = dir.to_path_buf();
+        Ok(entry)
+    }
+}
+
+/// A content-addressed cache of downloaded files, keyed by their url and checksum.
+///
+/// Only files with a known checksum are cached, because there is no way to tell
+/// whether the cached copy of an unchecksummed file is still up to date.
+///
+/// Each entry is stored in its own directory, named after the key,
+/// containing the cached file and a [`CacheEntry`] file describing it.
+#[derive(Debug, Clone)]
+pub struct DownloadCache {
+    root: PathBuf,
+}
+
+impl DownloadCache {
+    /// Create a cache that stores files under `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Open the default cache, which is located under [`rim_cache_dir`].
+    pub fn open() -> Self {
+        Self::new(rim_cache_dir().join("downloads"))
+    }
+
+    /// The directory where the cached files are stored.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the path of a cached file downloaded from `url`.
+    ///
+    /// The cached file will be verified against the `sha256` checksum,
+    /// and the entry will be discarded if it does not match.
+    pub fn get(&self, url: &Url, sha256: &str) -> Option<PathBuf> {
+        let dir = self.entry_dir(url, sha256);
+        let mut entry = CacheEntry::load_from_entry_dir(&dir).ok()?;
+        let path = entry.path();
+        if !path.is_file() {
+            return None;
+        }
+        if let Err(e) = super::verify_sha256(&path, sha256) {
+            debug!("discarding corrupted cache entry '{}': {e}", dir.display());
+            _ = super::remove(&dir);
+            return None;
+        }
+
+        entry.last_used = Utc::now();
+        if let Err(e) = entry.write_to_dir(&dir) {
+            debug!("unable to update cache entry '{}': {e}", dir.display());
+        }
+        Some(path)
+    }
+
+    /// Store a copy of `file` which was downloaded from `url` into the cache.
+    ///
+    /// The `file` will only be stored if it matches the `sha256` checksum.
+    pub fn insert(&self, url: &Url, sha256: &str, file: &Path) -> Result<CacheEntry> {
+        super::verify_sha256(file, sha256)?;
+        let filename = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("invalid file name of '{}'", file.display()))?;
+
+        let dir = self.entry_dir(url, sha256);
+        super::ensure_dir(&dir)?;
+        // copy to a temporary file first, so other processes never see a partial file
+        let partial = dir.join(format!("{filename}.partial"));
+        fs::copy(file, &partial).with_context(|| {
+            format!(
+                "unable to copy '{}' to cache directory '{}'",
+                file.display(),
+                dir.display()
+            )
+        })?;
+        let path = dir.join(filename);
+        fs::rename(&partial, &path)?;
+
+        let now = Utc::now();
+        let entry = CacheEntry {
+            url: url.to_string(),
+            sha256: normalize_checksum(sha256),
+            filename: filename.to_string(),
+            size: fs::metadata(&path)?.len(),
+            created: now,
+            last_used: now,
+            dir,
+        };
+        entry.write_to_dir(&entry.dir)?;
+        Ok(entry)
+    }
+
+    /// Get all the cache entries, with the most recently used ones come first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        if !self.root.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut entries = super::walk_dir(&self.root, false)?
+            .into_iter()
+            .filter_map(|dir| CacheEntry::load_from_entry_dir(&dir).ok())
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+        Ok(entries)
+    }
+
+    /// Remove everything in the cache, return the entries that were removed.
+    pub fn clean(&self) -> Result<Vec<CacheEntry>> {
+        self.remove_where(|_| true)
+    }
+
+    /// Remove the entries that haven't been used for the given duration,
+    /// return the entries that were removed.
+    ///
+    /// Broken entries, such as the ones left by interrupted downloads
+    /// or the ones without a checksum, will also be removed.
+    pub fn prune(&self, older_than: Duration) -> Result<Vec<CacheEntry>> {
+        let older_than = chrono::Duration::from_std(older_than)?;
+        let deadline = Utc::now()
+            .checked_sub_signed(older_than)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        self.remove_where(|entry| entry.last_used < deadline)
+    }
+
+    fn remove_where<F: Fn(&CacheEntry) -> bool>(&self, predicate: F) -> Result<Vec<CacheEntry>> {
+        if !self.root.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut removed = vec![];
+        for dir in super::walk_dir(&self.root, false)? {
+            match CacheEntry::load_from_entry_dir(&dir) {
+                Ok(entry) if predicate(&entry) => {
+                    super::remove(&dir)?;
+                    removed.push(entry);
+                }
+                Ok(_) => (),
+                Err(_) => super::remove(&dir)?,
+            }
+        }
+        Ok(removed)
+    }
+
+    fn entry_dir(&self, url: &Url, sha256: &str) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_str());
+        hasher.update("\n");
+        hasher.update(normalize_checksum(sha256));
+        self.root.join(format!("{:x}", hasher.finalize()))
+    }
+}
+
+/// Get the checksum value from the content of a `.sha256` file or a plain checksum.
+fn normalize_checksum(sha256: &str) -> String {
+    sha256
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_with_file(content: &str) -> (tempfile::TempDir, DownloadCache, PathBuf) {
+        let root = tempfile::tempdir().unwrap();
+        let cache = DownloadCache::new(root.path().join("cache"));
+        let file = root.path().join("a.zip");
+        fs::write(&file, content).unwrap();
+        (root, cache, file)
+    }
+
+    #[test]
+    fn cache_keyed_by_url_and_checksum() {
+        let (_root, cache, file) = cache_with_file("hello");
+        let url = Url::parse("https://example.com/a.zip").unwrap();
+        let sha256 = super::super::sha256_of(&file).unwrap();
+
+        assert!(cache.get(&url, &sha256).is_none());
+        cache.insert(&url, &sha256, &file).unwrap();
+
+        let cached = cache.get(&url, &sha256.to_uppercase()).unwrap();
+        assert_eq!(fs::read_to_string(cached).unwrap(), "hello");
+        let other = Url::parse("https://example.com/b.zip").unwrap();
+        assert!(cache.get(&other, &sha256).is_none());
+
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].filename, "a.zip");
+        assert_eq!(entries[0].size, 5);
+    }
+
+    #[test]
+    fn reject_mismatched_checksum() {
+        let (_root, cache, file) = cache_with_file("hello");
+        let url = Url::parse("https://example.com/a.zip").unwrap();
+        let sha256 = super::super::sha256_of(&file).unwrap();
+
+        assert!(cache.insert(&url, "abc", &file).is_err());
+        assert!(cache.entries().unwrap().is_empty());
+
+        // tampered files are discarded
+        let cached = cache.insert(&url, &sha256, &file).unwrap().path();
+        fs::write(cached, "world").unwrap();
+        assert!(cache.get(&url, &sha256).is_none());
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn prune_unused_entries() {
+        let (_root, cache, file) = cache_with_file("hello");
+        let old = Url::parse("https://example.com/old.zip").unwrap();
+        let new = Url::parse("https://example.com/new.zip").unwrap();
+
+        let sha256 = super::super::sha256_of(&file).unwrap();
+
+        let mut entry = cache.insert(&old, &sha256, &file).unwrap();
+        entry.last_used = Utc::now() - chrono::Duration::days(31);
+        entry.write_to_dir(&entry.dir).unwrap();
+        cache.insert(&new, &sha256, &file).unwrap();
+        // broken entry without metadata
+        fs::create_dir_all(cache.root().join("broken")).unwrap();
+        // entry without a checksum, which were cached by older versions
+        let unchecksummed = cache.root().join("unchecksummed");
+        fs::create_dir_all(&unchecksummed).unwrap();
+        fs::write(
+            unchecksummed.join(CacheEntry::FILENAME),
+            "url = \"https://example.com/a.zip\"\nfilename = \"a.zip\"\nsize = 5\n\
+            created = \"1970-01-01T00:00:00Z\"\nlast-used = \"2999-01-01T00:00:00Z\"\n",
+        )
+        .unwrap();
+
+        let removed = cache.prune(Duration::from_secs(30 * 24 * 3600)).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].url, old.as_str());
+        assert!(!cache.root().join("broken").exists());
+        assert!(!unchecksummed.exists());
+        assert_eq!(cache.entries().unwrap().len(), 1);
+
+        assert_eq!(cache.clean().unwrap().len(), 1);
+        assert!(cache.entries().unwrap().is_empty());
+    }
+}
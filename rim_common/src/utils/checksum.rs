@@ -0,0 +1,88 @@
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Calculate the sha256 checksum of a file, and return it as a lowercase hex string.
+pub fn sha256_of<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let file = fs::File::open(path)
+        .with_context(|| format!("unable to open '{}' to calculate checksum", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 8192];
+
+    loop {
+        let n = reader
+            .read(&mut buffer)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Verify the sha256 checksum of a file against an `expected` value.
+///
+/// The `expected` value is compared case-insensitively, and it may also be
+/// the content of a `.sha256` file, which looks like `<checksum> <filename>`,
+/// in which case only the first part will be compared.
+///
+/// Return the calculated checksum if matched.
+///
+/// # Error
+/// Return error if the file cannot be read or the checksum does not match.
+pub fn verify_sha256<P: AsRef<Path>>(path: P, expected: &str) -> Result<String> {
+    let path = path.as_ref();
+    let expected = expected
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual = sha256_of(path)?;
+
+    if actual != expected {
+        bail!(
+            "{}",
+            t!(
+                "checksum_mismatch",
+                path = path.display(),
+                expected = expected,
+                actual = actual
+            )
+        );
+    }
+    Ok(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sha256 of the string "hello world"
+    const HELLO_SHA256: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    #[test]
+    fn checksum_of_file() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        fs::write(temp.path(), "hello world").unwrap();
+
+        assert_eq!(sha256_of(temp.path()).unwrap(), HELLO_SHA256);
+    }
+
+    #[test]
+    fn verify_checksum() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        fs::write(temp.path(), "hello world").unwrap();
+
+        assert!(verify_sha256(temp.path(), HELLO_SHA256).is_ok());
+        assert!(verify_sha256(temp.path(), &HELLO_SHA256.to_uppercase()).is_ok());
+        // content of a `.sha256` file
+        assert!(verify_sha256(temp.path(), &format!("{HELLO_SHA256} hello.txt")).is_ok());
+        assert!(verify_sha256(temp.path(), "deadbeef").is_err());
+    }
+}
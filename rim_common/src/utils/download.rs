@@ -3,13 +3,13 @@ use std::path::Path;
 use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context, Result};
-use reqwest::{header, Client};
+use reqwest::{header, Client, StatusCode};
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use url::Url;
 
 use crate::types::Proxy as CrateProxy;
-use crate::utils::{ProgressHandler, ProgressKind};
+use crate::utils::{DownloadCache, ProgressHandler, ProgressKind};
 use crate::{build_config, setter};
 
 fn default_proxy() -> reqwest::Proxy {
@@ -28,6 +28,15 @@ pub struct DownloadOpt {
     pub proxy: Option<CrateProxy>,
     /// Whether or not to resuming previous download.
     resume: bool,
+    /// Policy of retrying failed downloads.
+    retry: RetryPolicy,
+    /// Base URLs of the mirrors to try when the server is unreachable.
+    mirrors: Vec<Url>,
+    /// The cache to look up before downloading, and to store the downloaded file in,
+    /// which is only used when the [`sha256`](Self::sha256) checksum is known.
+    cache: Option<DownloadCache>,
+    /// The expected sha256 checksum of the file, which is part of the cache key.
+    sha256: Option<String>,
 }
 
 impl DownloadOpt {
@@ -38,12 +47,20 @@ impl DownloadOpt {
             insecure: false,
             proxy: None,
             resume: false,
+            retry: RetryPolicy::default(),
+            mirrors: vec![],
+            cache: None,
+            sha256: None,
         }
     }
 
     setter!(with_proxy(self.proxy, Option<CrateProxy>));
     setter!(insecure(self.insecure, bool));
     setter!(resume(self.resume, bool));
+    setter!(with_retry(self.retry, RetryPolicy));
+    setter!(with_mirrors(self.mirrors, Vec<Url>));
+    setter!(with_cache(self.cache, Option<DownloadCache>));
+    setter!(with_sha256(self.sha256, Option<String>));
 
     /// Build and return a client for download
     fn client(&self) -> Result<Client> {
@@ -131,6 +148,14 @@ impl DownloadOpt {
     }
 
     /// Consume self, and download from given `Url` to `Path`.
+    ///
+    /// If a [`DownloadCache`] and a checksum were set, the file will be copied from the cache
+    /// if it was downloaded before, otherwise the downloaded file will be stored into the cache.
+    /// Files without a checksum are always downloaded, since a stale copy can't be detected.
+    ///
+    /// Failed downloads will be retried according to the [`RetryPolicy`],
+    /// then each of the mirrors will be tried in order, if there are any,
+    /// each of which downloads the whole file again.
     pub async fn download(mut self, url: &Url, path: &Path) -> Result<()> {
         if url.scheme() == "file" {
             let src = url
@@ -139,32 +164,147 @@ impl DownloadOpt {
             return self.copy(&src, path).await;
         }
 
+        let cached = self
+            .cache
+            .as_ref()
+            .zip(self.sha256.as_deref())
+            .and_then(|(cache, sha256)| cache.get(url, sha256));
+        if let Some(cached) = cached {
+            info!("{}", t!("download_cache_hit", file = &self.name));
+            return self.copy(&cached, path).await;
+        }
+
         if self.insecure {
             warn!("{}", t!("insecure_download"));
         }
 
-        let helper = DownloadHelper::new(&self.client()?, url, path, self.resume).await?;
+        let client = self.client()?;
+        let mut result = Ok(());
+        for (i, url) in self.candidate_urls(url).iter().enumerate() {
+            if i > 0 {
+                warn!(
+                    "{}",
+                    t!("download_try_mirror", file = &self.name, url = url)
+                );
+            }
+            // a partially downloaded file from another host might not be the same file,
+            // so downloads from mirrors always start from zero
+            let resume = self.resume && i == 0;
+            result = self.download_with_retries(&client, url, path, resume).await;
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        if let (Ok(()), Some(cache), Some(sha256)) = (&result, &self.cache, &self.sha256) {
+            if let Err(e) = cache.insert(url, sha256, path) {
+                warn!(
+                    "{}",
+                    t!(
+                        "download_cache_store_failed",
+                        file = &self.name,
+                        reason = format!("{e:#}")
+                    )
+                );
+            }
+        }
+        result
+    }
+
+    /// The `url` itself, followed by the same url under each mirror.
+    fn candidate_urls(&self, url: &Url) -> Vec<Url> {
+        let mut urls = vec![url.clone()];
+        for mirror in &self.mirrors {
+            match rebase_url(url, mirror) {
+                Ok(mirrored) if !urls.contains(&mirrored) => urls.push(mirrored),
+                Ok(_) => (),
+                Err(e) => debug!("skipping mirror '{mirror}': {e}"),
+            }
+        }
+        urls
+    }
+
+    async fn download_with_retries(
+        &mut self,
+        client: &Client,
+        url: &Url,
+        path: &Path,
+        mut resume: bool,
+    ) -> Result<()> {
+        let mut retried = 0;
+        loop {
+            let err = match self.download_once(client, url, path, resume).await {
+                Ok(()) => return Ok(()),
+                Err(Failure::Fatal(e)) => return Err(e),
+                Err(Failure::Transient(e)) => e,
+            };
+            if retried >= self.retry.retries {
+                return Err(err);
+            }
+
+            let delay = self.retry.delay(retried);
+            warn!(
+                "{}",
+                t!(
+                    "download_retry",
+                    file = &self.name,
+                    reason = format!("{err:#}"),
+                    secs = delay.as_secs_f32()
+                )
+            );
+            tokio::time::sleep(delay).await;
+            retried += 1;
+            // fatal errors were returned above, so this is a transient failure of the same host
+            resume = true;
+        }
+    }
+
+    async fn download_once(
+        &mut self,
+        client: &Client,
+        url: &Url,
+        path: &Path,
+        resume: bool,
+    ) -> Result<(), Failure> {
+        let timeout = self.retry.timeout;
+        let helper = DownloadHelper::new(client, url, path, resume, timeout).await?;
         let (mut resp, mut file, mut downloaded_bytes) =
             (helper.response, helper.file, helper.downloaded_bytes);
 
         let total_size = resp
             .content_length()
-            .ok_or_else(|| anyhow!("unable to get file length of '{url}'"))?;
+            .map(|len| len + downloaded_bytes)
+            .ok_or_else(|| Failure::Fatal(anyhow!("unable to get file length of '{url}'")))?;
 
-        self.progress_handler.start(
-            t!("downloading", file = &self.name).into(),
-            ProgressKind::Bytes(total_size),
-        )?;
+        self.progress_handler
+            .start(
+                t!("downloading", file = &self.name).into(),
+                ProgressKind::Bytes(total_size),
+            )
+            .map_err(Failure::Fatal)?;
+        self.progress_handler
+            .update(Some(downloaded_bytes))
+            .map_err(Failure::Fatal)?;
 
-        while let Some(chunk) = resp.chunk().await? {
-            file.write_all(&chunk).await?;
+        while let Some(chunk) = tokio::time::timeout(timeout, resp.chunk())
+            .await
+            .map_err(|_| Failure::Transient(anyhow!("timed out receiving data from '{url}'")))?
+            .map_err(|e| Failure::Transient(e.into()))?
+        {
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| Failure::Fatal(e.into()))?;
 
             downloaded_bytes = min(downloaded_bytes + chunk.len() as u64, total_size);
-            self.progress_handler.update(Some(downloaded_bytes))?;
+            self.progress_handler
+                .update(Some(downloaded_bytes))
+                .map_err(Failure::Fatal)?;
         }
+        file.flush().await.map_err(|e| Failure::Fatal(e.into()))?;
 
         self.progress_handler
-            .finish(t!("download_success", file = &self.name).into())?;
+            .finish(t!("download_success", file = &self.name).into())
+            .map_err(Failure::Fatal)?;
         Ok(())
     }
 
@@ -176,6 +316,62 @@ impl DownloadOpt {
     }
 }
 
+/// The reason why a download attempt failed.
+enum Failure {
+    /// Errors that might go away when retrying, such as network errors.
+    Transient(anyhow::Error),
+    /// Errors that won't go away no matter how many times it retries.
+    Fatal(anyhow::Error),
+}
+
+/// Policy of retrying a failed download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of times to retry after the first attempt failed.
+    pub retries: u32,
+    /// The delay before the first retry, which will be doubled after each retry.
+    pub backoff: Duration,
+    /// The maximum time to wait for the server to respond or to send more data.
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Get the delay before the `retried + 1`th retry.
+    fn delay(&self, retried: u32) -> Duration {
+        self.backoff.saturating_mul(2_u32.saturating_pow(retried))
+    }
+}
+
+/// Replace the origin of `url` with the `base` url,
+/// while keeping the path of `url` under the path of `base`.
+///
+/// For example, rebasing `https://example.com/dist/a.zip` on `https://mirror.com/rust/`
+/// results `https://mirror.com/rust/dist/a.zip`.
+fn rebase_url(url: &Url, base: &Url) -> Result<Url> {
+    if base.cannot_be_a_base() {
+        bail!("'{base}' cannot be used as a base url");
+    }
+    let mut rebased = base.clone();
+    let path = format!(
+        "{}/{}",
+        base.path().trim_end_matches('/'),
+        url.path().trim_start_matches('/')
+    );
+    rebased.set_path(&path);
+    rebased.set_query(url.query());
+    Ok(rebased)
+}
+
 struct DownloadHelper {
     response: reqwest::Response,
     file: fs::File,
@@ -184,38 +380,59 @@ struct DownloadHelper {
 }
 
 impl DownloadHelper {
-    async fn new_without_resume(client: &Client, url: &Url, path: &Path) -> Result<Self> {
-        let response = get_response_(client, url, None).await?;
-        let file = open_file_(path, true).await?;
-
-        Ok(Self {
-            response,
-            file,
-            downloaded_bytes: 0,
-        })
-    }
-
-    async fn new(client: &Client, url: &Url, path: &Path, resume: bool) -> Result<Self> {
+    async fn new(
+        client: &Client,
+        url: &Url,
+        path: &Path,
+        resume: bool,
+        timeout: Duration,
+    ) -> Result<Self, Failure> {
         let (downloaded_bytes, file) = if resume {
-            let file = open_file_(path, false).await?;
-            let downloaded = file.metadata().await?.len();
+            let file = open_file_(path, false).await.map_err(Failure::Fatal)?;
+            let downloaded = file
+                .metadata()
+                .await
+                .map_err(|e| Failure::Fatal(e.into()))?
+                .len();
             (downloaded, file)
         } else {
-            (0, open_file_(path, true).await?)
+            (0, open_file_(path, true).await.map_err(Failure::Fatal)?)
         };
 
-        // resume from the next of downloaded byte
-        let resume_from = (downloaded_bytes != 0).then_some(downloaded_bytes + 1);
-        let response = get_response_(client, url, resume_from).await?;
+        // ranges are zero-indexed, so this is the index of the next byte to download
+        let resume_from = (downloaded_bytes != 0).then_some(downloaded_bytes);
+        let response = tokio::time::timeout(timeout, get_response_(client, url, resume_from))
+            .await
+            .map_err(|_| Failure::Transient(anyhow!("timed out connecting to '{url}'")))?
+            .map_err(Failure::Transient)?;
 
         let status = response.status();
-        if status == 416 {
-            // 416: server does not support download range, retry without resuming
+        if status == StatusCode::RANGE_NOT_SATISFIABLE && resume_from.is_some() {
+            // the server does not support download range, or the local file is corrupted,
+            // retry without resuming
             info!("download range not satisfiable, retrying without ranges header");
-
-            return Self::new_without_resume(client, url, path).await;
+            return Box::pin(Self::new(client, url, path, false, timeout)).await;
+        } else if status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS
+        {
+            return Err(Failure::Transient(anyhow!(
+                "server returns error when attempting download from '{url}': {status}"
+            )));
         } else if !status.is_success() {
-            bail!("server returns error when attempting download from '{url}': {status}");
+            return Err(Failure::Fatal(anyhow!(
+                "server returns error when attempting download from '{url}': {status}"
+            )));
+        }
+
+        if resume_from.is_some() && status != StatusCode::PARTIAL_CONTENT {
+            // the server ignored the range and sent the whole file, start over
+            let file = open_file_(path, true).await.map_err(Failure::Fatal)?;
+            return Ok(Self {
+                response,
+                file,
+                downloaded_bytes: 0,
+            });
         }
 
         Ok(Self {
@@ -226,13 +443,16 @@ impl DownloadHelper {
     }
 }
 
+/// Open a file to write, the file will be truncated if `truncate` is `true`,
+/// otherwise the content will be appended to it.
 async fn open_file_(path: &Path, truncate: bool) -> Result<fs::File> {
-    Ok(fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(truncate)
-        .open(path)
-        .await?)
+    let mut options = fs::OpenOptions::new();
+    if truncate {
+        options.write(true).truncate(true);
+    } else {
+        options.append(true);
+    }
+    Ok(options.create(true).open(path).await?)
 }
 
 async fn get_response_(
@@ -249,3 +469,58 @@ async fn get_response_(
     })?;
     Ok(resp)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebase_url_on_mirrors() {
+        let url = Url::parse("https://example.com/dist/tools/a.zip?v=1").unwrap();
+
+        let mirror = Url::parse("https://mirror.com").unwrap();
+        assert_eq!(
+            rebase_url(&url, &mirror).unwrap().as_str(),
+            "https://mirror.com/dist/tools/a.zip?v=1"
+        );
+        let mirror = Url::parse("http://mirror.com:8080/rust/").unwrap();
+        assert_eq!(
+            rebase_url(&url, &mirror).unwrap().as_str(),
+            "http://mirror.com:8080/rust/dist/tools/a.zip?v=1"
+        );
+        let mirror = Url::parse("mailto:someone@example.com").unwrap();
+        assert!(rebase_url(&url, &mirror).is_err());
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy::default();
+        let delays = (0..4)
+            .map(|i| policy.delay(i).as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn candidate_urls_with_mirrors() {
+        let url = Url::parse("https://example.com/a.zip").unwrap();
+        let opt = DownloadOpt::new("a", Box::new(crate::utils::HiddenProgress)).with_mirrors(vec![
+            Url::parse("https://mirror-a.com").unwrap(),
+            Url::parse("https://example.com").unwrap(),
+            Url::parse("https://mirror-b.com/rust").unwrap(),
+        ]);
+        let urls = opt
+            .candidate_urls(&url)
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/a.zip",
+                "https://mirror-a.com/a.zip",
+                "https://mirror-b.com/rust/a.zip",
+            ]
+        );
+    }
+}
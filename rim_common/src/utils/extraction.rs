@@ -1,10 +1,11 @@
 use anyhow::{anyhow, bail, Result};
+use bzip2::read::BzDecoder;
 use flate2::read::GzDecoder;
-use sevenz_rust::{Password, SevenZReader};
+use sevenz_rust::{Password, SevenZArchiveEntry, SevenZReader};
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
 use xz2::read::XzDecoder;
 use zip::ZipArchive;
 
@@ -14,94 +15,284 @@ use crate::utils::{ProgressHandler, ProgressKind};
 use super::file_system::{ensure_dir, ensure_parent_dir, walk_dir};
 use super::progress_bar::CliProgress;
 
+/// Size of a tar header block, which has the `ustar` magic at offset 257.
+const TAR_BLOCK_SIZE: usize = 512;
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Compression formats that are used for tarballs or single files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    /// Not compressed, only applicable for tarballs, ended with `.tar`
+    None,
+    /// `gzip` compressed files, ended with `.gz`, `.tgz` or `.crate`
+    Gz,
+    /// `xz` compressed files, ended with `.xz` or `.txz`
+    Xz,
+    /// `bzip2` compressed files, ended with `.bz2`, `.tbz` or `.tbz2`
+    Bz2,
+    /// `zstd` compressed files, ended with `.zst` or `.tzst`
+    Zst,
+}
+
+impl Compression {
+    fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "gz" => Some(Self::Gz),
+            "xz" => Some(Self::Xz),
+            "bz2" => Some(Self::Bz2),
+            "zst" => Some(Self::Zst),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::None => "tar",
+            Self::Gz => "gz",
+            Self::Xz => "xz",
+            Self::Bz2 => "bz2",
+            Self::Zst => "zst",
+        }
+    }
+
+    /// Wrap a `reader` with the decoder of this compression format.
+    fn decoder<'r, R: Read + 'r>(&self, reader: R) -> Result<Box<dyn Read + 'r>> {
+        Ok(match self {
+            Self::None => Box::new(reader),
+            Self::Gz => Box::new(GzDecoder::new(reader)),
+            Self::Xz => Box::new(XzDecoder::new(reader)),
+            Self::Bz2 => Box::new(BzDecoder::new(reader)),
+            Self::Zst => Box::new(zstd::Decoder::new(reader)?),
+        })
+    }
+}
+
 enum ExtractableKind {
     /// `7-zip` compressed files, ended with `.7z`
-    SevenZ(SevenZReader<File>),
-    Gz(tar::Archive<GzDecoder<File>>),
-    Xz(tar::Archive<XzDecoder<File>>),
+    SevenZ(Box<SevenZReader<File>>),
     Zip(ZipArchive<File>),
+    /// Tarballs, either compressed or not, such as `.tar`, `.tar.gz`, `.tar.zst` etc.
+    Tar(Compression),
+    /// A single compressed file that is not a tarball, such as `foo.gz`
+    SingleFile(Compression),
+}
+
+/// Archive formats before opening, see [`ArchiveFormat::detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    SevenZ,
+    Zip,
+    Tar(Compression),
+    /// Compressed file that could either be a tarball or a single file,
+    /// which can only be told after decompressing its first few bytes.
+    Compressed(Compression),
+}
+
+impl ArchiveFormat {
+    /// Get the format of `path` base on its file name, such as `foo.tar.gz`, `foo.tgz`.
+    ///
+    /// If `custom_ext` was provided, it will be used as the file extension instead.
+    fn from_file_name(path: &Path, custom_ext: Option<&str>) -> Option<Self> {
+        let ext = match custom_ext {
+            Some(custom) => custom.to_string(),
+            None => path.extension()?.to_str()?.to_ascii_lowercase(),
+        };
+        let format = match ext.as_str() {
+            "7z" => Self::SevenZ,
+            "zip" => Self::Zip,
+            "tar" => Self::Tar(Compression::None),
+            "tgz" | "crate" => Self::Tar(Compression::Gz),
+            "txz" => Self::Tar(Compression::Xz),
+            "tbz" | "tbz2" => Self::Tar(Compression::Bz2),
+            "tzst" => Self::Tar(Compression::Zst),
+            other => {
+                let compression = Compression::from_extension(other)?;
+                let is_tar_stem = custom_ext.is_none()
+                    && path
+                        .file_stem()
+                        .and_then(|stem| Path::new(stem).extension())
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("tar"));
+                if is_tar_stem {
+                    Self::Tar(compression)
+                } else {
+                    Self::Compressed(compression)
+                }
+            }
+        };
+        Some(format)
+    }
+
+    /// Get the format of `path` base on the magic bytes at the start of it.
+    fn from_magic_bytes(path: &Path) -> Result<Option<Self>> {
+        let mut header = Vec::with_capacity(TAR_BLOCK_SIZE);
+        File::open(path)?
+            .take(TAR_BLOCK_SIZE as u64)
+            .read_to_end(&mut header)?;
+
+        let format = if header.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+            Self::SevenZ
+        } else if header.starts_with(b"PK\x03\x04") {
+            Self::Zip
+        } else if header.starts_with(&[0x1F, 0x8B]) {
+            Self::Compressed(Compression::Gz)
+        } else if header.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+            Self::Compressed(Compression::Xz)
+        } else if header.starts_with(b"BZh") {
+            Self::Compressed(Compression::Bz2)
+        } else if header.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            Self::Compressed(Compression::Zst)
+        } else if has_tar_magic(&header) {
+            Self::Tar(Compression::None)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(format))
+    }
+
+    /// Detect the format of `path`, base on its file name, or the magic bytes of it
+    /// if the file name does not have an extension.
+    ///
+    /// Note that the magic bytes were not checked for files with unknown extensions,
+    /// because some packages are zip archives with custom extensions (such as `.vsix`)
+    /// which should not be extracted.
+    fn detect(path: &Path, custom_ext: Option<&str>) -> Result<Self> {
+        let format = if custom_ext.is_some() || path.extension().is_some() {
+            Self::from_file_name(path, custom_ext)
+        } else {
+            Self::from_magic_bytes(path)?
+        };
+        let Some(format) = format else {
+            bail!(
+                "'{}' is not a supported extractable file format",
+                path.display()
+            );
+        };
+
+        // decompress the first block to see if it's a tarball
+        if let Self::Compressed(compression) = format {
+            let mut header = Vec::with_capacity(TAR_BLOCK_SIZE);
+            compression
+                .decoder(File::open(path)?)?
+                .take(TAR_BLOCK_SIZE as u64)
+                .read_to_end(&mut header)?;
+            if has_tar_magic(&header) {
+                return Ok(Self::Tar(compression));
+            }
+        }
+        Ok(format)
+    }
+}
+
+fn has_tar_magic(header: &[u8]) -> bool {
+    header
+        .get(TAR_MAGIC_OFFSET..)
+        .is_some_and(|magic| magic.starts_with(b"ustar"))
+}
+
+/// Limits of an extraction, which prevent malicious archives (such as zip bombs)
+/// from exhausting the disk space.
+#[derive(Debug, Clone, Copy, Default)]
+struct ExtractLimits {
+    /// Maximum number of bytes to write.
+    max_size: Option<u64>,
+    /// Maximum number of entries to extract, including directories.
+    max_entries: Option<u64>,
 }
 
 pub struct Extractable<'a> {
     path: &'a Path,
     kind: ExtractableKind,
     quiet: bool,
+    limits: ExtractLimits,
 }
 
 impl<'a> Extractable<'a> {
+    /// Check if `path` is a supported archive or compressed file.
+    ///
+    /// The magic bytes will only be checked for existing files without an extension,
+    /// check [`load`](Extractable::load) for more details.
     pub fn is_supported(path: &'a Path) -> bool {
-        let Ok(extensions) = file_extension(path) else {
-            return false;
-        };
-        matches!(extensions, "7z" | "zip" | "gz" | "xz" | "crate")
+        if path.extension().is_some() {
+            ArchiveFormat::from_file_name(path, None).is_some()
+        } else {
+            path.is_file() && matches!(ArchiveFormat::from_magic_bytes(path), Ok(Some(_)))
+        }
     }
 
+    /// Load a file to extract, its format was detected by the file name, or by the magic bytes
+    /// of it if it does not have an extension.
+    ///
+    /// Supported formats are `.7z`, `.zip`, `.crate`, tarballs (such as `.tar`, `.tar.gz`,
+    /// `.tar.xz`, `.tar.bz2`, `.tar.zst`), and single compressed files
+    /// (such as `.gz`, `.xz`, `.bz2`, `.zst`).
+    ///
+    /// `custom_kind` can be used to specify the extension of the file, for example `Some("gz")`.
     pub fn load(path: &'a Path, custom_kind: Option<&str>) -> Result<Self> {
-        let ext = if let Some(custom) = custom_kind {
-            custom
-        } else {
-            file_extension(path)?
+        let format = ArchiveFormat::detect(path, custom_kind)?;
+        let kind_name = match format {
+            ArchiveFormat::SevenZ => "7z",
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar(c) | ArchiveFormat::Compressed(c) => c.extension(),
         };
+        info!(
+            "{}",
+            t!(
+                "loading_archive_info",
+                kind = kind_name,
+                path = path.display()
+            )
+        );
 
-        let kind = match ext {
-            "7z" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
-                );
-                ExtractableKind::SevenZ(SevenZReader::open(path, Password::empty())?)
-            }
-            "zip" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
-                );
-                ExtractableKind::Zip(ZipArchive::new(File::open(path)?)?)
-            }
-            "gz" | "crate" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
-                );
-                let tar_gz = GzDecoder::new(File::open(path)?);
-                ExtractableKind::Gz(tar::Archive::new(tar_gz))
-            }
-            "xz" => {
-                info!(
-                    "{}",
-                    t!("loading_archive_info", kind = ext, path = path.display())
-                );
-                let tar_xz = XzDecoder::new(File::open(path)?);
-                ExtractableKind::Xz(tar::Archive::new(tar_xz))
+        let kind = match format {
+            ArchiveFormat::SevenZ => {
+                ExtractableKind::SevenZ(Box::new(SevenZReader::open(path, Password::empty())?))
             }
-            _ => bail!("'{ext}' is not a supported extractable file format"),
+            ArchiveFormat::Zip => ExtractableKind::Zip(ZipArchive::new(File::open(path)?)?),
+            ArchiveFormat::Tar(compression) => ExtractableKind::Tar(compression),
+            ArchiveFormat::Compressed(compression) => ExtractableKind::SingleFile(compression),
         };
 
         Ok(Self {
             path,
             kind,
             quiet: false,
+            limits: ExtractLimits::default(),
         })
     }
 
     setter!(quiet(self.quiet, bool));
+    setter!(max_size(self.limits.max_size, size: u64) { Some(size) });
+    setter!(max_entries(self.limits.max_entries, count: u64) { Some(count) });
 
     /// Extract current file into a specific directory.
     ///
     /// This will extract file under the `root`, make sure it's an empty folder before using this function.
+    ///
+    /// Entries that would be placed outside of `root` are rejected, such as the ones with
+    /// absolute paths, `..` components, or the ones that go through symlinks pointing elsewhere,
+    /// as well as the symlinks pointing outside of `root`. The extraction also fails
+    /// once it exceeds the limits set by [`max_size`](Extractable::max_size)
+    /// or [`max_entries`](Extractable::max_entries).
     pub fn extract_to(&mut self, root: &Path) -> Result<()> {
+        ensure_dir(root)?;
         let mut helper = ExtractHelper {
             file_path: self.path,
             output_dir: root,
             handler: CliProgress::default(),
+            limits: self.limits,
+            extracted_entries: 0,
+            extracted_size: 0,
+            symlinks: vec![],
+            dir_modes: vec![],
         };
 
         match &mut self.kind {
-            ExtractableKind::Zip(archive) => helper.extract_zip(archive),
-            ExtractableKind::SevenZ(archive) => helper.extract_7z(archive),
-            ExtractableKind::Gz(archive) => helper.extract_tar(archive),
-            ExtractableKind::Xz(archive) => helper.extract_tar(archive),
+            ExtractableKind::Zip(archive) => helper.extract_zip(archive)?,
+            ExtractableKind::SevenZ(archive) => helper.extract_7z(archive)?,
+            ExtractableKind::Tar(compression) => helper.extract_tar(*compression)?,
+            ExtractableKind::SingleFile(compression) => helper.extract_single_file(*compression)?,
         }
+        helper.finalize()
     }
 
     /// Extract file into a specific root like [`extract_to`](Extractable::extract_to),
@@ -160,24 +351,6 @@ impl<'a> Extractable<'a> {
     }
 }
 
-fn file_extension(path: &Path) -> Result<&str> {
-    path.extension()
-        .ok_or_else(|| {
-            anyhow!(
-                "'{}' is not extractable because it appears to have no file extension",
-                path.display()
-            )
-        })?
-        .to_str()
-        .ok_or_else(|| {
-            anyhow!(
-                "'{}' is not extractable because its extension contains \
-                invalid unicode characters",
-                path.display()
-            )
-        })
-}
-
 fn filename_matches_keyword<S: AsRef<OsStr>>(path: &Path, keyword: S) -> bool {
     if let Some(name) = path.file_name() {
         name == keyword.as_ref()
@@ -186,14 +359,30 @@ fn filename_matches_keyword<S: AsRef<OsStr>>(path: &Path, keyword: S) -> bool {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Maximum number of symlinks to follow when resolving a path, prevents symlink loops.
+const MAX_SYMLINK_FOLLOWS: usize = 40;
+
+/// Bits of the file type in a unix mode, and the value of it for symlinks.
+const UNIX_FILE_TYPE_MASK: u32 = 0o170000;
+const UNIX_SYMLINK: u32 = 0o120000;
+
+#[derive(Debug)]
 struct ExtractHelper<'a, T: ProgressHandler> {
     file_path: &'a Path,
     output_dir: &'a Path,
     handler: T,
+    limits: ExtractLimits,
+    extracted_entries: u64,
+    extracted_size: u64,
+    /// Symlinks that were created, relative to the output directory,
+    /// which will be checked again after all entries were extracted.
+    symlinks: Vec<PathBuf>,
+    /// Unix modes of the extracted directories, which are applied after all entries
+    /// were extracted, so that read-only directories won't block their own entries.
+    dir_modes: Vec<(PathBuf, u32)>,
 }
 
-impl<T: ProgressHandler> ExtractHelper<'_, T> {
+impl<T: ProgressHandler + Clone + 'static> ExtractHelper<'_, T> {
     fn start_progress_bar(&mut self, style: ProgressKind) -> Result<()> {
         self.handler.start(
             format!("extracting file '{}'", self.file_path.display()),
@@ -218,27 +407,18 @@ impl<T: ProgressHandler> ExtractHelper<'_, T> {
 
         for i in 0..zip_len {
             let mut zip_file = archive.by_index(i)?;
-            let Some(out_path) = zip_file
-                .enclosed_name()
-                .map(|path| self.output_dir.join(path))
-            else {
-                continue;
-            };
+            self.count_entry()?;
+            let name = PathBuf::from(zip_file.name());
+            let mode = zip_file.unix_mode();
 
             if zip_file.is_dir() {
-                ensure_dir(&out_path)?;
+                self.create_dir(&name, mode)?;
+            } else if zip_file.is_symlink() {
+                let mut target = String::new();
+                zip_file.read_to_string(&mut target)?;
+                self.create_symlink(&name, Path::new(&target))?;
             } else {
-                ensure_parent_dir(&out_path)?;
-                let mut out_file = std::fs::File::create(&out_path)?;
-                std::io::copy(&mut zip_file, &mut out_file)?;
-            }
-
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Some(mode) = zip_file.unix_mode() {
-                    std::fs::set_permissions(&out_path, std::fs::Permissions::from_mode(mode))?;
-                }
+                self.create_file(&name, &mut zip_file, mode)?;
             }
 
             self.update_progress_bar(Some(i.try_into()?))?;
@@ -254,66 +434,388 @@ impl<T: ProgressHandler> ExtractHelper<'_, T> {
             .iter()
             .filter_map(|e| e.has_stream().then_some(e.size()))
             .sum();
-        let mut extracted_len: u64 = 0;
 
         // Init progress bar
         self.start_progress_bar(ProgressKind::Bytes(sz_len))?;
 
+        // keep the original error, then stop the iteration by returning `false`
+        let mut error = None;
         archive.for_each_entries(|entry, reader| {
-            let mut buf = [0_u8; 1024];
-            let entry_path = PathBuf::from(entry.name());
-            let out_path = self.output_dir.join(&entry_path);
-
-            if entry.is_directory() {
-                ensure_dir(&out_path).map_err(|_| {
-                    sevenz_rust::Error::other(format!(
-                        "unable to create entry directory '{}'",
-                        out_path.display()
-                    ))
-                })?;
-                Ok(true)
-            } else {
-                ensure_parent_dir(&out_path).map_err(|_| {
-                    sevenz_rust::Error::other(format!(
-                        "unable to create parent directory for '{}'",
-                        out_path.display()
-                    ))
-                })?;
-
-                let mut out_file = std::fs::File::create(&out_path)?;
-                loop {
-                    let read_size = reader.read(&mut buf)?;
-                    if read_size == 0 {
-                        break Ok(true);
-                    }
-                    out_file.write_all(&buf[..read_size])?;
-                    extracted_len += read_size as u64;
-                    // Update progress bar
-                    self.update_progress_bar(Some(extracted_len))
-                        .map_err(|anyhow_err| {
-                            sevenz_rust::Error::Other(anyhow_err.to_string().into())
-                        })?;
+            let res = self
+                .extract_7z_entry(entry, reader)
+                // Update progress bar
+                .and_then(|_| self.update_progress_bar(Some(self.extracted_size)));
+            match res {
+                Ok(()) => Ok(true),
+                Err(e) => {
+                    error = Some(e);
+                    Ok(false)
                 }
             }
         })?;
+        if let Some(e) = error {
+            return Err(e);
+        }
 
         self.end_progress_bar()?;
         Ok(())
     }
 
-    fn extract_tar<R: Read>(&mut self, archive: &mut tar::Archive<R>) -> Result<()> {
-        #[cfg(unix)]
-        archive.set_preserve_permissions(true);
+    fn extract_7z_entry(
+        &mut self,
+        entry: &SevenZArchiveEntry,
+        reader: &mut dyn Read,
+    ) -> Result<()> {
+        if entry.is_anti_item() {
+            return Ok(());
+        }
+        self.count_entry()?;
+        let name = PathBuf::from(entry.name());
+        // 7z stores unix modes in the high 16 bits of the attributes,
+        // which is indicated by the `FILE_ATTRIBUTE_UNIX_EXTENSION` flag.
+        let mode = (entry.has_windows_attributes && entry.windows_attributes() & 0x8000 != 0)
+            .then(|| entry.windows_attributes() >> 16);
+
+        if entry.is_directory() {
+            self.create_dir(&name, mode)
+        } else if mode.is_some_and(|m| m & UNIX_FILE_TYPE_MASK == UNIX_SYMLINK) {
+            let mut target = String::new();
+            reader.read_to_string(&mut target)?;
+            self.create_symlink(&name, Path::new(&target))
+        } else {
+            self.create_file(&name, reader, mode)
+        }
+    }
 
-        // Init progress bar, use spinner because the length of entries cannot be retrieved.
-        self.start_progress_bar(ProgressKind::Spinner {
-            auto_tick_duration: Some(std::time::Duration::from_millis(100)),
-        })?;
+    /// Open the file to extract with a decoder of `compression`,
+    /// and start a progress bar that goes with the number of (compressed) bytes read.
+    fn open_with_progress(&mut self, compression: Compression) -> Result<Box<dyn Read>> {
+        let file = File::open(self.file_path)?;
+        let len = file.metadata()?.len();
+        self.start_progress_bar(ProgressKind::Bytes(len))?;
+
+        let reader = ProgressReader {
+            inner: file,
+            handler: self.handler.clone(),
+            read: 0,
+        };
+        compression.decoder(reader)
+    }
 
-        archive.unpack(self.output_dir)?;
+    fn extract_tar(&mut self, compression: Compression) -> Result<()> {
+        let mut archive = tar::Archive::new(self.open_with_progress(compression)?);
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            self.count_entry()?;
+            let name = entry.path()?.into_owned();
+            let entry_type = entry.header().entry_type();
+
+            if entry_type.is_dir() {
+                self.create_dir(&name, entry.header().mode().ok())?;
+            } else if entry_type.is_symlink() || entry_type.is_hard_link() {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| anyhow!("link '{}' in archive has no target", name.display()))?;
+                if entry_type.is_symlink() {
+                    self.create_symlink(&name, &target)?;
+                } else {
+                    self.create_hard_link(&name, &target)?;
+                }
+            } else if entry_type.is_file()
+                || entry_type.is_contiguous()
+                || entry_type.is_gnu_sparse()
+            {
+                // the size in header is not what will be written for sparse files,
+                // the bytes are counted while writing instead
+                let mode = entry.header().mode().ok();
+                self.create_file(&name, &mut entry, mode)?;
+            } else {
+                debug!("skipping unsupported archive entry '{}'", name.display());
+            }
+        }
 
         // Stop progress bar's progress
         self.end_progress_bar()?;
         Ok(())
     }
+
+    /// Decompress a single file into the output directory, with the compression extension
+    /// removed from its name, e.g. `foo.gz` will be extracted as `foo`.
+    fn extract_single_file(&mut self, compression: Compression) -> Result<()> {
+        let file_name = match self.file_path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case(compression.extension()) => {
+                self.file_path.file_stem()
+            }
+            _ => self.file_path.file_name(),
+        }
+        .ok_or_else(|| anyhow!("invalid file path '{}'", self.file_path.display()))?;
+
+        self.count_entry()?;
+        let mut reader = self.open_with_progress(compression)?;
+        self.create_file(Path::new(file_name), &mut reader, None)?;
+
+        self.end_progress_bar()?;
+        Ok(())
+    }
+
+    /// Verify the extracted symlinks again, because a symlink that was safe when it
+    /// was created might be redirected by the entries extracted after it,
+    /// then apply the modes of extracted directories.
+    fn finalize(&self) -> Result<()> {
+        for link in &self.symlinks {
+            if resolve_within(self.output_dir, link).is_none() {
+                let path = self.output_dir.join(link);
+                std::fs::remove_file(&path)?;
+                bail!(
+                    "symlink '{}' in archive points outside of the extraction directory",
+                    link.display()
+                );
+            }
+        }
+
+        for (dir, mode) in self.dir_modes.iter().rev() {
+            set_unix_mode(dir, *mode)?;
+        }
+        Ok(())
+    }
+
+    fn count_entry(&mut self) -> Result<()> {
+        self.extracted_entries += 1;
+        if let Some(max) = self.limits.max_entries {
+            if self.extracted_entries > max {
+                bail!(
+                    "'{}' contains more than {max} entries, which exceeds the extraction limit",
+                    self.file_path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn add_size(&mut self, size: u64) -> Result<()> {
+        self.extracted_size = self.extracted_size.saturating_add(size);
+        if let Some(max) = self.limits.max_size {
+            if self.extracted_size > max {
+                bail!(
+                    "extracted content of '{}' is larger than {max} bytes, \
+                    which exceeds the extraction limit",
+                    self.file_path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the path to extract an entry named `name` to.
+    ///
+    /// The `name` must be a relative path without `..` components, and its parent
+    /// must stays in the output directory, even after resolving the symlinks
+    /// that were already extracted.
+    fn checked_out_path(&self, name: &Path) -> Result<PathBuf> {
+        let rel = sanitized_entry_name(name)?;
+        let Some(parent) = rel.parent() else {
+            return Ok(self.output_dir.to_path_buf());
+        };
+        let (Some(resolved_parent), Some(file_name)) =
+            (resolve_within(self.output_dir, parent), rel.file_name())
+        else {
+            bail!(
+                "archive entry '{}' points outside of the extraction directory",
+                name.display()
+            );
+        };
+        Ok(resolved_parent.join(file_name))
+    }
+
+    fn create_dir(&mut self, name: &Path, mode: Option<u32>) -> Result<()> {
+        let out_path = self.checked_out_path(name)?;
+        ensure_dir(&out_path)?;
+        if let Some(mode) = mode {
+            self.dir_modes.push((out_path, mode));
+        }
+        Ok(())
+    }
+
+    fn create_file<R: Read + ?Sized>(
+        &mut self,
+        name: &Path,
+        reader: &mut R,
+        mode: Option<u32>,
+    ) -> Result<()> {
+        let out_path = self.checked_out_path(name)?;
+        prepare_file_path(&out_path)?;
+        let mut out_file = File::create(&out_path)?;
+
+        // read one more byte than the remaining size, so we can tell if it exceeds the limit
+        let written = match self.limits.max_size {
+            Some(max) => {
+                let remaining = max.saturating_sub(self.extracted_size);
+                io::copy(&mut reader.take(remaining.saturating_add(1)), &mut out_file)?
+            }
+            None => io::copy(reader, &mut out_file)?,
+        };
+        self.add_size(written)?;
+
+        if let Some(mode) = mode {
+            set_unix_mode(&out_path, mode)?;
+        }
+        Ok(())
+    }
+
+    /// Create a symlink to `target`, which must be a relative path that
+    /// resolves to somewhere inside of the output directory.
+    fn create_symlink(&mut self, name: &Path, target: &Path) -> Result<()> {
+        let out_path = self.checked_out_path(name)?;
+        let rel = sanitized_entry_name(name)?;
+        let resolved_target = rel
+            .parent()
+            .and_then(|parent| resolve_within(self.output_dir, &parent.join(target)));
+        if target.has_root() || resolved_target.is_none() {
+            bail!(
+                "symlink '{}' in archive points outside of the extraction directory",
+                name.display()
+            );
+        }
+
+        prepare_file_path(&out_path)?;
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(target, &out_path)?;
+            self.symlinks.push(rel);
+        }
+        #[cfg(not(unix))]
+        warn!(
+            "skipping symlink '{}' in archive, because it is not supported on this platform",
+            name.display()
+        );
+
+        Ok(())
+    }
+
+    /// Create a hard link to `target`, which is a path relative to the output directory,
+    /// and must be resolved to somewhere inside of it.
+    fn create_hard_link(&mut self, name: &Path, target: &Path) -> Result<()> {
+        let out_path = self.checked_out_path(name)?;
+        let Some(source) = resolve_within(self.output_dir, &sanitized_entry_name(target)?) else {
+            bail!(
+                "hard link '{}' in archive points outside of the extraction directory",
+                name.display()
+            );
+        };
+
+        prepare_file_path(&out_path)?;
+        std::fs::hard_link(source, out_path)?;
+        Ok(())
+    }
+}
+
+/// Make sure an archive entry's `name` is a relative path without any `..` component,
+/// and return it without `.` components.
+fn sanitized_entry_name(name: &Path) -> Result<PathBuf> {
+    let mut rel = PathBuf::new();
+    for comp in name.components() {
+        match comp {
+            Component::Normal(part) => rel.push(part),
+            Component::CurDir => (),
+            _ => bail!(
+                "archive entry '{}' points outside of the extraction directory",
+                name.display()
+            ),
+        }
+    }
+    Ok(rel)
+}
+
+/// Resolve `rel`, which is a path relative to `root`, into a path under `root`,
+/// following the symlinks along the way.
+///
+/// Return `None` if the path goes outside of `root` at any point,
+/// or it contains too many levels of symlinks.
+fn resolve_within(root: &Path, rel: &Path) -> Option<PathBuf> {
+    fn components_of(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+        path.components()
+            .rev()
+            .map(|c| PathBuf::from(c.as_os_str()))
+    }
+
+    let mut resolved = root.to_path_buf();
+    let mut pending = components_of(rel).collect::<Vec<_>>();
+    let mut links_followed = 0;
+
+    while let Some(part) = pending.pop() {
+        match part.components().next() {
+            None | Some(Component::CurDir) => (),
+            Some(Component::ParentDir) => {
+                if resolved == root {
+                    return None;
+                }
+                resolved.pop();
+            }
+            Some(Component::Normal(name)) => {
+                resolved.push(name);
+                let is_symlink = resolved
+                    .symlink_metadata()
+                    .is_ok_and(|meta| meta.file_type().is_symlink());
+                if is_symlink {
+                    links_followed += 1;
+                    if links_followed > MAX_SYMLINK_FOLLOWS {
+                        return None;
+                    }
+                    let target = std::fs::read_link(&resolved).ok()?;
+                    resolved.pop();
+                    pending.extend(components_of(&target));
+                }
+            }
+            // absolute paths
+            Some(Component::RootDir | Component::Prefix(_)) => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Create the parent directory of `path`, and remove the existing file or symlink of it,
+/// so that the new entry won't be written to where the old symlink points to.
+fn prepare_file_path(path: &Path) -> Result<()> {
+    ensure_parent_dir(path)?;
+    if path
+        .symlink_metadata()
+        .is_ok_and(|meta| !meta.file_type().is_dir())
+    {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Set the permissions of `path` base on a unix `mode`, only the permission bits are kept,
+/// the file type and the special bits (setuid, setgid and sticky) in it are ignored.
+///
+/// This does nothing on non-unix platforms.
+fn set_unix_mode(path: &Path, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
+}
+
+/// A reader that reports the number of bytes read to a progress handler.
+struct ProgressReader<R, T> {
+    inner: R,
+    handler: T,
+    read: u64,
+}
+
+impl<R: Read, T: ProgressHandler> Read for ProgressReader<R, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.read += size as u64;
+        self.handler
+            .update(Some(self.read))
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(size)
+    }
 }
@@ -13,6 +13,8 @@ static LOGGER_SET: OnceLock<bool> = OnceLock::new();
 #[derive(Debug)]
 pub struct Logger {
     output_sender: Option<Sender<String>>,
+    /// Print log messages to `stderr` instead of `stdout`.
+    use_stderr: bool,
     /// This level only effects displayed log,
     /// the file logger will still be using max log level.
     level: LevelFilter,
@@ -33,6 +35,7 @@ impl Logger {
 
         Self {
             output_sender: None,
+            use_stderr: false,
             level,
         }
     }
@@ -50,6 +53,12 @@ impl Logger {
         }
         self
     }
+    /// Print log messages to `stderr` instead of `stdout`,
+    /// so that `stdout` can be reserved for machine-readable output.
+    pub fn stderr(mut self, yes: bool) -> Self {
+        self.use_stderr = yes;
+        self
+    }
     /// Send output using a specific sender rather than printing on `stdout`.
     pub fn sender(mut self, sender: Sender<String>) -> Self {
         self.output_sender = Some(sender);
@@ -72,6 +81,11 @@ impl Logger {
         };
 
         // log to standard output (colored info label)
+        let output: fern::Output = if self.use_stderr {
+            std::io::stderr().into()
+        } else {
+            std::io::stdout().into()
+        };
         let stdout = fern::Dispatch::new()
             .filter(filter_log_for_output)
             .format(|out, msg, rec| {
@@ -85,7 +99,7 @@ impl Logger {
                         .to_lowercase(),
                 ));
             })
-            .chain(std::io::stdout());
+            .chain(output);
         dispatch = dispatch.chain(stdout);
         // log to file (detailed trace with timestamp)
         let file_config = fern::Dispatch::new()
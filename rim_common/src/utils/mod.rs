@@ -1,20 +1,26 @@
 //! Utility functions/types to use across the whole crate.
 
+mod cache;
+mod checksum;
 mod download;
 mod extraction;
 mod file_system;
 mod log;
 mod process;
 mod progress_bar;
+mod signature;
 
 use cfg_if::cfg_if;
 // Re-exports
-pub use download::DownloadOpt;
+pub use cache::{CacheEntry, DownloadCache};
+pub use checksum::*;
+pub use download::{DownloadOpt, RetryPolicy};
 pub use extraction::Extractable;
 pub use file_system::*;
 pub use log::*;
 pub use process::*;
 pub use progress_bar::*;
+pub use signature::*;
 
 use std::{
     ffi::OsStr,
@@ -230,6 +236,22 @@ pub fn build_cfg_locale(key: &str) -> &str {
     crate::cfg_locale!(cur_locale.locale_str(), key)
 }
 
+/// Format the size in bytes to a human readable string, such as `1.5 MiB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
 /// Check if the current operation system has desktop environment running.
 pub fn has_desktop_environment() -> bool {
     cfg_if! {
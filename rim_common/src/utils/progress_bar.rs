@@ -1,7 +1,9 @@
 //! Progress bar indicator for commandline user interface.
 
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar as CliProgressBar, ProgressState, ProgressStyle as CliProgressStyle};
+use indicatif::{
+    MultiProgress, ProgressBar as CliProgressBar, ProgressState, ProgressStyle as CliProgressStyle,
+};
 use std::time::Duration;
 
 #[allow(unused_variables)]
@@ -28,6 +30,15 @@ pub trait ProgressHandler: Send + Sync {
     fn finish_master(&self, msg: String) -> Result<()> {
         Ok(())
     }
+
+    /// Create a handler for another progress that runs concurrently with this one,
+    /// such as downloading multiple files at the same time.
+    ///
+    /// Each of the concurrent progresses should be displayed separately,
+    /// handlers that don't support this show nothing for them by default.
+    fn concurrent(&self) -> Box<dyn ProgressHandler> {
+        Box::new(HiddenProgress)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -86,6 +97,9 @@ impl ProgressHandler for HiddenProgress {
 pub struct CliProgress {
     bar: CliProgressBar,
     style: ProgressKind,
+    /// Shared by the [`concurrent`](ProgressHandler::concurrent) handlers,
+    /// so that their progress bars can be drawn together without overlapping.
+    multi: MultiProgress,
 }
 
 impl Default for CliProgress {
@@ -93,6 +107,7 @@ impl Default for CliProgress {
         Self {
             bar: CliProgressBar::hidden(),
             style: ProgressKind::Hidden,
+            multi: MultiProgress::new(),
         }
     }
 }
@@ -100,7 +115,7 @@ impl Default for CliProgress {
 impl ProgressHandler for CliProgress {
     fn start(&mut self, msg: String, style: ProgressKind) -> Result<()> {
         // log the starting of the progress
-        info!("{msg}");
+        self.multi.suspend(|| info!("{msg}"));
 
         let bar = match style {
             ProgressKind::Bytes(len) | ProgressKind::Len(len) => CliProgressBar::new(len),
@@ -113,6 +128,12 @@ impl ProgressHandler for CliProgress {
             }
             ProgressKind::Hidden => CliProgressBar::hidden(),
         };
+        // hidden bar will be drawn if added to the multi-progress
+        let bar = if matches!(style, ProgressKind::Hidden) {
+            bar
+        } else {
+            self.multi.add(bar)
+        };
 
         self.bar = bar
             .with_style(
@@ -151,7 +172,14 @@ impl ProgressHandler for CliProgress {
         // log the starting of the progress.
         // NB: This need to be done after `finish_with_message` to prevent
         // showing double progress bar on terminal
-        info!("{msg}");
+        self.multi.suspend(|| info!("{msg}"));
         Ok(())
     }
+
+    fn concurrent(&self) -> Box<dyn ProgressHandler> {
+        Box::new(Self {
+            multi: self.multi.clone(),
+            ..Default::default()
+        })
+    }
 }
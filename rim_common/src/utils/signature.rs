@@ -0,0 +1,84 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+/// Verify a detached ed25519 `signature` of `data`, the signature will be
+/// accepted if it was signed by any one of the `trusted_keys`.
+///
+/// Both the `signature` and each of the `trusted_keys` are expected to be base64 encoded,
+/// surrounding whitespace are ignored so that the content of a `.sig` file can be used directly.
+///
+/// # Error
+/// Return error if the signature is malformed, or it cannot be verified by any trusted key.
+pub fn verify_signature<S: AsRef<str>>(
+    data: &[u8],
+    signature: &str,
+    trusted_keys: &[S],
+) -> Result<()> {
+    let sig_bytes = BASE64
+        .decode(signature.trim())
+        .context("signature is not a valid base64 string")?;
+    let signature = Signature::from_slice(&sig_bytes).context("malformed ed25519 signature")?;
+
+    for key in trusted_keys {
+        let key = match parse_public_key(key.as_ref()) {
+            Ok(k) => k,
+            Err(e) => {
+                warn!("skipping invalid trusted key '{}': {e}", key.as_ref());
+                continue;
+            }
+        };
+        if key.verify(data, &signature).is_ok() {
+            return Ok(());
+        }
+    }
+
+    bail!("signature does not match any of the trusted keys")
+}
+
+fn parse_public_key(key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = BASE64
+        .decode(key.trim())?
+        .try_into()
+        .map_err(|_| anyhow!("ed25519 public key must be 32 bytes long"))?;
+    Ok(VerifyingKey::from_bytes(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn public_key(key: &SigningKey) -> String {
+        BASE64.encode(key.verifying_key().as_bytes())
+    }
+
+    #[test]
+    fn verify_detached_signature() {
+        let key = signing_key(1);
+        let data = b"name = \"toolkit\"";
+        let sig = BASE64.encode(key.sign(data).to_bytes());
+        let trusted = [public_key(&signing_key(2)), public_key(&key)];
+
+        assert!(verify_signature(data, &sig, &trusted).is_ok());
+        // with trailing newline, just like a `.sig` file
+        assert!(verify_signature(data, &format!("{sig}\n"), &trusted).is_ok());
+        // tampered data
+        assert!(verify_signature(b"name = \"evil\"", &sig, &trusted).is_err());
+        // signed by an untrusted key
+        assert!(verify_signature(data, &sig, &[public_key(&signing_key(2))]).is_err());
+    }
+
+    #[test]
+    fn malformed_signature() {
+        let trusted = [public_key(&signing_key(1))];
+
+        assert!(verify_signature(b"data", "not base64!", &trusted).is_err());
+        assert!(verify_signature(b"data", &BASE64.encode([0; 10]), &trusted).is_err());
+    }
+}
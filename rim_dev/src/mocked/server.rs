@@ -3,10 +3,9 @@ use crate::common;
 use super::TOOLKIT_NAME;
 use anyhow::{anyhow, Context, Result};
 use rim_common::utils;
-use sha2::Digest;
 use std::collections::HashMap;
 use std::fs;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use url::Url;
@@ -454,7 +453,7 @@ impl<'a> RustInstallerPackage<'a> {
 
 /// Write checksum to a file next to `path`, and return the calculated sha256 checksum.
 fn write_checksum(path: &Path) -> Result<String> {
-    let checksum = calculate_sha256(path)?;
+    let checksum = utils::sha256_of(path)?;
     let mut checksum_path = path.as_os_str().to_os_string();
     checksum_path.push(".sha256");
     let filename = path
@@ -465,28 +464,6 @@ fn write_checksum(path: &Path) -> Result<String> {
     Ok(checksum)
 }
 
-fn calculate_sha256(file_path: &Path) -> Result<String> {
-    // Open the file
-    let file = fs::File::open(file_path)?;
-    let mut reader = BufReader::new(file);
-
-    // Create a SHA-256 hasher
-    let mut hasher = sha2::Sha256::new();
-    let mut buffer = [0; 4096]; // Read in chunks
-
-    // Read file in chunks and update the hash
-    while let Ok(n) = reader.read(&mut buffer) {
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buffer[..n]);
-    }
-
-    // Finalize and get the checksum as a hex string
-    let result = hasher.finalize();
-    Ok(format!("{result:x}"))
-}
-
 pub(crate) fn generate_rim_server_files() -> Result<()> {
     let mocked = RimServer::new();
     mocked.gen_tool_packages()?;
@@ -2,16 +2,22 @@ use crate::{
     common::{download, resources_dir},
     toolkits_parser::{Component, Configuration, Toolkits},
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use indexmap::IndexMap;
 use rim_common::{
-    types::{ToolInfo, ToolSource},
-    utils::{ensure_dir, ensure_parent_dir},
+    types::{RustupInit, ToolInfo, ToolSource, VendoredCrate},
+    utils::{copy_as, ensure_dir, ensure_parent_dir},
+};
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
 };
-use std::{fs, path::Path};
 
 const TOOLS_DIRNAME: &str = "tools";
 const TOOLCHAIN_DIRNAME: &str = "toolchain";
+const CRATES_DIRNAME: &str = "crates";
 
 pub(super) const VENDOR_HELP: &str = r#"
 Split `toolkits.toml` and download packages specified in it for offline packaging
@@ -197,11 +203,26 @@ fn gen_manifest_and_download_packages(args: &VendorArgs, toolkits: &mut Toolkits
                 download(&url, &dest)?;
             }
 
-            rustup_sources.insert(triple.into(), value);
+            // keep the expected checksum if the original manifest has one
+            let rustup_init = match rust_section.rustup_init_sha256(triple) {
+                Some(sha256) => RustupInit::Detailed {
+                    path: Some(value),
+                    sha256: Some(sha256.into()),
+                },
+                None => value.into(),
+            };
+            rustup_sources.insert(triple.into(), rustup_init);
         }
         rust_section.rustup = rustup_sources;
 
-        // Download rust-toolchain component packages if necessary
+        // Download rust-toolchain component packages if necessary,
+        // including the standard libraries of the additional compilation targets
+        let cross_targets = toolkit
+            .manifest
+            .toolchain_targets()
+            .into_iter()
+            .map(|(target, _)| target.to_string())
+            .collect::<Vec<_>>();
         for target in &config.targets {
             let triple = target.triple();
             if !args.should_download(name, triple) {
@@ -214,10 +235,32 @@ fn gen_manifest_and_download_packages(args: &VendorArgs, toolkits: &mut Toolkits
                 toolkit.rust_version(),
                 toolkit.date(),
                 triple,
+                &cross_targets,
                 args,
             )?;
         }
 
+        // Vendor the listed crates (if any), those crates are not target specific,
+        // so they are only vendored once, then copied into every target's package.
+        if !toolkit.manifest.crates.packages.is_empty() {
+            let mut vendored: Option<PathBuf> = None;
+            for target in &config.targets {
+                let triple = target.triple();
+                if !args.should_download(name, triple) {
+                    continue;
+                }
+                let crates_dir = toolkit_root.join(triple).join(CRATES_DIRNAME);
+                if let Some(src) = &vendored {
+                    println!("copying vendored crates into: {}", crates_dir.display());
+                    copy_as(src, &crates_dir)?;
+                } else {
+                    vendor_crates(&toolkit.manifest.crates.packages, &crates_dir)?;
+                    vendored = Some(crates_dir);
+                }
+            }
+            toolkit.manifest.crates.path = Some(CRATES_DIRNAME.into());
+        }
+
         let offline_manifest = toolkit.manifest_string()?;
         let offline_manifest_content = format!("{TOOLSET_MANIFEST_HEADER}{offline_manifest}");
         args.write_manifest_if_needed(&offline_manifest_path, &offline_manifest_content)?;
@@ -231,6 +274,7 @@ fn download_toolchain_components(
     version: &str,
     date: &str,
     triple: &str,
+    cross_targets: &[String],
     args: &VendorArgs,
 ) -> Result<()> {
     let components = &config.components;
@@ -280,5 +324,88 @@ fn download_toolchain_components(
         download(&pkg_src, &pkg_dest)?;
     }
 
+    // `rust-std` of cross compilation targets, which can be installed on any host
+    for target in cross_targets {
+        let pkg_name = format!("rust-std-{version}-{target}.tar.xz");
+        let pkg_src = config.rust_dist_url(&format!("{date}/{pkg_name}"));
+        let pkg_dest = date_dir.join(&pkg_name);
+        download(&pkg_src, &pkg_dest)?;
+    }
+
     Ok(())
 }
+
+/// Download the `packages` and their dependencies into `dest` using `cargo vendor`,
+/// so that they can be used as a directory source.
+///
+/// Since `cargo vendor` only works with a cargo project, a temporary project will be
+/// created with every package listed as its dependency.
+fn vendor_crates(packages: &IndexMap<String, VendoredCrate>, dest: &Path) -> Result<()> {
+    let project = tempfile::tempdir()?;
+    fs::write(
+        project.path().join("Cargo.toml"),
+        vendor_project_manifest(packages)?,
+    )?;
+    ensure_dir(project.path().join("src"))?;
+    fs::write(project.path().join("src").join("lib.rs"), "")?;
+
+    println!("vendoring crates into: {}", dest.display());
+    ensure_parent_dir(dest)?;
+    let status = Command::new("cargo")
+        .arg("vendor")
+        .arg("--versioned-dirs")
+        .arg("--manifest-path")
+        .arg(project.path().join("Cargo.toml"))
+        .arg(dest)
+        .status()?;
+    if !status.success() {
+        bail!("failed to vendor crates into '{}'", dest.display());
+    }
+    Ok(())
+}
+
+/// Generate the `Cargo.toml` content of the temporary project used by [`vendor_crates`],
+/// packages are listed as dependencies along with their features.
+fn vendor_project_manifest(packages: &IndexMap<String, VendoredCrate>) -> Result<String> {
+    #[derive(Serialize)]
+    struct Dependencies<'a> {
+        dependencies: &'a IndexMap<String, VendoredCrate>,
+    }
+
+    let dependencies = toml::to_string(&Dependencies {
+        dependencies: packages,
+    })?;
+    Ok(format!(
+        "[package]\nname = \"rim-vendored-crates\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n{dependencies}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vendor_crates_with_features() {
+        let packages: IndexMap<String, VendoredCrate> = toml::from_str(
+            r#"
+serde = "1.0.210"
+tokio = { version = "1.40", features = ["full"], default-features = false }
+"#,
+        )
+        .unwrap();
+        let manifest: toml::Table = vendor_project_manifest(&packages).unwrap().parse().unwrap();
+
+        assert_eq!(
+            manifest["package"]["name"].as_str(),
+            Some("rim-vendored-crates")
+        );
+        let deps = &manifest["dependencies"];
+        assert_eq!(deps["serde"].as_str(), Some("1.0.210"));
+        assert_eq!(deps["tokio"]["version"].as_str(), Some("1.40"));
+        assert_eq!(
+            deps["tokio"]["features"].as_array().unwrap(),
+            &[toml::Value::from("full")]
+        );
+        assert_eq!(deps["tokio"]["default-features"].as_bool(), Some(false));
+    }
+}
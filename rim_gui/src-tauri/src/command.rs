@@ -5,6 +5,7 @@ use crate::consts::{MANAGER_UPDATE_NOTICE, MANAGER_WINDOW_LABEL};
 use crate::error::Result;
 use crate::progress::GuiProgress;
 use rim::components::Component;
+use rim::lock::{InstallationLock, LockInfo};
 use rim::update::{UpdateKind, UpdateOpt};
 use rim::{AppInfo, GlobalOpts};
 use rim_common::types::{Configuration, ReleaseChannel};
@@ -30,6 +31,7 @@ macro_rules! with_shared_commands {
             $crate::command::set_auto_check_toolkit_updates,
             $crate::command::set_manager_update_channel,
             $crate::command::check_manager_update,
+            $crate::command::get_lock_state,
             $($additional_cmd),*
         ]
     };
@@ -147,3 +149,12 @@ pub(crate) async fn check_manager_update(app: AppHandle) -> Result<bool> {
         Ok(false)
     }
 }
+
+/// Get the information of the operation that is running in another process, if there is one.
+///
+/// This is useful to disable the actions that modify the installation
+/// until the other operation finishes.
+#[tauri::command]
+pub(crate) fn get_lock_state() -> Option<LockInfo> {
+    InstallationLock::state().filter(|info| info.pid != std::process::id())
+}
@@ -11,7 +11,7 @@ use crate::command::with_shared_commands;
 use crate::common::{self, expected_manifest, BaseConfiguration, TOOLKIT_MANIFEST};
 use crate::error::Result;
 use rim::components::Component;
-use rim::{get_toolkit_manifest, ToolkitManifestExt};
+use rim::{get_toolkit_manifest, preflight, ToolkitManifestExt};
 use rim_common::types::{ToolInfo, ToolSource, ToolkitManifest};
 use rim_common::utils;
 use tokio::sync::RwLock as AsyncRwLock;
@@ -50,21 +50,39 @@ async fn default_configuration() -> BaseConfiguration {
 }
 
 /// Check if the given path could be used for installation, and return the reason if not.
+///
+/// This includes the same diagnostics as the preflight check before installation,
+/// such as write permission and free disk space, assuming the pre-selected components
+/// will be installed.
 #[tauri::command]
-fn check_install_path(path: String) -> Option<String> {
+async fn check_install_path(path: String) -> Option<String> {
     if path.is_empty() {
-        Some(t!("notify_empty_path").to_string())
+        return Some(t!("notify_empty_path").to_string());
     } else if Path::new(&path).is_relative() {
         // We won't accept relative path because the result might gets a little bit unpredictable
-        Some(t!("notify_relative_path").to_string())
-    } else if utils::is_root_dir(path) {
-        Some(t!("notify_root_dir").to_string())
-    } else {
-        None
+        return Some(t!("notify_relative_path").to_string());
+    } else if utils::is_root_dir(&path) {
+        return Some(t!("notify_root_dir").to_string());
     }
+
+    let required = if let Some(cached) = TOOLKIT_MANIFEST.get() {
+        let manifest = cached.read().await;
+        let preselected = manifest
+            .current_target_components(true)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|c| !c.installed && (c.required || !c.optional))
+            .collect::<Vec<_>>();
+        preflight::estimated_size(&manifest, &preselected)
+    } else {
+        0
+    };
+    let problems = preflight::diagnose(Path::new(&path), required);
+    (!problems.is_empty()).then(|| problems.join("\n"))
 }
 
-/// Get full list of supported components
+/// Get full list of supported components, each one knows what other components require it,
+/// so the frontend can select the dependencies along with a component.
 #[tauri::command]
 async fn get_component_list() -> Result<Vec<Component>> {
     let components = expected_manifest()
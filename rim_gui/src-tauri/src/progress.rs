@@ -1,5 +1,8 @@
 //! GUI progress bar module
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 use rim_common::utils::{ProgressHandler, ProgressKind};
 use serde::Serialize;
 use tauri::{AppHandle, Manager};
@@ -10,6 +13,9 @@ const MAIN_PROGRESS_END_EVENT: &str = "progress:main-end";
 const SUB_PROGRESS_START_EVENT: &str = "progress:sub-start";
 const SUB_PROGRESS_UPDATE_EVENT: &str = "progress:sub-update";
 const SUB_PROGRESS_END_EVENT: &str = "progress:sub-end";
+const TASK_PROGRESS_START_EVENT: &str = "progress:task-start";
+const TASK_PROGRESS_UPDATE_EVENT: &str = "progress:task-update";
+const TASK_PROGRESS_END_EVENT: &str = "progress:task-end";
 
 #[derive(Debug, Clone, Copy, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -38,14 +44,46 @@ struct ProgressPayload {
     length: Option<u64>,
 }
 
+/// Payload of the concurrent task progress events,
+/// which wraps the payload of a sub progress event with the `id` of the task.
+#[derive(Debug, Clone, Serialize)]
+struct TaskPayload<T> {
+    id: usize,
+    data: T,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct GuiProgress {
     handle: AppHandle,
+    /// The id of a concurrent task, `None` if this is handling the sub progress.
+    task: Option<usize>,
+    /// Counter to generate ids for concurrent tasks.
+    next_task: Arc<AtomicUsize>,
 }
 
 impl GuiProgress {
     pub(crate) fn new(handle: AppHandle) -> Self {
-        Self { handle }
+        Self {
+            handle,
+            task: None,
+            next_task: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Emit an event of the sub progress, or the task progress if this is a concurrent one.
+    fn emit<T: Serialize + Clone>(
+        &self,
+        sub_event: &str,
+        task_event: &str,
+        payload: T,
+    ) -> anyhow::Result<()> {
+        if let Some(id) = self.task {
+            let payload = TaskPayload { id, data: payload };
+            self.handle.emit_all(task_event, payload)?;
+        } else {
+            self.handle.emit_all(sub_event, payload)?;
+        }
+        Ok(())
     }
 }
 
@@ -64,19 +102,19 @@ impl ProgressHandler for GuiProgress {
             style: gui_style,
         };
 
-        self.handle.emit_all(SUB_PROGRESS_START_EVENT, payload)?;
-        Ok(())
+        self.emit(SUB_PROGRESS_START_EVENT, TASK_PROGRESS_START_EVENT, payload)
     }
 
     fn update(&self, value: Option<u64>) -> anyhow::Result<()> {
-        self.handle
-            .emit_all(SUB_PROGRESS_UPDATE_EVENT, value.unwrap_or(1))?;
-        Ok(())
+        self.emit(
+            SUB_PROGRESS_UPDATE_EVENT,
+            TASK_PROGRESS_UPDATE_EVENT,
+            value.unwrap_or(1),
+        )
     }
 
     fn finish(&self, msg: String) -> anyhow::Result<()> {
-        self.handle.emit_all(SUB_PROGRESS_END_EVENT, msg)?;
-        Ok(())
+        self.emit(SUB_PROGRESS_END_EVENT, TASK_PROGRESS_END_EVENT, msg)
     }
 
     fn start_master(&mut self, msg: String, style: ProgressKind) -> anyhow::Result<()> {
@@ -100,4 +138,12 @@ impl ProgressHandler for GuiProgress {
         self.handle.emit_all(MAIN_PROGRESS_END_EVENT, msg)?;
         Ok(())
     }
+
+    fn concurrent(&self) -> Box<dyn ProgressHandler> {
+        let id = self.next_task.fetch_add(1, Ordering::Relaxed);
+        Box::new(Self {
+            task: Some(id),
+            ..self.clone()
+        })
+    }
 }
@@ -16,6 +16,7 @@ import BasePanel from '@/components/BasePanel.vue';
 import BaseSelect from '@/components/BaseSelect.vue';
 import LockIndicator from '@/components/LockIndicator.vue';
 import Spinner from '@/components/Spinner.vue';
+import TaskProgress from '@/components/TaskProgress.vue';
 
 export default {
   install(app: App) {
@@ -36,5 +37,6 @@ export default {
     app.component('base-select', BaseSelect);
     app.component('lock-indicator', LockIndicator);
     app.component('spinner', Spinner);
+    app.component('task-progress', TaskProgress);
   },
 };
@@ -9,8 +9,8 @@ export function componentUtils(component: Component) {
     },
   
     requires(): string[] {
-      const req = this.getToolInfo()?.requires;
-      return req ?? [];
+      const info = this.getToolInfo();
+      return info ? info.requires ?? [] : component.requires;
     },
   
     obsoletes(): string[] {
@@ -39,9 +39,12 @@ export interface Component {
   required: boolean;
   optional: boolean;
   toolInstaller?: string | ToolInfoDetails;
+  requires: string[];
   kind: ComponentType;
   kindDesc: ComponentTypeDesc;
   installed: boolean;
+  held: boolean;
+  requiredBy: string[];
 }
 
 export type ToolInfoDetails =
@@ -100,6 +103,7 @@ export enum ComponentType {
   Tool = "Tool",
   ToolchainComponent = "ToolchainComponent",
   ToolchainProfile = "ToolchainProfile",
+  ToolchainTarget = "ToolchainTarget",
 }
 
 export interface ComponentTypeDesc {
@@ -127,3 +131,39 @@ export function toChecked(components: Component[]): CheckItem<Component>[] {
 export function isRecommended(component: Component): boolean {
   return !component.installed && (component.required || !component.optional);
 }
+
+/**
+ * Apply the `changed` check states to `items`, then select every component
+ * that is required by a checked one.
+ *
+ * Components that are required by other checked components cannot be unchecked,
+ * returns a list of such components with the names of the ones requiring them.
+ */
+export function updateCheckedWithDependencies(
+  items: CheckItem<Component>[],
+  changed: CheckItem<Component>[]
+): [Component, string[]][] {
+  const previouslyChecked = new Set(items.filter((i) => i.checked).map((i) => i.value.id));
+  for (const item of items) {
+    const changedItem = changed.find((i) => i.value.id === item.value.id);
+    if (changedItem) {
+      item.checked = changedItem.checked;
+    }
+  }
+
+  const checked = items.filter((i) => i.checked);
+  const blocked: [Component, string[]][] = [];
+  for (const item of items) {
+    if (item.value.installed) continue;
+    const dependents = checked
+      .filter((i) => item.value.requiredBy.includes(i.value.name))
+      .map((i) => i.value.displayName);
+    if (dependents.length === 0) continue;
+
+    if (previouslyChecked.has(item.value.id) && !item.checked) {
+      blocked.push([item.value, dependents]);
+    }
+    item.checked = true;
+  }
+  return blocked;
+}
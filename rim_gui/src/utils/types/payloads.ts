@@ -20,3 +20,15 @@ export interface UpdatePayload {
     version: string,
     data?: string,
 }
+
+/** Payload of the `progress:task-*` events, which are sent by concurrent tasks. */
+export interface TaskPayload<T> {
+    id: number,
+    data: T,
+}
+
+/** Information about the operation that holds the installation lock. */
+export interface LockInfo {
+    pid: number,
+    started: string,
+}
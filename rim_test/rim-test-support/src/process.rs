@@ -159,6 +159,31 @@ impl TestProcess {
         config_dir
     }
 
+    /// Return the path to a mocked cache dir under temporary test folder
+    pub fn cache_dir(&self) -> PathBuf {
+        let mut cache_dir = self.home_dir();
+
+        #[cfg(target_os = "linux")]
+        {
+            cache_dir.push(".cache");
+            cache_dir.push("rim");
+        }
+        #[cfg(windows)]
+        {
+            cache_dir.push("AppData");
+            cache_dir.push("Local");
+            cache_dir.push("rim");
+            cache_dir.push("cache");
+        }
+        #[cfg(target_os = "macos")]
+        {
+            cache_dir.push("Library");
+            cache_dir.push("Caches");
+            cache_dir.push("rim");
+        }
+        cache_dir
+    }
+
     /// Return the default installation directory of rim
     pub fn default_install_dir(&self) -> PathBuf {
         self.home_dir().join("rust")
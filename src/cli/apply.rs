@@ -0,0 +1,90 @@
+//! Separated module to handle `apply` command in command line.
+
+use super::{common, output, ExecStatus, GlobalOpts, ManagerSubcommands};
+use crate::core::apply::{Action, ApplyPlan, Step};
+use crate::core::parser::lockfile::Lockfile;
+use crate::fingerprint::InstallationRecord;
+use crate::ToolkitManifestExt;
+use anyhow::Result;
+use colored::Colorize;
+use rim_common::types::{TomlParser, ToolkitManifest};
+use rim_common::utils::CliProgress;
+
+/// Execute `apply` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    let ManagerSubcommands::Apply {
+        lockfile,
+        dry_run,
+        insecure,
+    } = subcommand
+    else {
+        return Ok(ExecStatus::default());
+    };
+
+    let lockfile = Lockfile::load(lockfile)?;
+    let record = InstallationRecord::load_from_config_dir()?;
+    let manifest = ToolkitManifest::load_from_install_dir()?;
+    let plan = ApplyPlan::new(&lockfile, &record, manifest.toolchain.name());
+
+    if GlobalOpts::get().json_output() {
+        output::print_json("apply", &plan)?;
+    } else {
+        show_plan(&plan);
+    }
+    if *dry_run || plan.is_empty() {
+        return Ok(ExecStatus::new_executed().no_pause(true));
+    }
+
+    if !common::confirm(t!("apply_confirmation"), true)? {
+        return Ok(ExecStatus::new_executed());
+    }
+    blocking!(plan.execute(CliProgress::default(), *insecure))?;
+    info!("{}", t!("task_success"));
+
+    Ok(ExecStatus::new_executed())
+}
+
+fn show_plan(plan: &ApplyPlan) {
+    // held steps are still shown, so users know why they are not applied
+    if plan.toolkit.is_none() && plan.steps.is_empty() {
+        println!("{}", t!("apply_up_to_date"));
+    } else {
+        print_plan(plan, &t!("apply_plan"));
+    }
+}
+
+/// Print the changes of a non-empty `plan` under a `title`.
+pub(super) fn print_plan(plan: &ApplyPlan, title: &str) {
+    println!("{title}\n");
+    if let Some(meta) = &plan.toolkit {
+        let name = meta.name.as_deref().unwrap_or_default();
+        let version = meta.version.as_deref().unwrap_or_default();
+        println!(
+            "  * {}",
+            t!("apply_toolkit_meta", name = name, version = version)
+        );
+    }
+    for step in &plan.steps {
+        println!("  {}", format_step(step));
+    }
+    println!();
+}
+
+/// Format a change of component, such as `~ cargo-expand 1.0.90 -> 1.0.100`.
+pub(super) fn format_step(step: &Step) -> String {
+    let from = step.from.as_deref().unwrap_or_default();
+    let to = step.to.as_deref().unwrap_or_default();
+    let (sign, detail) = match step.action {
+        Action::Install => ("+".green(), to.to_string()),
+        Action::Update => ("~".yellow(), format!("{from} -> {to}")),
+        Action::Remove => ("-".red(), from.to_string()),
+    };
+    let line = format!("{sign} {}", format!("{} {detail}", step.name).trim_end());
+    if step.held {
+        format!("{line} ({})", t!("held_step_skipped"))
+            .dimmed()
+            .to_string()
+    } else {
+        line
+    }
+}
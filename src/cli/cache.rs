@@ -0,0 +1,95 @@
+//! Separated module to handle `cache` command in command line.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::Subcommand;
+use rim_common::utils::{human_size, CacheEntry, DownloadCache};
+
+use super::{ExecStatus, ManagerSubcommands};
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// Show the downloaded packages in cache
+    List,
+    /// Remove all the downloaded packages from cache
+    Clean,
+    /// Remove the downloaded packages that haven't been used for a while
+    Prune {
+        /// Remove the packages that haven't been used for this long, such as `30d`, `12h`, `2w`
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        older_than: Duration,
+    },
+}
+
+/// Execute `cache` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    let ManagerSubcommands::Cache { command } = subcommand else {
+        return Ok(ExecStatus::default());
+    };
+
+    let cache = DownloadCache::open();
+    match command {
+        CacheCommand::List => {
+            let entries = cache.entries()?;
+            for entry in &entries {
+                println!(
+                    "{:<40} {:>10}  {}  {}",
+                    entry.filename,
+                    human_size(entry.size),
+                    entry.last_used.format("%Y-%m-%d %H:%M"),
+                    entry.url
+                );
+            }
+            println!(
+                "{}",
+                t!(
+                    "cache_summary",
+                    count = entries.len(),
+                    size = human_size(total_size(&entries)),
+                    path = cache.root().display()
+                )
+            );
+        }
+        CacheCommand::Clean => report_removed(&cache.clean()?),
+        CacheCommand::Prune { older_than } => report_removed(&cache.prune(*older_than)?),
+    }
+
+    Ok(ExecStatus::new_executed().no_pause(true))
+}
+
+fn report_removed(removed: &[CacheEntry]) {
+    info!(
+        "{}",
+        t!(
+            "cache_removed",
+            count = removed.len(),
+            size = human_size(total_size(removed))
+        )
+    );
+}
+
+fn total_size(entries: &[CacheEntry]) -> u64 {
+    entries.iter().map(|e| e.size).sum()
+}
+
+/// Parse a duration such as `30d`, the supported units are
+/// `s` (seconds), `m` (minutes), `h` (hours), `d` (days) and `w` (weeks).
+///
+/// Days will be used if there is no unit.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let num: u64 = num
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}', expecting a value like '30d'"))?;
+    let secs = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        other => return Err(format!("invalid duration unit '{other}'")),
+    };
+    Ok(Duration::from_secs(num.saturating_mul(secs)))
+}
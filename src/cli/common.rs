@@ -11,7 +11,7 @@ use std::{
     sync::OnceLock,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use indexmap::IndexMap;
 
 use crate::components::Component;
@@ -94,6 +94,9 @@ pub(crate) struct VersionDiff<'c> {
     /// `true` a tool wasn't supported or installed previously, but have a new version
     /// available, which means that tool is newly supported.
     pub(crate) is_newly_supported: bool,
+    /// `true` if the installed component is held at its installed version,
+    /// which means it will not be updated.
+    pub(crate) held: bool,
 }
 
 pub(crate) fn question_str<Q: Display, A: Display>(
@@ -117,6 +120,8 @@ where
     A: Display,
     F: Fn(&str) -> bool,
 {
+    ensure_interactive()?;
+
     let mut stdout = io::stdout();
     let default_badge = if !default.to_string().is_empty() {
         format!(" [{}: {default}]", t!("default"))
@@ -281,6 +286,7 @@ pub(crate) fn confirm<Q: Display>(question: Q, default: bool) -> Result<bool> {
     if GlobalOpts::get().yes_to_all {
         return Ok(true);
     }
+    ensure_interactive()?;
 
     let mut stdout = io::stdout();
     writeln!(
@@ -311,6 +317,8 @@ pub(crate) enum Confirm {
 }
 
 pub(crate) fn confirm_options() -> Result<Confirm> {
+    ensure_interactive()?;
+
     let mut stdout = io::stdout();
 
     writeln!(&mut stdout, "\n{}\n", t!("question_options"))?;
@@ -354,8 +362,14 @@ pub(crate) fn show_confirmation(
     let list_of_comp = ComponentListBuilder::new(choices.values().copied())
         .decorate(ComponentDecoration::Confirmation)
         .build();
-    for line in list_of_comp {
-        writeln!(&mut stdout, "\t{line}")?;
+    for (line, comp) in list_of_comp.into_iter().zip(choices.values()) {
+        let dependents = selected_dependents(comp, choices);
+        if is_remove || comp.installed || dependents.is_empty() {
+            writeln!(&mut stdout, "\t{line}")?;
+        } else {
+            let note = t!("required_by", list = dependents.join(", "));
+            writeln!(&mut stdout, "\t{line} ({note})")?;
+        }
     }
 
     // list obsoleted components
@@ -395,25 +409,19 @@ pub(crate) fn component_choices_with_constrains<F>(
 where
     F: Fn(usize, &Component) -> bool,
 {
-    // tracking dependency and conflicting component names.
-    // dependencies will be added, and conflicted tools will be removed later.
-    let mut dependencies = HashSet::new();
-
     let mut selections = all_components
         .iter()
         .enumerate()
-        .filter(|(idx, c)| {
-            let selected = condition_callback(*idx, c);
-            if selected {
-                dependencies.extend(c.dependencies());
-            }
-            selected
-        })
+        .filter(|(idx, c)| condition_callback(*idx, c))
         .collect::<ComponentChoices>();
+    let selected = selections
+        .values()
+        .map(|c| c.name.clone())
+        .collect::<HashSet<_>>();
 
-    // iterate all components again to add dependencies
+    // iterate all components again to add dependencies, including the indirect ones
     for (idx, comp) in all_components.iter().enumerate() {
-        if dependencies.contains(&comp.name) && !comp.installed {
+        if !comp.installed && comp.required_by.iter().any(|name| selected.contains(name)) {
             selections.insert(idx, comp);
         }
     }
@@ -421,6 +429,40 @@ where
     selections
 }
 
+/// Get the names of selected components that require `comp`.
+fn selected_dependents<'c>(comp: &Component, choices: &ComponentChoices<'c>) -> Vec<&'c str> {
+    choices
+        .values()
+        .filter(|c| comp.required_by.contains(&c.name))
+        .map(|c| c.display_name.as_str())
+        .collect()
+}
+
+/// Warn about the dependencies that were added to `choices` automatically,
+/// including the ones that user tried to deselect, where `picked` is the
+/// set of indexes (starting from 1) that user actually entered.
+pub(crate) fn warn_auto_selected_dependencies(
+    choices: &ComponentChoices<'_>,
+    picked: &HashSet<usize>,
+) {
+    for (idx, comp) in choices {
+        if picked.contains(&(idx + 1)) {
+            continue;
+        }
+        let dependents = selected_dependents(comp, choices);
+        if !dependents.is_empty() {
+            warn!(
+                "{}",
+                t!(
+                    "dependency_auto_selected",
+                    name = comp.display_name,
+                    required_by = dependents.join(", ")
+                )
+            );
+        }
+    }
+}
+
 /// Pausing the console window.
 ///
 /// This will ask user to press `enter` key after the program finishes.
@@ -445,6 +487,15 @@ pub fn show_source_hint(install_dir: &std::path::Path) {
     }
 }
 
+/// Return error if user input is needed while the output is meant for scripts,
+/// as prompts would break the machine-readable output.
+fn ensure_interactive() -> Result<()> {
+    if GlobalOpts::get().json_output() {
+        bail!(t!("confirmation_required_in_json"));
+    }
+    Ok(())
+}
+
 fn readline() -> Result<String> {
     let mut input_buf = String::new();
     io::stdin()
@@ -496,11 +547,15 @@ impl ComponentDecoration<'_> {
             Self::VersionDiff(diff_map) => diff_map
                 .get(comp.name.as_str())
                 .map(|diff| {
-                    format!(
-                        " ({} -> {})",
-                        diff.from.unwrap_or("N/A"),
-                        diff.to.unwrap_or("N/A")
-                    )
+                    if diff.held {
+                        format!(" ({}, {})", diff.from.unwrap_or("N/A"), t!("held"))
+                    } else {
+                        format!(
+                            " ({} -> {})",
+                            diff.from.unwrap_or("N/A"),
+                            diff.to.unwrap_or("N/A")
+                        )
+                    }
                 })
                 .unwrap_or_else(String::new),
         }
@@ -9,14 +9,19 @@ use rim_common::{
 use url::Url;
 
 use crate::{
-    components::{split_components, Component},
+    components::{
+        all_components_from_installation, split_components, Component, ComponentType,
+        ToolchainComponent,
+    },
     fingerprint::InstallationRecord,
+    lock::InstallationLock,
     AppInfo, InstallConfiguration, ToolkitManifestExt, UninstallConfiguration,
 };
 
 use super::{
     common::{self, ComponentDecoration, ComponentListBuilder, Confirm},
-    ExecStatus, ManagerSubcommands,
+    output::{self, InstallationInfo},
+    ExecStatus, GlobalOpts, ManagerSubcommands,
 };
 
 #[derive(Subcommand, Debug, Clone)]
@@ -40,23 +45,68 @@ pub enum ComponentCommand {
         /// The list of components to uninstall, check `list component --installed` for available options
         #[arg(value_name = "COMPONENTS", value_delimiter = ',')]
         components: Vec<String>,
+        /// Uninstall the components even if other installed tools depend on them
+        #[arg(long, conflicts_with = "cascade")]
+        force: bool,
+        /// Uninstall the installed tools that depend on the components as well
+        #[arg(long)]
+        cascade: bool,
+    },
+    /// Hold components at their installed version, so that updates will skip them
+    ///
+    /// Holding the toolchain also holds all of its components.
+    Hold {
+        /// The list of components to hold, check `list component --installed` for available options
+        #[arg(value_name = "COMPONENTS", value_delimiter = ',', required = true)]
+        components: Vec<String>,
+    },
+    /// Release the hold of components, allowing them to be updated again
+    Unhold {
+        /// The list of held components to release
+        #[arg(value_name = "COMPONENTS", value_delimiter = ',', required = true)]
+        components: Vec<String>,
     },
 }
 
 impl ComponentCommand {
     fn execute(&self) -> Result<()> {
-        match self {
+        let command = match self {
             Self::Install {
                 components,
                 insecure,
                 rustup_dist_server,
-            } => blocking!(install_components(
+            } => {
+                blocking!(install_components(
+                    components,
+                    *insecure,
+                    rustup_dist_server
+                ))?;
+                "component-install"
+            }
+            Self::Uninstall {
                 components,
-                *insecure,
-                rustup_dist_server
-            )),
-            Self::Uninstall { components } => uninstall_components(components),
+                force,
+                cascade,
+            } => {
+                uninstall_components(components, *force, *cascade)?;
+                "component-uninstall"
+            }
+            Self::Hold { components } => {
+                hold_components(components)?;
+                "component-hold"
+            }
+            Self::Unhold { components } => {
+                unhold_components(components)?;
+                "component-unhold"
+            }
+        };
+
+        if GlobalOpts::get().json_output() {
+            // print the installation state after the change
+            let record = InstallationRecord::load_from_config_dir()?;
+            output::print_json(command, InstallationInfo::from(&record))?;
         }
+        Ok(())
     }
 }
 
@@ -110,8 +160,7 @@ async fn install_components(
     )?
     .insecure(insecure)
     .with_rustup_dist_server(rustup_dist_server.clone());
-    config.install_toolchain_components(&tc_components).await?;
-    config.install_tools(&tools).await?;
+    config.install_components(&tc_components, &tools).await?;
 
     info!("{}", t!("task_success"));
 
@@ -119,8 +168,8 @@ async fn install_components(
     #[cfg(unix)]
     {
         use rim_common::types::ToolKind;
-        let g_opts = crate::core::GlobalOpts::get();
-        if !(g_opts.quiet || g_opts.no_modify_env())
+        let g_opts = GlobalOpts::get();
+        if !(g_opts.quiet || g_opts.json_output() || g_opts.no_modify_env())
             && tools.iter().any(|(_, info)| {
                 matches!(info.kind(), Some(ToolKind::DirWithBin | ToolKind::Custom))
             })
@@ -131,20 +180,20 @@ async fn install_components(
     Ok(())
 }
 
-fn uninstall_components(components: &[String]) -> Result<()> {
+fn uninstall_components(components: &[String], force: bool, cascade: bool) -> Result<()> {
     let record = InstallationRecord::load_from_config_dir()?;
 
     // make a set out of components to:
     // 1. remove duplicates; 2. search faster;
     let mut comp_set: HashSet<&String> = components.iter().collect();
     // collect the toolchain components that needed to be removed
-    let tc_comps_to_remove = record
+    let mut tc_comps_to_remove = record
         .installed_toolchain_components()
         .into_iter()
         .filter(|c| comp_set.remove(&c.name))
         .collect::<Vec<_>>();
     // collect the tools that needed to be removed
-    let tools_to_remove = record
+    let mut tools_to_remove = record
         .tools
         .into_iter()
         .filter(|(name, _)| comp_set.remove(name))
@@ -168,13 +217,96 @@ fn uninstall_components(components: &[String]) -> Result<()> {
         return Ok(());
     }
 
-    let mut config = UninstallConfiguration::init(CliProgress::default())?;
-    config.remove_toolchain_components(&tc_comps_to_remove, 50)?;
-    config.remove_tools(tools_to_remove, 50)?;
+    let mut config = UninstallConfiguration::init(CliProgress::default())?.force(force);
+    if !force {
+        let names = tc_comps_to_remove
+            .iter()
+            .map(|c| c.name.as_str())
+            .chain(tools_to_remove.keys().map(String::as_str))
+            .collect::<Vec<_>>();
+        let dependents = config.dependents_of(&names);
+        if !dependents.is_empty() {
+            let (list, dependents_list) = (names.join(", "), dependents.join(", "));
+            let question = t!(
+                "uninstall_cascade_confirmation",
+                list = list,
+                dependents = dependents_list
+            );
+            // never remove the dependents without being asked to, even with `--yes`
+            let confirmed =
+                cascade || (!GlobalOpts::get().yes_to_all && common::confirm(question, false)?);
+            if !confirmed {
+                bail!(t!(
+                    "component_required_by",
+                    list = list,
+                    dependents = dependents_list
+                ));
+            }
+            // remove the dependents as well
+            let installed_targets = config.install_record.installed_toolchain_targets();
+            for name in dependents {
+                if let Some(rec) = config.install_record.tools.get(&name).cloned() {
+                    tools_to_remove.insert(name, rec);
+                } else if installed_targets.contains(&name) {
+                    tc_comps_to_remove.push(ToolchainComponent::new(name).is_target(true));
+                }
+            }
+        }
+    }
+    config.remove_components(&tc_comps_to_remove, tools_to_remove)?;
     info!("{}", t!("task_success"));
     Ok(())
 }
 
+fn hold_components(components: &[String]) -> Result<()> {
+    let _lock = InstallationLock::acquire()?;
+    let mut record = InstallationRecord::load_from_config_dir()?;
+    let installed = all_components_from_installation(&record)?;
+
+    for name in components {
+        let Some(comp) = installed.iter().find(|c| &c.name == name) else {
+            bail!(t!("hold_not_installed", name = name));
+        };
+        if matches!(
+            comp.kind,
+            ComponentType::ToolchainComponent | ComponentType::ToolchainTarget
+        ) {
+            let toolchain = installed
+                .iter()
+                .find(|c| c.kind == ComponentType::ToolchainProfile)
+                .map(|c| c.name.as_str())
+                .unwrap_or_default();
+            bail!(t!(
+                "hold_toolchain_component",
+                name = name,
+                toolchain = toolchain
+            ));
+        }
+    }
+    for name in components {
+        if record.hold(name) {
+            info!("{}", t!("component_held", name = name));
+        } else {
+            info!("{}", t!("component_already_held", name = name));
+        }
+    }
+    record.write()
+}
+
+fn unhold_components(components: &[String]) -> Result<()> {
+    let _lock = InstallationLock::acquire()?;
+    let mut record = InstallationRecord::load_from_config_dir()?;
+
+    for name in components {
+        if record.unhold(name) {
+            info!("{}", t!("component_unheld", name = name));
+        } else {
+            warn!("{}", t!("component_not_held", name = name));
+        }
+    }
+    record.write()
+}
+
 /// Ask user about a list of component's name to install.
 ///
 /// This is done by:
@@ -211,6 +343,7 @@ pub(super) fn collect_components_to_add() -> Result<Vec<String>> {
         let choices = common::component_choices_with_constrains(&all_components, |idx, _| {
             index_set.contains(&(idx + 1))
         });
+        common::warn_auto_selected_dependencies(&choices, &index_set);
 
         common::show_confirmation(None, &choices, false)?;
 
@@ -0,0 +1,56 @@
+//! Separated module to handle `crates` command in command line.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::{Subcommand, ValueHint};
+
+use super::{ExecStatus, ManagerSubcommands};
+use crate::core::lock::InstallationLock;
+use crate::core::vendored_crates;
+use crate::AppInfo;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CratesCommand {
+    /// Add crates to the vendored crates, which will be used in place of `crates-io`
+    Add {
+        /// Path to a directory of crates, such as the output of `cargo vendor`
+        #[arg(value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+    },
+}
+
+/// Execute `crates` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    let ManagerSubcommands::Crates { command } = subcommand else {
+        return Ok(ExecStatus::default());
+    };
+
+    match command {
+        CratesCommand::Add { dir } => add_crates(dir)?,
+    }
+
+    Ok(ExecStatus::new_executed())
+}
+
+fn add_crates(dir: &Path) -> Result<()> {
+    let _lock = InstallationLock::acquire()?;
+    let install_dir = AppInfo::get_installed_dir();
+    let dest = vendored_crates::vendored_crates_dir(install_dir);
+
+    let added = vendored_crates::merge_crates(dir, &dest)?;
+    vendored_crates::use_vendored_source(&install_dir.join("cargo"), &dest)?;
+
+    for name in &added {
+        info!("{}", t!("vendored_crate_added", name = name));
+    }
+    info!(
+        "{}",
+        t!(
+            "vendored_crates_summary",
+            count = added.len(),
+            path = dest.display()
+        )
+    );
+    Ok(())
+}
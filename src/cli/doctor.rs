@@ -0,0 +1,41 @@
+use super::{output, ExecStatus, GlobalOpts, ManagerSubcommands};
+use crate::core::doctor::{self, Status};
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+/// Execute `doctor` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    let ManagerSubcommands::Doctor { fix } = subcommand else {
+        return Ok(ExecStatus::default());
+    };
+
+    let mut report = doctor::diagnose();
+    if *fix {
+        report.fix()?;
+    }
+
+    if GlobalOpts::get().json_output() {
+        output::print_json("doctor", &report)?;
+    } else {
+        for diag in &report.checks {
+            let status = match diag.status {
+                Status::Pass => "PASS".green(),
+                Status::Warn => "WARN".yellow(),
+                Status::Fail => "FAIL".red(),
+            };
+            let suffix = if diag.fixed {
+                format!(" ({})", t!("fixed")).green()
+            } else if diag.fixable {
+                format!(" ({})", t!("doctor_fix_hint")).normal()
+            } else {
+                "".normal()
+            };
+            println!("[{status}] {}: {}{suffix}", diag.check, diag.message);
+        }
+    }
+
+    if report.has_failures() {
+        bail!(t!("doctor_found_problems"));
+    }
+    Ok(ExecStatus::new_executed().no_pause(true))
+}
@@ -0,0 +1,25 @@
+//! Separated module to handle `export` command in command line.
+
+use super::{ExecStatus, ManagerSubcommands};
+use crate::core::export;
+use anyhow::Result;
+use rim_common::types::TomlParser;
+use rim_common::utils;
+
+/// Execute `export` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    let ManagerSubcommands::Export { insecure, output } = subcommand else {
+        return Ok(ExecStatus::default());
+    };
+
+    let manifest = blocking!(export::export_manifest(*insecure))?;
+    let content = manifest.to_toml()?;
+    if let Some(path) = output {
+        utils::write_file(path, &content, false)?;
+        info!("{}", t!("export_manifest_written", path = path.display()));
+    } else {
+        print!("{content}");
+    }
+
+    Ok(ExecStatus::new_executed().no_pause(true))
+}
@@ -0,0 +1,131 @@
+//! Separated module to handle `history` and `undo` commands in command line.
+
+use super::apply::{format_step, print_plan};
+use super::{common, output, ExecStatus, GlobalOpts, ManagerSubcommands};
+use crate::core::apply::ApplyPlan;
+use crate::core::history::{self, Entry, History, Outcome};
+use crate::fingerprint::InstallationRecord;
+use crate::ToolkitManifestExt;
+use anyhow::{bail, Result};
+use colored::Colorize;
+use rim_common::types::ToolkitManifest;
+use rim_common::utils::CliProgress;
+use serde::Serialize;
+
+/// Execute `history` or `undo` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    match subcommand {
+        ManagerSubcommands::History { limit } => show_history(*limit)?,
+        ManagerSubcommands::Undo { insecure, dry_run } => undo(*insecure, *dry_run)?,
+        _ => return Ok(ExecStatus::default()),
+    }
+    Ok(ExecStatus::new_executed().no_pause(true))
+}
+
+fn show_history(limit: Option<usize>) -> Result<()> {
+    let history = History::load_from_config_dir()?;
+    let skipped = limit.map_or(0, |n| history.entries.len().saturating_sub(n));
+    let entries = &history.entries[skipped..];
+
+    if GlobalOpts::get().json_output() {
+        return output::print_json("history", entries);
+    }
+    if entries.is_empty() {
+        println!("{}", t!("history_empty"));
+    }
+    for entry in entries {
+        print_entry(entry);
+    }
+    Ok(())
+}
+
+fn print_entry(entry: &Entry) {
+    let result = match entry.result {
+        Outcome::Success => "SUCCESS".green(),
+        Outcome::Failure => "FAILURE".red(),
+    };
+    let toolkit = match (&entry.toolkit_before, &entry.toolkit_after) {
+        (Some(before), Some(after)) if before != after => format!("{before} -> {after}"),
+        (_, Some(version)) | (Some(version), None) => version.clone(),
+        (None, None) => String::new(),
+    };
+    let mut notes = vec![];
+    if let Some(id) = entry.undoes {
+        notes.push(t!("history_undoes", id = id));
+    }
+    if let Some(id) = entry.undone_by {
+        notes.push(t!("history_undone_by", id = id));
+    }
+    let notes = if notes.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", notes.join(", "))
+    };
+
+    println!(
+        "#{:<4} {}  [{result}] {} {toolkit}{notes}",
+        entry.id,
+        entry.time.format("%Y-%m-%d %H:%M:%S"),
+        entry.operation
+    );
+    for step in &entry.changes {
+        println!("        {}", format_step(step));
+    }
+    if let Some(error) = &entry.error {
+        println!("        {}", error.red());
+    }
+}
+
+#[derive(Serialize)]
+struct UndoOutput<'a> {
+    entry: u32,
+    plan: &'a ApplyPlan,
+}
+
+fn undo(insecure: bool, dry_run: bool) -> Result<()> {
+    let history = History::load_from_config_dir()?;
+    let Some(entry) = history.last_undoable() else {
+        bail!(t!("undo_nothing"));
+    };
+    let record = InstallationRecord::load_from_config_dir()?;
+    let manifest = ToolkitManifest::load_from_install_dir()?;
+    let plan = entry.undo_plan(&record, manifest.toolchain.name())?;
+
+    if GlobalOpts::get().json_output() {
+        output::print_json(
+            "undo",
+            UndoOutput {
+                entry: entry.id,
+                plan: &plan,
+            },
+        )?;
+    } else if plan.is_empty() {
+        println!(
+            "{}",
+            t!(
+                "undo_no_changes",
+                id = entry.id,
+                operation = entry.operation
+            )
+        );
+    } else {
+        let title = t!("undo_plan", id = entry.id, operation = entry.operation);
+        print_plan(&plan, &title);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    // an empty plan still needs to be executed, so the entry is marked as undone
+    if !plan.is_empty() && !common::confirm(t!("undo_confirmation"), true)? {
+        return Ok(());
+    }
+
+    blocking!(history::undo(
+        entry.id,
+        plan,
+        CliProgress::default(),
+        insecure
+    ))?;
+    info!("{}", t!("task_success"));
+    Ok(())
+}
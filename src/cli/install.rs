@@ -15,7 +15,7 @@ use crate::default_install_dir;
 use super::common::{
     question_single_choice, ComponentChoices, ComponentDecoration, ComponentListBuilder,
 };
-use super::{ExecStatus, Installer, ManagerSubcommands};
+use super::{ExecStatus, Installer};
 
 use anyhow::{bail, Result};
 use rim_common::types::ToolkitManifest;
@@ -250,10 +250,11 @@ fn custom_component_choices<'a>(
 
     // convert the input indexes to `ComponentChoices`,
     // also we need to add missing `required` tools even if the user didn't choose it.
-    Ok(common::component_choices_with_constrains(
-        all_components,
-        |idx, c| (c.required && !c.installed) || index_set.contains(&(idx + 1)),
-    ))
+    let selections = common::component_choices_with_constrains(all_components, |idx, c| {
+        (c.required && !c.installed) || index_set.contains(&(idx + 1))
+    });
+    common::warn_auto_selected_dependencies(&selections, &index_set);
+    Ok(selections)
 }
 
 /// Read user response of what set of components they want to install.
@@ -300,11 +301,3 @@ fn ask_tool_source(name: String, default: Option<&str>) -> Result<String> {
         default.unwrap_or_default(),
     )
 }
-
-pub(super) fn execute_manager(manager: &ManagerSubcommands) -> Result<ExecStatus> {
-    let ManagerSubcommands::Install { version, .. } = manager else {
-        return Ok(ExecStatus::default());
-    };
-
-    todo!("install dist with version '{version}'");
-}
@@ -4,6 +4,7 @@ use anyhow::Result;
 use clap::Subcommand;
 use rim_common::types::ToolkitManifest;
 
+use super::output::{self, ComponentInfo, ToolkitInfo};
 use super::{handle_user_choice, ExecStatus, GlobalOpts, ManagerSubcommands};
 use crate::{
     components,
@@ -22,7 +23,7 @@ pub enum ListCommand {
 }
 
 impl ListCommand {
-    fn execute(&self, installed: bool) -> Result<()> {
+    fn execute(&self, installed: bool, insecure: bool) -> Result<()> {
         match self {
             Self::Component => {
                 let manifest = if installed {
@@ -32,13 +33,18 @@ impl ListCommand {
                 };
                 list_components(installed, true, manifest.as_ref())
             }
-            Self::Toolkit => blocking!(list_toolkits(installed)),
+            Self::Toolkit => blocking!(list_toolkits(installed, insecure)),
         }
     }
 }
 
 pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<ExecStatus> {
-    let ManagerSubcommands::List { installed, command } = cmd else {
+    let ManagerSubcommands::List {
+        installed,
+        insecure,
+        command,
+    } = cmd
+    else {
         return Ok(ExecStatus::default());
     };
 
@@ -46,7 +52,7 @@ pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<ExecStatus> {
     // asked from user interaction until determined, which means it couldn't be `none`,
     // but we still fallback to default in case something bad happens.
     let sub_cmd = command.unwrap_or_default();
-    sub_cmd.execute(*installed)?;
+    sub_cmd.execute(*installed, *insecure)?;
 
     Ok(ExecStatus::new_executed().no_pause(true))
 }
@@ -70,12 +76,18 @@ pub(crate) fn list_components(
     check_record: bool,
     manifest: Option<&ToolkitManifest>,
 ) -> Result<()> {
-    let mut components = if check_record {
-        let fp = InstallationRecord::load_from_config_dir()?;
-        components::all_components_from_installation(&fp)?
+    let record = check_record
+        .then(InstallationRecord::load_from_config_dir)
+        .transpose()?;
+    let mut components = if let Some(fp) = &record {
+        components::all_components_from_installation(fp)?
     } else {
         vec![]
     };
+    let extra_toolchains = extra_toolchains(
+        record.as_ref(),
+        (!installed_only).then_some(manifest).flatten(),
+    );
 
     if !installed_only {
         // we need to insert the non-installed components by reading
@@ -90,6 +102,18 @@ pub(crate) fn list_components(
         }
     }
 
+    if GlobalOpts::get().json_output() {
+        let infos = components
+            .iter()
+            .filter(|comp| !installed_only || comp.installed)
+            .map(ComponentInfo::from)
+            .chain(extra_toolchains.iter().map(|(name, channel, installed)| {
+                ComponentInfo::extra_toolchain(name, channel, *installed)
+            }))
+            .collect::<Vec<_>>();
+        return output::print_json("list-component", infos);
+    }
+
     let verbose = GlobalOpts::get().verbose;
     let mut stdout = std::io::stdout();
 
@@ -98,16 +122,21 @@ pub(crate) fn list_components(
         let installed_comps = components
             .iter()
             .filter_map(|comp| {
-                comp.installed.then_some(if verbose {
-                    let version = comp
-                        .version
+                let version = if verbose {
+                    comp.version
                         .as_ref()
                         .map(|ver| format!(" {ver}"))
-                        .unwrap_or_default();
-                    format!("{}{version}", comp.display_name)
+                        .unwrap_or_default()
+                } else {
+                    String::new()
+                };
+                let held_suffix = if comp.held {
+                    format!(" ({})", t!("held"))
                 } else {
-                    comp.display_name.clone()
-                })
+                    String::new()
+                };
+                comp.installed
+                    .then(|| format!("{}{version}{held_suffix}", comp.display_name))
             })
             .collect::<Vec<_>>();
         if installed_comps.is_empty() {
@@ -127,7 +156,9 @@ pub(crate) fn list_components(
             } else {
                 String::new()
             };
-            let installed_suffix = if comp.installed {
+            let installed_suffix = if comp.held {
+                format!(" ({}, {})", t!("installed"), t!("held"))
+            } else if comp.installed {
                 format!(" ({})", t!("installed"))
             } else {
                 String::new()
@@ -135,11 +166,55 @@ pub(crate) fn list_components(
             writeln!(&mut stdout, "{}{version}{installed_suffix}", comp.name)?;
         }
     }
+
+    if !extra_toolchains.is_empty() {
+        writeln!(&mut stdout, "\n{}:", t!("extra_toolchains"))?;
+        for (name, channel, installed) in extra_toolchains {
+            let installed_suffix = if installed && !installed_only {
+                format!(" ({})", t!("installed"))
+            } else {
+                String::new()
+            };
+            writeln!(&mut stdout, "{name} {channel}{installed_suffix}")?;
+        }
+    }
     Ok(())
 }
 
-async fn list_toolkits(installed_only: bool) -> Result<()> {
+/// Get the name, channel and the installation state of every additional toolchain,
+/// from the installation `record` and the optional `manifest`.
+fn extra_toolchains(
+    record: Option<&InstallationRecord>,
+    manifest: Option<&ToolkitManifest>,
+) -> Vec<(String, String, bool)> {
+    let mut toolchains = record
+        .into_iter()
+        .flat_map(|fp| &fp.extra_toolchains)
+        .map(|(name, rec)| (name.clone(), rec.version().to_string(), true))
+        .collect::<Vec<_>>();
+    let not_installed = manifest
+        .into_iter()
+        .flat_map(|mf| &mf.toolchain.extra_toolchains)
+        .filter(|(name, tc)| {
+            !toolchains
+                .iter()
+                .any(|(n, channel, _)| n == *name && channel == &tc.channel)
+        })
+        .map(|(name, tc)| (name.clone(), tc.channel.clone(), false))
+        .collect::<Vec<_>>();
+    toolchains.extend(not_installed);
+    toolchains
+}
+
+async fn list_toolkits(installed_only: bool, insecure: bool) -> Result<()> {
     let maybe_installed_tk = Toolkit::installed(false).await?;
+    if GlobalOpts::get().json_output() {
+        let installed = match maybe_installed_tk {
+            Some(mutex) => Some(mutex.lock().await.clone()),
+            None => None,
+        };
+        return list_toolkits_json(installed_only, installed.as_ref(), insecure).await;
+    }
     let mut stdout = std::io::stdout();
 
     writeln!(&mut stdout)?;
@@ -151,7 +226,7 @@ async fn list_toolkits(installed_only: bool) -> Result<()> {
             writeln!(&mut stdout, "{}", t!("no_toolkit_installed"))?;
         }
     } else {
-        let all_toolkits = toolkits_from_server(false).await?
+        let all_toolkits = toolkits_from_server(insecure).await?
             .into_iter()
             .map(|tk| async move {
                 let installed_suffix = if matches!(maybe_installed_tk, Some(mutex) if *mutex.lock().await == tk) {
@@ -168,3 +243,23 @@ async fn list_toolkits(installed_only: bool) -> Result<()> {
     }
     Ok(())
 }
+
+async fn list_toolkits_json(
+    installed_only: bool,
+    installed: Option<&Toolkit>,
+    insecure: bool,
+) -> Result<()> {
+    let infos = if installed_only {
+        installed
+            .into_iter()
+            .map(|tk| ToolkitInfo::new(tk, true))
+            .collect::<Vec<_>>()
+    } else {
+        toolkits_from_server(insecure)
+            .await?
+            .iter()
+            .map(|tk| ToolkitInfo::new(tk, installed == Some(tk)))
+            .collect()
+    };
+    output::print_json("list-toolkit", infos)
+}
@@ -1,13 +1,23 @@
 //! Contains all the definition of command line arguments.
 
+mod apply;
+mod cache;
 mod check;
 mod common;
 mod component;
+mod crates;
+mod doctor;
+mod export;
+mod history;
 mod install;
 mod list;
+mod output;
+mod repair;
+mod switch;
 mod tryit;
 mod uninstall;
 mod update;
+mod verify;
 
 use crate::core::GlobalOpts;
 use anyhow::{anyhow, bail, Result};
@@ -25,6 +35,7 @@ use url::Url;
 // Re-exports
 pub use common::pause;
 pub use component::ComponentCommand;
+pub use output::OutputFormat;
 
 /// Receive a list of function calls, that waits to be executed,
 /// if any of them returning [`ExecStatus`] which the `executed` flag is `true`,
@@ -179,6 +190,9 @@ pub struct Installer {
     /// Allow insecure connections when download packages from server.
     #[arg(short = 'k', long)]
     insecure: bool,
+    /// Allow using manifests that are unsigned or failed signature verification.
+    #[arg(long)]
+    allow_unsigned: bool,
 
     /// Specify another language to display
     #[arg(short, long, value_name = "LANG", value_parser = possible_lang())]
@@ -284,6 +298,16 @@ pub struct Manager {
     /// uninstallation.
     #[arg(long, conflicts_with = "no_modify_path")]
     no_modify_env: bool,
+    /// Allow using manifests that are unsigned or failed signature verification.
+    #[arg(long)]
+    allow_unsigned: bool,
+    /// Specify the output format of the commands that print a result.
+    ///
+    /// When using `json`, a versioned JSON document will be printed on `stdout`,
+    /// log messages will be printed on `stderr` instead, and the command fails
+    /// if a confirmation is required, unless `--yes` is given.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
 
     /// Specify another language to display
     #[arg(short, long, value_name = "LANG", value_parser = possible_lang())]
@@ -307,7 +331,9 @@ impl ExecutableCommand for Installer {
             self.no_modify_env,
             self.no_modify_path,
             self.lang.as_deref(),
+            false,
         )?;
+        GlobalOpts::set_allow_unsigned(self.allow_unsigned);
         blocking!(install::execute_installer(self))
     }
 
@@ -326,7 +352,10 @@ impl ExecutableCommand for Manager {
             self.no_modify_env,
             self.no_modify_path,
             self.lang.as_deref(),
+            self.stdout_reserved(),
         )?;
+        GlobalOpts::set_allow_unsigned(self.allow_unsigned);
+        GlobalOpts::set_output_format(self.format);
 
         let Some(subcmd) = &self.command else {
             return ManagerSubcommands::from_interaction()?.execute();
@@ -341,18 +370,46 @@ impl ExecutableCommand for Manager {
 
     #[cfg(feature = "gui")]
     fn no_gui(&self) -> bool {
-        if self.no_gui {
+        // machine-readable output is meant for scripts, not for GUI
+        if self.no_gui || self.format == OutputFormat::Json {
             return true;
         }
 
         // (manager only) If any of these subcommand was invoked, do not start GUI
         matches!(
             self.command,
-            Some(ManagerSubcommands::Check { .. } | ManagerSubcommands::TryIt { .. })
+            Some(
+                ManagerSubcommands::Check { .. }
+                    | ManagerSubcommands::Switch { .. }
+                    | ManagerSubcommands::Pin { .. }
+                    | ManagerSubcommands::Unpin
+                    | ManagerSubcommands::TryIt { .. }
+                    | ManagerSubcommands::Doctor { .. }
+                    | ManagerSubcommands::Apply { .. }
+                    | ManagerSubcommands::Export { .. }
+                    | ManagerSubcommands::Cache { .. }
+                    | ManagerSubcommands::Crates { .. }
+                    | ManagerSubcommands::History { .. }
+                    | ManagerSubcommands::Undo { .. }
+                    | ManagerSubcommands::Verify { .. }
+                    | ManagerSubcommands::Repair { .. }
+            )
         )
     }
 }
 
+impl Manager {
+    /// Return `true` if the `stdout` is reserved for the output of a command,
+    /// which means the logs should be printed to `stderr` instead.
+    fn stdout_reserved(&self) -> bool {
+        self.format == OutputFormat::Json
+            || matches!(
+                self.command,
+                Some(ManagerSubcommands::Export { output: None, .. })
+            )
+    }
+}
+
 impl TryFrom<Vec<String>> for Manager {
     type Error = anyhow::Error;
 
@@ -364,18 +421,6 @@ impl TryFrom<Vec<String>> for Manager {
 #[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug, Clone)]
 pub enum ManagerSubcommands {
-    /// Install a specific dist version
-    #[command(hide = true)]
-    Install {
-        /// Allow insecure connections when download packages from server.
-        #[arg(short = 'k', long)]
-        insecure: bool,
-        #[arg(value_name = "VERSION")]
-        version: String,
-        /// Specify another server to download Rust toolchain components.
-        #[arg(long, value_name = "URL", value_hint = ValueHint::Url)]
-        rustup_dist_server: Option<Url>,
-    },
     /// Update toolkit and/or this installation manager
     ///
     /// By default, this will update both the toolkit and manager, if you just want to update
@@ -405,19 +450,109 @@ pub enum ManagerSubcommands {
         #[arg(long, value_name = "URL", value_hint = ValueHint::Url)]
         rustup_dist_server: Option<Url>,
     },
+    /// Switch to a specific toolkit version, which could be older than the installed one
+    ///
+    /// Installed components will be updated or downgraded to the versions provided by
+    /// the target toolkit, and the ones that are not provided by it will be removed.
+    #[command(alias = "install")]
+    Switch {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// The toolkit version to switch to, check `list toolkit` for available versions
+        #[arg(value_name = "VERSION")]
+        version: String,
+        /// Specify another server to download Rust toolchain components.
+        #[arg(long, value_name = "URL", value_hint = ValueHint::Url)]
+        rustup_dist_server: Option<Url>,
+    },
+    /// Stay on a specific toolkit version, switching to it if needed
+    ///
+    /// Toolkit updates will no longer be offered until `unpin` is called.
+    Pin {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// The toolkit version to pin to
+        #[arg(value_name = "VERSION")]
+        version: String,
+    },
+    /// Remove the toolkit version pin, allowing toolkit updates again
+    Unpin,
     /// Display a list of toolkits or components
     List {
         /// Show installed only
         #[arg(long)]
         installed: bool,
+        /// Allow insecure connections when fetching toolkits from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
         #[command(subcommand)]
         command: Option<list::ListCommand>,
     },
-    /// Install or uninstall components
+    /// Install, uninstall or hold components
     Component {
         #[command(subcommand)]
         command: component::ComponentCommand,
     },
+    /// Install, update or remove components to match a lockfile
+    Apply {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// Only show the changes to be made, without applying them
+        #[arg(long)]
+        dry_run: bool,
+        /// Path to the lockfile
+        #[arg(value_name = "PATH", value_hint = ValueHint::FilePath)]
+        lockfile: PathBuf,
+    },
+    /// Export the current installation as a toolkit manifest
+    ///
+    /// The exported manifest can be used to reproduce the same installation on another machine,
+    /// by running the installer with `--manifest <PATH>` option.
+    Export {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// Write the manifest to a file instead of printing it
+        #[arg(short, long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+    /// Manage the cache of downloaded packages
+    ///
+    /// Downloaded packages are kept in cache, so that reinstalling or switching between
+    /// toolkit versions doesn't need to download the unchanged packages again.
+    Cache {
+        #[command(subcommand)]
+        command: cache::CacheCommand,
+    },
+    /// Manage the vendored crates that can be used without network access
+    ///
+    /// Vendored crates replace the `crates-io` source in cargo configuration,
+    /// they are usually shipped along with offline packages.
+    Crates {
+        #[command(subcommand)]
+        command: crates::CratesCommand,
+    },
+    /// Show the history of operations made to the installation
+    History {
+        /// Show the most recent entries only
+        #[arg(short = 'n', long, value_name = "NUMBER")]
+        limit: Option<usize>,
+    },
+    /// Revert the last successful operation
+    ///
+    /// Such as reinstalling the previous versions of tools that were replaced by the last update.
+    /// Running it again reverts the operation before that.
+    Undo {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// Only show the changes to be made, without reverting them
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Uninstall individual components or everything.
     Uninstall {
         /// Keep this manager tool, only uninstall toolkit
@@ -440,18 +575,50 @@ pub enum ManagerSubcommands {
         /// Additional args to run `cargo clippy`, see all options with `cargo clippy --help`.
         extra_args: Vec<String>,
     },
+    /// Check the files of installed components for missing or modified ones
+    Verify {
+        /// The list of components to verify, defaulting to all installed components
+        #[arg(value_name = "COMPONENTS", value_delimiter = ',')]
+        components: Vec<String>,
+    },
+    /// Reinstall the missing or corrupted components of the current installation
+    ///
+    /// Only the broken parts will be reinstalled, using the toolkit manifest that the
+    /// current installation was made from, healthy components will not be touched.
+    Repair {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+        /// Only show the broken parts, without repairing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Diagnose the current installation, and fix the problems if possible
+    Doctor {
+        /// Try fixing the problems that can be fixed automatically
+        #[arg(long)]
+        fix: bool,
+    },
 }
 
 impl ExecutableCommand for ManagerSubcommands {
     fn execute(&self) -> Result<ExecStatus> {
         return_if_executed! {
-            install::execute_manager(self)?,
             update::execute(self)?,
+            switch::execute(self)?,
             list::execute(self)?,
             component::execute(self)?,
+            apply::execute(self)?,
+            export::execute(self)?,
+            cache::execute(self)?,
+            crates::execute(self)?,
+            history::execute(self)?,
             uninstall::execute(self)?,
             tryit::execute(self)?,
-            check::execute(self)?
+            check::execute(self)?,
+            verify::execute(self)?,
+            repair::execute(self)?,
+            doctor::execute(self)?
         }
         Ok(ExecStatus::default())
     }
@@ -501,7 +668,7 @@ impl ManagerSubcommands {
         let maybe_cmd = handle_user_choice!(
             t!("choose_an_option"), 5,
             {
-                1 t!("manage_components") => { Some(Self::Component { command: ComponentCommand::Uninstall { components: vec![] } }) },
+                1 t!("manage_components") => { Some(Self::Component { command: ComponentCommand::Uninstall { components: vec![], force: false, cascade: false } }) },
                 2 t!("update") => {
                     let insecure = handle_user_choice!(
                         t!("choose_an_option"), 1,
@@ -521,7 +688,7 @@ impl ManagerSubcommands {
                             2 t!("installed") => { true }
                         }
                     );
-                    Some(Self::List { installed, command: list::ask_list_command()? })
+                    Some(Self::List { installed, insecure: false, command: list::ask_list_command()? })
                 },
                 5 t!("cancel") => { None }
             }
@@ -591,7 +758,7 @@ impl ManagerSubcommands {
                 },
                 2 t!("remove") => {
                     let components = component::collect_components_to_remove()?;
-                    Self::Component { command: ComponentCommand::Uninstall { components } }
+                    Self::Component { command: ComponentCommand::Uninstall { components, force: false, cascade: false } }
                 },
                 3 t!("back") => { return Ok(false) }
             }
@@ -630,9 +797,14 @@ fn setup(
     no_modify_env: bool,
     no_modify_path: bool,
     lang: Option<&str>,
+    log_to_stderr: bool,
 ) -> Result<()> {
     // Setup logger
-    utils::Logger::new().verbose(verbose).quiet(quiet).setup()?;
+    utils::Logger::new()
+        .verbose(verbose)
+        .quiet(quiet)
+        .stderr(log_to_stderr)
+        .setup()?;
 
     // Setup locale
     if let Some(lang_str) = lang {
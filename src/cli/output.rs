@@ -0,0 +1,239 @@
+//! Machine-readable output of the commands, enabled by `--format json`.
+//!
+//! Every document printed by a command has the same envelope:
+//!
+//! ```json
+//! { "schema_version": 1, "command": "list-component", "data": ... }
+//! ```
+//!
+//! The types here are decoupled from the internal ones on purpose, so that
+//! internal refactoring won't break the schema that scripts rely on.
+//! Make sure to bump [`SCHEMA_VERSION`] when making breaking changes to any of them.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use rim_common::types::ToolKind;
+use serde::Serialize;
+
+use super::common::VersionDiffMap;
+use crate::components::{Component, ComponentType};
+use crate::fingerprint::InstallationRecord;
+use crate::toolkit::Toolkit;
+
+/// The version of the JSON schema.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+/// The format of a command's output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    // human-readable, localized text
+    #[default]
+    Text,
+    // a versioned JSON document printed on `stdout`
+    Json,
+}
+
+#[derive(Serialize)]
+struct Document<'a, T> {
+    schema_version: u32,
+    command: &'a str,
+    data: T,
+}
+
+/// Print `data` of a `command` on `stdout` as a versioned JSON document.
+pub(crate) fn print_json<T: Serialize>(command: &str, data: T) -> Result<()> {
+    let doc = Document {
+        schema_version: SCHEMA_VERSION,
+        command,
+        data,
+    };
+    println!("{}", serde_json::to_string_pretty(&doc)?);
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ComponentInfo {
+    name: String,
+    display_name: String,
+    category: String,
+    kind: &'static str,
+    version: Option<String>,
+    required: bool,
+    optional: bool,
+    installed: bool,
+    held: bool,
+}
+
+impl From<&Component> for ComponentInfo {
+    fn from(value: &Component) -> Self {
+        let kind = match value.kind {
+            ComponentType::Tool => "tool",
+            ComponentType::ToolchainComponent => "toolchain-component",
+            ComponentType::ToolchainProfile => "toolchain-profile",
+            ComponentType::ToolchainTarget => "toolchain-target",
+        };
+        Self {
+            name: value.name.clone(),
+            display_name: value.display_name.clone(),
+            category: value.category.clone(),
+            kind,
+            version: value.version.clone(),
+            required: value.required,
+            optional: value.optional,
+            installed: value.installed,
+            held: value.held,
+        }
+    }
+}
+
+impl ComponentInfo {
+    /// Info of an additional toolchain, which is listed along with the components.
+    pub(crate) fn extra_toolchain(name: &str, channel: &str, installed: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            category: t!("extra_toolchains").to_string(),
+            kind: "extra-toolchain",
+            version: Some(channel.to_string()),
+            required: false,
+            optional: false,
+            installed,
+            held: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ToolkitInfo {
+    name: String,
+    version: String,
+    edition: Option<String>,
+    installed: bool,
+}
+
+impl ToolkitInfo {
+    pub(crate) fn new(toolkit: &Toolkit, installed: bool) -> Self {
+        Self {
+            name: toolkit.name.clone(),
+            version: toolkit.version.clone(),
+            edition: toolkit.edition.clone(),
+            installed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct VersionDiffInfo {
+    name: String,
+    from: Option<String>,
+    to: Option<String>,
+    newly_supported: bool,
+    held: bool,
+}
+
+/// Convert a [`VersionDiffMap`] into a list sorted by component names,
+/// so that the output is stable across runs.
+pub(crate) fn version_diffs(map: &VersionDiffMap) -> Vec<VersionDiffInfo> {
+    let mut diffs = map
+        .iter()
+        .map(|(name, diff)| VersionDiffInfo {
+            name: name.to_string(),
+            from: diff.from.map(ToString::to_string),
+            to: diff.to.map(ToString::to_string),
+            newly_supported: diff.is_newly_supported,
+            held: diff.held,
+        })
+        .collect::<Vec<_>>();
+    diffs.sort_by(|a, b| a.name.cmp(&b.name));
+    diffs
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ToolchainInfo {
+    channel: String,
+    components: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ExtraToolchainInfo {
+    name: String,
+    channel: String,
+    components: Vec<String>,
+    alias: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct InstalledToolInfo {
+    name: String,
+    kind: ToolKind,
+    version: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct InstallationInfo {
+    install_dir: PathBuf,
+    name: Option<String>,
+    version: Option<String>,
+    edition: Option<String>,
+    toolchain: Option<ToolchainInfo>,
+    extra_toolchains: Vec<ExtraToolchainInfo>,
+    tools: Vec<InstalledToolInfo>,
+    held: Vec<String>,
+}
+
+impl From<&InstallationRecord> for InstallationInfo {
+    fn from(value: &InstallationRecord) -> Self {
+        let toolchain = value
+            .installed_toolchain()
+            .map(|(channel, components)| ToolchainInfo {
+                channel: channel.to_string(),
+                components: components.to_vec(),
+            });
+        let extra_toolchains = value
+            .extra_toolchains
+            .iter()
+            .map(|(name, rec)| ExtraToolchainInfo {
+                name: name.clone(),
+                channel: rec.version().to_string(),
+                components: rec.components.clone(),
+                alias: rec.alias().map(ToString::to_string),
+            })
+            .collect();
+        let mut tools = value
+            .tools
+            .iter()
+            .map(|(name, rec)| InstalledToolInfo {
+                name: name.clone(),
+                kind: rec.tool_kind(),
+                version: rec.version().map(ToString::to_string),
+            })
+            .collect::<Vec<_>>();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            install_dir: value.install_dir.clone(),
+            name: value.name.clone(),
+            version: value.version.clone(),
+            edition: value.edition.clone(),
+            toolchain,
+            extra_toolchains,
+            tools,
+            held: value.held.iter().cloned().collect(),
+        }
+    }
+}
+
+/// The result of `update` command.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct UpdateInfo {
+    /// The version of the installed toolkit, `None` if no toolkit was installed.
+    pub(crate) current_version: Option<String>,
+    /// The version of the toolkit that was updated to, `None` if there's no update.
+    pub(crate) target_version: Option<String>,
+    /// The version differences of each component in the target toolkit.
+    pub(crate) components: Vec<VersionDiffInfo>,
+    /// The names of components that were updated.
+    pub(crate) updated: Vec<String>,
+}
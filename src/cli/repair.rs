@@ -0,0 +1,69 @@
+//! Separated module to handle `repair` command in command line.
+
+use super::{common, output, ExecStatus, GlobalOpts, ManagerSubcommands};
+use crate::core::repair::RepairPlan;
+use crate::fingerprint::InstallationRecord;
+use crate::ToolkitManifestExt;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rim_common::types::{TomlParser, ToolkitManifest};
+use rim_common::utils::CliProgress;
+
+/// Execute `repair` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    let ManagerSubcommands::Repair { insecure, dry_run } = subcommand else {
+        return Ok(ExecStatus::default());
+    };
+
+    let record = InstallationRecord::load_from_config_dir()?;
+    let manifest_path = record.install_dir.join(ToolkitManifest::FILENAME);
+    let manifest = ToolkitManifest::load_validated(&manifest_path)
+        .with_context(|| t!("doctor_manifest_missing", path = manifest_path.display()))?;
+    let plan = RepairPlan::detect(&record, &manifest);
+
+    if GlobalOpts::get().json_output() {
+        output::print_json("repair", &plan)?;
+    } else {
+        show_plan(&plan);
+    }
+    if *dry_run || plan.is_empty() {
+        return Ok(ExecStatus::new_executed().no_pause(true));
+    }
+
+    if !common::confirm(t!("repair_confirmation"), true)? {
+        return Ok(ExecStatus::new_executed());
+    }
+    blocking!(plan.apply(&record, manifest, CliProgress::default(), *insecure))?;
+    info!("{}", t!("task_success"));
+
+    Ok(ExecStatus::new_executed())
+}
+
+fn show_plan(plan: &RepairPlan) {
+    for name in &plan.unrepairable_tools {
+        warn!("{}", t!("repair_tool_not_in_manifest", name = name));
+    }
+    if plan.is_empty() {
+        println!("{}", t!("repair_nothing_broken"));
+        return;
+    }
+
+    println!("{}\n", t!("repair_plan"));
+    let sign = "*".yellow();
+    if plan.toolchain {
+        println!("  {sign} {}", t!("toolchain"));
+    }
+    for component in &plan.toolchain_components {
+        println!("  {sign} {component}");
+    }
+    for tool in &plan.tools {
+        println!("  {sign} {tool}");
+    }
+    if plan.env {
+        println!("  {sign} {}", t!("env_config"));
+    }
+    if plan.cargo_config {
+        println!("  {sign} {}", t!("cargo_config"));
+    }
+    println!();
+}
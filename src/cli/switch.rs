@@ -0,0 +1,194 @@
+//! Separated module to handle `switch`, `pin` and `unpin` commands in command line.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+use rim_common::types::{Configuration, ToolkitManifest};
+use rim_common::utils::CliProgress;
+use url::Url;
+
+use super::output::{self, UpdateInfo};
+use super::update::change_toolkit;
+use super::{ExecStatus, GlobalOpts, ManagerSubcommands};
+use crate::core::history::{Operation, Tracker};
+use crate::core::toolkit::{is_same_version, toolkit_of_version, Toolkit};
+use crate::fingerprint::InstallationRecord;
+use crate::{AppInfo, ToolkitManifestExt, UninstallConfiguration};
+
+/// Execute `switch`, `pin` or `unpin` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    match subcommand {
+        ManagerSubcommands::Switch {
+            insecure,
+            version,
+            rustup_dist_server,
+        } => {
+            let pinned = Configuration::load_from_config_dir().update.pinned_toolkit;
+            if let Some(pinned) = pinned.filter(|pinned| !is_same_version(pinned, version)) {
+                bail!(t!(
+                    "switch_while_pinned",
+                    pinned = pinned,
+                    version = version
+                ));
+            }
+            let update_info = blocking!(switch_toolkit(version, *insecure, rustup_dist_server))?;
+            if GlobalOpts::get().json_output() {
+                output::print_json("switch", update_info)?;
+            }
+        }
+        ManagerSubcommands::Pin { insecure, version } => pin(version, *insecure)?,
+        ManagerSubcommands::Unpin => unpin()?,
+        _ => return Ok(ExecStatus::default()),
+    }
+    Ok(ExecStatus::new_executed())
+}
+
+/// Install the toolkit of a specific `version`, which could be older than the installed one,
+/// then remove the installed components that are not provided by that version.
+async fn switch_toolkit(
+    version: &str,
+    insecure: bool,
+    rustup_dist_server: &Option<Url>,
+) -> Result<UpdateInfo> {
+    let mut update_info = UpdateInfo::default();
+    let Some(installed) = Toolkit::installed(false).await? else {
+        bail!(t!("no_toolkit_installed"));
+    };
+    let installed = installed.lock().await.clone();
+    update_info.current_version = Some(installed.version.clone());
+
+    if is_same_version(&installed.version, version) {
+        info!(
+            "{}",
+            t!(
+                "toolkit_version_installed",
+                name = installed.name,
+                version = installed.version
+            )
+        );
+        return Ok(update_info);
+    }
+    let target = toolkit_of_version(&installed, version, insecure).await?;
+
+    let tracker = Tracker::start(Operation::Switch);
+    let result = async {
+        let Some(manifest) = change_toolkit(
+            AppInfo::get_installed_dir(),
+            &installed,
+            &target,
+            insecure,
+            None,
+            rustup_dist_server,
+            &mut update_info,
+        )
+        .await?
+        else {
+            return Ok(());
+        };
+        remove_absent_components(&manifest)
+    }
+    .await;
+    tracker.finish(&result);
+    result?;
+
+    info!("{}", t!("task_success"));
+    Ok(update_info)
+}
+
+/// Remove the installed components that are no longer provided by the toolkit `manifest`,
+/// except for the ones that are still required by the remaining components.
+fn remove_absent_components(manifest: &ToolkitManifest) -> Result<()> {
+    let available = manifest
+        .current_target_components(false)?
+        .into_iter()
+        .map(|c| c.name)
+        .collect::<HashSet<_>>();
+    let mut config = UninstallConfiguration::init(CliProgress::default())?;
+    let record = &config.install_record;
+    let absent = record
+        .installed_toolchain_components()
+        .into_iter()
+        .map(|c| c.name)
+        .chain(record.tools.keys().cloned())
+        .filter(|name| !available.contains(name))
+        .collect::<HashSet<_>>();
+
+    // dependents are transitive, so the components required (indirectly) by
+    // a remaining one are all kept.
+    let mut kept = HashSet::new();
+    for name in &absent {
+        let remaining = config
+            .dependents_of(&[name])
+            .into_iter()
+            .filter(|dep| !absent.contains(dep))
+            .collect::<Vec<_>>();
+        if !remaining.is_empty() {
+            warn!(
+                "{}",
+                t!(
+                    "keeping_required_absent_component",
+                    name = name,
+                    dependents = remaining.join(", ")
+                )
+            );
+            kept.insert(name.as_str());
+        }
+    }
+    let should_remove = |name: &str| absent.contains(name) && !kept.contains(name);
+
+    let record = &config.install_record;
+    let toolchain_components = record
+        .installed_toolchain_components()
+        .into_iter()
+        .filter(|c| should_remove(&c.name))
+        .collect::<Vec<_>>();
+    let tools = record
+        .tools
+        .iter()
+        .filter(|(name, _)| should_remove(name))
+        .map(|(name, rec)| (name.clone(), rec.clone()))
+        .collect::<HashMap<_, _>>();
+    if toolchain_components.is_empty() && tools.is_empty() {
+        return Ok(());
+    }
+
+    let mut names = toolchain_components
+        .iter()
+        .map(|c| c.name.as_str())
+        .chain(tools.keys().map(|name| name.as_str()))
+        .collect::<Vec<_>>();
+    names.sort();
+    info!(
+        "{}",
+        t!("removing_absent_components", list = names.join(", "))
+    );
+    config.remove_components(&toolchain_components, tools)
+}
+
+fn pin(version: &str, insecure: bool) -> Result<()> {
+    blocking!(switch_toolkit(version, insecure, &None))?;
+
+    // the switch might be cancelled by user, don't pin to a version that is not installed
+    let Some(installed) = InstallationRecord::load_from_config_dir()?
+        .version
+        .filter(|installed| is_same_version(installed, version))
+    else {
+        return Ok(());
+    };
+    Configuration::load_from_config_dir()
+        .set_pinned_toolkit(Some(installed.clone()))
+        .write()?;
+    info!("{}", t!("toolkit_pinned_to", version = installed));
+    Ok(())
+}
+
+fn unpin() -> Result<()> {
+    let conf = Configuration::load_from_config_dir();
+    let Some(pinned) = conf.update.pinned_toolkit.clone() else {
+        info!("{}", t!("toolkit_not_pinned"));
+        return Ok(());
+    };
+    conf.set_pinned_toolkit(None).write()?;
+    info!("{}", t!("toolkit_unpinned", version = pinned));
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use rim_common::types::ToolkitManifest;
 use rim_common::utils::CliProgress;
 use std::collections::HashSet;
 use std::path::Path;
@@ -16,6 +17,7 @@ use crate::InstallConfiguration;
 use super::common::{
     ComponentChoices, ComponentDecoration, ComponentListBuilder, VersionDiff, VersionDiffMap,
 };
+use super::output::{self, UpdateInfo};
 use super::{common, ExecStatus, GlobalOpts, ManagerSubcommands};
 
 pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<ExecStatus> {
@@ -31,19 +33,25 @@ pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<ExecStatus> {
     };
 
     let update_opt = UpdateOpt::new(CliProgress::default()).insecure(*insecure);
-    if !manager_only {
+    let update_info = if *manager_only {
+        None
+    } else {
         let install_dir = update_opt.install_dir();
-        blocking!(update_toolkit_(
+        Some(blocking!(update_toolkit_(
             install_dir,
             *insecure,
             component.as_deref(),
             rustup_dist_server
-        ))?;
-    }
+        ))?)
+    };
     if !toolkit_only {
         blocking!(update_opt.self_update(false))?;
     }
 
+    if GlobalOpts::get().json_output() {
+        output::print_json("update", update_info.unwrap_or_default())?;
+    }
+
     Ok(ExecStatus::new_executed())
 }
 
@@ -52,16 +60,18 @@ async fn update_toolkit_(
     insecure: bool,
     user_selected_comps: Option<&[String]>,
     rustup_dist_server: &Option<Url>,
-) -> Result<()> {
+) -> Result<UpdateInfo> {
+    let mut update_info = UpdateInfo::default();
     let Some(installed) = Toolkit::installed(false).await? else {
         info!("{}", t!("no_toolkit_installed"));
-        return Ok(());
+        return Ok(update_info);
     };
     let installed = &*installed.lock().await;
+    update_info.current_version = Some(installed.version.clone());
 
     // get possible update
     let Some(latest_toolkit) = latest_installable_toolkit(installed, insecure).await? else {
-        return Ok(());
+        return Ok(update_info);
     };
     log::debug!(
         "detected latest toolkit: {}-{}",
@@ -69,8 +79,36 @@ async fn update_toolkit_(
         &latest_toolkit.version
     );
 
-    // load the latest manifest
-    let manifest_url = latest_toolkit
+    change_toolkit(
+        install_dir,
+        installed,
+        &latest_toolkit,
+        insecure,
+        user_selected_comps,
+        rustup_dist_server,
+        &mut update_info,
+    )
+    .await?;
+    Ok(update_info)
+}
+
+/// Replace the `installed` toolkit with the `target` one, which could be either
+/// newer or older than the installed one.
+///
+/// Return the manifest of `target` toolkit, or `None` if user cancelled the change.
+pub(super) async fn change_toolkit(
+    install_dir: &Path,
+    installed: &Toolkit,
+    target: &Toolkit,
+    insecure: bool,
+    user_selected_comps: Option<&[String]>,
+    rustup_dist_server: &Option<Url>,
+    update_info: &mut UpdateInfo,
+) -> Result<Option<ToolkitManifest>> {
+    update_info.target_version = Some(target.version.clone());
+
+    // load the target manifest
+    let manifest_url = target
         .manifest_url
         .as_deref()
         .and_then(|s| Url::parse(s).ok())
@@ -90,28 +128,34 @@ async fn update_toolkit_(
 
     let new_components = manifest.current_target_components(false)?;
 
-    // notify user that we will install the latest update to replace their current installation
+    // notify user that we will install the target toolkit to replace their current installation
     info!(
         "{}",
         t!(
             "pre_update_note",
-            target_version = latest_toolkit.version,
+            target_version = target.version,
             current_version = installed.version
         )
     );
 
     let updater = ComponentsUpdater::new(&installed.components, &new_components);
+    update_info.components = output::version_diffs(&updater.version_diff);
     // let user choose if they want to update installed component only, or want to select more components to install
-    if let UpdateOption::Yes(components) = updater.to_update_option(user_selected_comps)? {
-        // install update for selected components
-        let config = InstallConfiguration::new(install_dir, &manifest, CliProgress::default())?
-            .with_rustup_dist_server(rustup_dist_server.clone());
-        config
-            .update(components.into_values().cloned().collect())
-            .await
-    } else {
-        Ok(())
-    }
+    let UpdateOption::Yes(components) = updater.to_update_option(user_selected_comps)? else {
+        return Ok(None);
+    };
+    update_info.updated = components
+        .values()
+        .filter(|c| !updater.is_held(&c.name))
+        .map(|c| c.name.clone())
+        .collect();
+    // install update for selected components
+    let config = InstallConfiguration::new(install_dir, &manifest, CliProgress::default())?
+        .with_rustup_dist_server(rustup_dist_server.clone());
+    config
+        .update(components.into_values().cloned().collect())
+        .await?;
+    Ok(Some(manifest))
 }
 
 enum UpdateOption<'c> {
@@ -129,23 +173,15 @@ impl<'c> ComponentsUpdater<'c> {
         let version_diff = target
             .iter()
             .map(|c| {
-                let mut is_installed = false;
-                let installed_version = installed
-                    .iter()
-                    .find_map(|ic| {
-                        (ic.name == c.name).then(|| {
-                            is_installed = true;
-                            ic.version.as_deref()
-                        })
-                    })
-                    .flatten();
-                let is_newly_supported = !is_installed && c.version.is_some();
+                let installed_comp = installed.iter().find(|ic| ic.name == c.name);
+                let is_newly_supported = installed_comp.is_none() && c.version.is_some();
                 (
                     c.name.as_str(),
                     VersionDiff {
-                        from: installed_version,
+                        from: installed_comp.and_then(|ic| ic.version.as_deref()),
                         to: c.version.as_deref(),
                         is_newly_supported,
+                        held: installed_comp.is_some_and(|ic| ic.held),
                     },
                 )
             })
@@ -164,8 +200,8 @@ impl<'c> ComponentsUpdater<'c> {
         self.version_diff
             .iter()
             .filter_map(|(name, diff)| {
-                // return only the components that are previously installed
-                if diff.is_newly_supported {
+                // return only the components that are previously installed, and not held
+                if diff.is_newly_supported || diff.held {
                     None
                 } else {
                     (diff.from != diff.to).then_some(*name)
@@ -174,6 +210,10 @@ impl<'c> ComponentsUpdater<'c> {
             .collect()
     }
 
+    fn is_held(&self, name: &str) -> bool {
+        self.version_diff.get(name).is_some_and(|diff| diff.held)
+    }
+
     fn to_update_option(&self, user_selected_comps: Option<&[String]>) -> Result<UpdateOption<'c>> {
         let default = self.default_component_choices(user_selected_comps);
         self.handle_update_interaction_(default)
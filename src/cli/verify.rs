@@ -0,0 +1,49 @@
+//! Separated module to handle `verify` command in command line.
+
+use super::{output, ExecStatus, GlobalOpts, ManagerSubcommands};
+use crate::core::verify;
+use crate::fingerprint::{FileState, InstallationRecord};
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+/// Execute `verify` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<ExecStatus> {
+    let ManagerSubcommands::Verify { components } = subcommand else {
+        return Ok(ExecStatus::default());
+    };
+
+    let record = InstallationRecord::load_from_config_dir()?;
+    let reports = verify::verify(&record, components)?;
+
+    if GlobalOpts::get().json_output() {
+        output::print_json("verify", &reports)?;
+    } else {
+        for report in &reports {
+            if !report.verifiable {
+                println!(
+                    "[{}] {}: {}",
+                    "SKIP".normal(),
+                    report.name,
+                    t!("verify_no_file_records")
+                );
+            } else if report.is_intact() {
+                println!("[{}] {}", "PASS".green(), report.name);
+            } else {
+                println!("[{}] {}", "FAIL".red(), report.name);
+                for problem in &report.problems {
+                    let state = match problem.state {
+                        FileState::Missing => t!("missing"),
+                        FileState::Modified => t!("modified"),
+                        FileState::Intact => continue,
+                    };
+                    println!("    {state}: {}", problem.path.display());
+                }
+            }
+        }
+    }
+
+    if reports.iter().any(|r| !r.is_intact()) {
+        bail!(t!("verify_found_problems"));
+    }
+    Ok(ExecStatus::new_executed().no_pause(true))
+}
@@ -0,0 +1,499 @@
+//! Converge an existing installation to the state described by a [`Lockfile`].
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rim_common::types::ToolkitManifest;
+use rim_common::utils::ProgressHandler;
+use serde::{Deserialize, Serialize};
+
+use super::components::{Component, ComponentType, ToolchainComponent};
+use super::history::{Operation, Tracker};
+use super::install::InstallConfiguration;
+use super::parser::fingerprint::{InstallationRecord, ToolRecord};
+use super::parser::lockfile::{Lockfile, ToolkitLock};
+use super::rustup::ToolchainInstaller;
+use super::uninstall::UninstallConfiguration;
+use super::{AppInfo, ToolkitManifestExt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Action {
+    Install,
+    Update,
+    Remove,
+}
+
+/// A single change of a component, which is used for displaying an [`ApplyPlan`],
+/// and also recorded in the [`History`](super::history::History) of operations.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct Step {
+    pub(crate) name: String,
+    pub(crate) action: Action,
+    pub(crate) from: Option<String>,
+    pub(crate) to: Option<String>,
+    /// `true` if the component is held at its installed version,
+    /// such step is only shown to users, but it will be skipped.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) held: bool,
+}
+
+/// The changes that need to be made to an installation to match a [`Lockfile`].
+#[derive(Debug, Serialize)]
+pub(crate) struct ApplyPlan {
+    /// The toolkit meta info to be recorded, `None` if it's already matched.
+    pub(crate) toolkit: Option<ToolkitLock>,
+    pub(crate) steps: Vec<Step>,
+    /// The desired toolchain channel, `None` if the toolchain is not locked.
+    #[serde(skip)]
+    channel: Option<String>,
+    /// `true` if the toolchain will be installed with another channel.
+    #[serde(skip)]
+    switch_channel: bool,
+    /// The channel of the toolchain to uninstall after switching to another channel,
+    /// `None` if it's still needed by an additional toolchain.
+    #[serde(skip)]
+    replaced_channel: Option<String>,
+    /// Components to install or update, including toolchain components and tools.
+    #[serde(skip)]
+    components: Vec<Component>,
+    #[serde(skip)]
+    components_to_remove: Vec<ToolchainComponent>,
+    #[serde(skip)]
+    tools_to_remove: HashMap<String, ToolRecord>,
+}
+
+impl ApplyPlan {
+    /// Compare the `lockfile` with the installation `record` to create a plan.
+    ///
+    /// `profile` is the name of toolchain profile, which is needed when the toolchain
+    /// channel is about to be changed, as the whole toolchain needs to be installed.
+    ///
+    /// Changes of the components that are held (check [`InstallationRecord::is_held`])
+    /// are kept in the plan as [`held`](Step::held) steps, which will not be executed.
+    /// The toolchain components are held along with the toolchain.
+    pub(crate) fn new(lockfile: &Lockfile, record: &InstallationRecord, profile: &str) -> Self {
+        let mut plan = Self {
+            toolkit: None,
+            steps: vec![],
+            channel: None,
+            switch_channel: false,
+            replaced_channel: None,
+            components: vec![],
+            components_to_remove: vec![],
+            tools_to_remove: HashMap::new(),
+        };
+
+        if let Some(lock) = &lockfile.toolkit {
+            let recorded = ToolkitLock {
+                name: record.name.clone(),
+                version: record.version.clone(),
+                edition: record.edition.clone(),
+            };
+            if lock != &recorded {
+                plan.toolkit = Some(lock.clone());
+            }
+        }
+
+        if let Some(rust) = &lockfile.rust {
+            plan.plan_toolchain(&rust.channel, &rust.components, record, profile);
+        }
+        plan.plan_tools(lockfile, record);
+        plan
+    }
+
+    fn plan_toolchain(
+        &mut self,
+        channel: &str,
+        components: &[String],
+        record: &InstallationRecord,
+        profile: &str,
+    ) {
+        let held = record.toolchain_is_held() || record.is_held(profile);
+        let (installed_channel, installed_comps) = record.installed_toolchain().unzip();
+        let installed_comps = installed_comps.unwrap_or_default();
+        let switch_channel = installed_channel != Some(channel);
+        if !held {
+            self.channel = Some(channel.to_string());
+            self.switch_channel = switch_channel;
+            self.replaced_channel = installed_channel
+                .filter(|installed| {
+                    switch_channel
+                        && !record
+                            .extra_toolchains
+                            .values()
+                            .any(|rec| rec.version() == *installed)
+                })
+                .map(ToString::to_string);
+        }
+
+        if switch_channel {
+            // the whole toolchain needs to be installed with the new channel
+            self.steps.push(Step {
+                name: profile.to_string(),
+                action: if installed_channel.is_some() {
+                    Action::Update
+                } else {
+                    Action::Install
+                },
+                from: installed_channel.map(ToString::to_string),
+                to: Some(channel.to_string()),
+                held,
+            });
+            if !held {
+                self.components
+                    .push(Component::new(profile).with_type(ComponentType::ToolchainProfile));
+            }
+        }
+        for comp in components {
+            let installed = installed_comps.contains(comp);
+            if installed && !switch_channel {
+                continue;
+            }
+            self.steps.push(Step {
+                name: comp.clone(),
+                action: if installed {
+                    Action::Update
+                } else {
+                    Action::Install
+                },
+                from: installed_channel
+                    .filter(|_| installed)
+                    .map(ToString::to_string),
+                to: Some(channel.to_string()),
+                held,
+            });
+            if !held {
+                self.components
+                    .push(Component::new(comp).with_type(ComponentType::ToolchainComponent));
+            }
+        }
+        for comp in installed_comps.iter().filter(|c| !components.contains(*c)) {
+            self.steps.push(Step {
+                name: comp.clone(),
+                action: Action::Remove,
+                from: installed_channel.map(ToString::to_string),
+                to: None,
+                held,
+            });
+            if !held {
+                self.components_to_remove
+                    .push(ToolchainComponent::new(comp));
+            }
+        }
+    }
+
+    fn plan_tools(&mut self, lockfile: &Lockfile, record: &InstallationRecord) {
+        for (name, info) in &lockfile.tools {
+            let step = match record.tools.get(name) {
+                None => Step {
+                    name: name.to_string(),
+                    action: Action::Install,
+                    from: None,
+                    to: info.version().map(ToString::to_string),
+                    held: false,
+                },
+                // tools without a locked version are considered matched once installed
+                Some(rec) if info.version().is_some() && info.version() != rec.version() => Step {
+                    name: name.to_string(),
+                    action: Action::Update,
+                    from: rec.version().map(ToString::to_string),
+                    to: info.version().map(ToString::to_string),
+                    held: record.is_held(name),
+                },
+                Some(_) => continue,
+            };
+            if !step.held {
+                self.components
+                    .push(Component::new(name).with_tool_installer(info));
+            }
+            self.steps.push(step);
+        }
+
+        let locked_names = lockfile
+            .tools
+            .iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        let mut tools_to_remove = record
+            .tools
+            .iter()
+            .filter(|(name, _)| !locked_names.contains(&name.as_str()))
+            .collect::<Vec<_>>();
+        tools_to_remove.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, rec) in tools_to_remove {
+            let held = record.is_held(name);
+            self.steps.push(Step {
+                name: name.clone(),
+                action: Action::Remove,
+                from: rec.version().map(ToString::to_string),
+                to: None,
+                held,
+            });
+            if !held {
+                self.tools_to_remove.insert(name.clone(), rec.clone());
+            }
+        }
+    }
+
+    /// Return `true` if there's nothing to change, which means the installation already
+    /// matches the lockfile, except for the components that are held.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.toolkit.is_none() && self.steps.iter().all(|step| step.held)
+    }
+
+    /// Make the changes to the installation.
+    ///
+    /// Components are removed first, so that the obsoleted or conflicting tools
+    /// are out of the way before installing or updating the others.
+    pub(crate) async fn execute<T: ProgressHandler + Clone + 'static>(
+        self,
+        handler: T,
+        insecure: bool,
+    ) -> Result<()> {
+        let tracker = Tracker::start(Operation::Apply);
+        let result = self.execute_(handler, insecure).await;
+        tracker.finish(&result);
+        result
+    }
+
+    async fn execute_<T: ProgressHandler + Clone + 'static>(
+        self,
+        handler: T,
+        insecure: bool,
+    ) -> Result<()> {
+        if !self.tools_to_remove.is_empty() || !self.components_to_remove.is_empty() {
+            let mut config = UninstallConfiguration::init(handler.clone())?;
+            // components of the previous channel are gone with the switch,
+            // there is nothing to remove but their records.
+            if !self.switch_channel {
+                config.remove_toolchain_components(&self.components_to_remove, 50)?;
+            }
+            config.remove_tools(self.tools_to_remove, 50)?;
+        }
+
+        let install_dir = AppInfo::get_installed_dir();
+        let mut manifest = ToolkitManifest::load_from_install_dir()?;
+        if let Some(channel) = self.channel {
+            manifest.toolchain.channel = channel;
+        }
+        if let Some(meta) = &self.toolkit {
+            manifest.name.clone_from(&meta.name);
+            manifest.version.clone_from(&meta.version);
+            manifest.edition.clone_from(&meta.edition);
+        }
+
+        // components are split into toolchain components and tools during the update
+        if !self.components.is_empty() {
+            InstallConfiguration::new(install_dir, &manifest, handler.clone())?
+                .insecure(insecure)
+                .update(self.components)
+                .await?;
+        }
+        // the new toolchain is the default one now, the replaced one is no longer used
+        if let Some(channel) = &self.replaced_channel {
+            let config = UninstallConfiguration::init(handler)?;
+            ToolchainInstaller::init(&config).uninstall_toolchain(&config, channel)?;
+        }
+
+        // update the records that might not be covered by the installation above
+        let mut record = InstallationRecord::load_from_config_dir()?;
+        if self.switch_channel {
+            record.remove_component_record(&self.components_to_remove);
+        }
+        if self.toolkit.is_some() {
+            record.clone_toolkit_meta_from_manifest(&manifest);
+        }
+        record.write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rim_common::types::{ExtraToolchain, TomlParser};
+
+    fn record(raw: &str) -> InstallationRecord {
+        InstallationRecord::from_str(raw).unwrap()
+    }
+
+    fn lockfile(raw: &str) -> Lockfile {
+        Lockfile::from_str(raw).unwrap()
+    }
+
+    fn summary(plan: &ApplyPlan) -> Vec<(&str, Action)> {
+        plan.steps
+            .iter()
+            .map(|s| (s.name.as_str(), s.action))
+            .collect()
+    }
+
+    const RECORD: &str = r#"
+install_dir = "/path/to/rust"
+name = "My Rust"
+version = "1.0.0"
+
+[rust]
+version = "1.87.0"
+components = ["clippy", "rust-docs"]
+
+[tools.cargo-expand]
+kind = "cargo-tool"
+version = "1.0.100"
+paths = []
+
+[tools.hello]
+kind = "dir-with-bin"
+version = "0.1.0"
+paths = ["/path/to/rust/tools/hello"]
+"#;
+
+    #[test]
+    fn matched_installation() {
+        let lock = lockfile(
+            r#"
+[toolkit]
+name = "My Rust"
+version = "1.0.0"
+
+[rust]
+channel = "1.87.0"
+components = ["clippy", "rust-docs"]
+
+[tools]
+cargo-expand = "1.0.100"
+hello = { path = "tools/hello.zip" }
+"#,
+        );
+        let plan = ApplyPlan::new(&lock, &record(RECORD), "minimal");
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn install_update_and_remove() {
+        let lock = lockfile(
+            r#"
+[rust]
+channel = "1.87.0"
+components = ["clippy", "rustfmt"]
+
+[tools]
+cargo-expand = "1.0.101"
+world = { version = "0.2.0", url = "https://example.com/world.zip" }
+"#,
+        );
+        let plan = ApplyPlan::new(&lock, &record(RECORD), "minimal");
+        assert!(plan.toolkit.is_none());
+        assert_eq!(
+            summary(&plan),
+            [
+                ("rustfmt", Action::Install),
+                ("rust-docs", Action::Remove),
+                ("cargo-expand", Action::Update),
+                ("world", Action::Install),
+                ("hello", Action::Remove),
+            ]
+        );
+        assert!(!plan.switch_channel);
+    }
+
+    #[test]
+    fn switch_toolchain_channel() {
+        let lock = lockfile(
+            r#"
+[toolkit]
+name = "My Rust"
+version = "2.0.0"
+
+[rust]
+channel = "1.88.0"
+components = ["clippy"]
+
+[tools]
+cargo-expand = "1.0.100"
+hello = { path = "tools/hello.zip" }
+"#,
+        );
+        let plan = ApplyPlan::new(&lock, &record(RECORD), "minimal");
+        assert_eq!(
+            plan.toolkit.as_ref().unwrap().version.as_deref(),
+            Some("2.0.0")
+        );
+        assert_eq!(
+            summary(&plan),
+            [
+                ("minimal", Action::Update),
+                ("clippy", Action::Update),
+                ("rust-docs", Action::Remove),
+            ]
+        );
+        assert!(plan.switch_channel);
+        assert_eq!(plan.replaced_channel.as_deref(), Some("1.87.0"));
+
+        // the previous toolchain is kept if an additional toolchain uses it
+        let mut rec = record(RECORD);
+        rec.add_extra_toolchain_record(
+            "stable",
+            &ExtraToolchain {
+                channel: "1.87.0".into(),
+                profile: None,
+                components: vec![],
+                alias: None,
+            },
+        );
+        let plan = ApplyPlan::new(&lock, &rec, "minimal");
+        assert!(plan.switch_channel);
+        assert!(plan.replaced_channel.is_none());
+    }
+
+    #[test]
+    fn skip_held_components() {
+        let lock = lockfile(
+            r#"
+[rust]
+channel = "1.88.0"
+components = ["clippy"]
+
+[tools]
+cargo-expand = "1.0.101"
+"#,
+        );
+        let mut rec = record(RECORD);
+        rec.hold("minimal");
+        rec.hold("cargo-expand");
+        rec.hold("hello");
+
+        let plan = ApplyPlan::new(&lock, &rec, "minimal");
+        assert_eq!(
+            summary(&plan),
+            [
+                ("minimal", Action::Update),
+                ("clippy", Action::Update),
+                ("rust-docs", Action::Remove),
+                ("cargo-expand", Action::Update),
+                ("hello", Action::Remove),
+            ]
+        );
+        assert!(plan.steps.iter().all(|step| step.held));
+        assert!(plan.is_empty());
+        assert!(plan.channel.is_none());
+        assert!(!plan.switch_channel);
+        assert!(plan.replaced_channel.is_none());
+        assert!(plan.components.is_empty());
+        assert!(plan.components_to_remove.is_empty());
+        assert!(plan.tools_to_remove.is_empty());
+
+        // only the held ones are skipped
+        rec.unhold("hello");
+        let plan = ApplyPlan::new(&lock, &rec, "minimal");
+        let applied = plan
+            .steps
+            .iter()
+            .filter(|step| !step.held)
+            .map(|step| step.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(applied, ["hello"]);
+        assert!(!plan.is_empty());
+        assert!(plan.tools_to_remove.contains_key("hello"));
+    }
+}
@@ -1,3 +1,4 @@
+use super::dependency_handler::collect_dependents;
 use super::ToolkitManifestExt;
 use crate::fingerprint::InstallationRecord;
 use anyhow::Result;
@@ -13,12 +14,18 @@ pub enum ComponentType {
     Tool,
     ToolchainComponent,
     ToolchainProfile,
+    /// An additional compilation target, whose standard library is installed via rustup.
+    ToolchainTarget,
 }
 
 impl ComponentType {
-    /// Return `true` if this type is a toolchain component or a toolchain profile.
+    /// Return `true` if this type is a toolchain component, a toolchain profile,
+    /// or a compilation target of the toolchain.
     pub fn is_from_toolchain(&self) -> bool {
-        matches!(self, Self::ToolchainComponent | Self::ToolchainProfile)
+        matches!(
+            self,
+            Self::ToolchainComponent | Self::ToolchainProfile | Self::ToolchainTarget
+        )
     }
 
     #[cfg(feature = "gui")]
@@ -33,6 +40,10 @@ impl ComponentType {
                 name: t!("toolchain").into(),
                 help: None,
             },
+            (Self::ToolchainTarget, _) => ComponentTypeDesc {
+                name: t!("toolchain_target").into(),
+                help: Some(t!("toolchain_target_help").into()),
+            },
             (Self::Tool, None) => ComponentTypeDesc::default(),
             (Self::Tool, Some(kind)) => match kind {
                 ToolKind::CargoTool => ComponentTypeDesc {
@@ -82,11 +93,19 @@ pub struct Component {
     pub required: bool,
     pub optional: bool,
     pub tool_installer: Option<ToolInfo>,
+    /// Names of the components required by this component when it has no
+    /// [`tool_installer`](Component::tool_installer), such as the tools
+    /// (a linker for example) required by a compilation target.
+    pub requires: Vec<String>,
     pub kind: ComponentType,
     #[cfg(feature = "gui")]
     pub kind_desc: ComponentTypeDesc,
     /// Indicates whether this component was already installed or not.
     pub installed: bool,
+    /// Indicates whether this component is held at its installed version.
+    pub held: bool,
+    /// Names of the components that require this component, either directly or indirectly.
+    pub required_by: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -119,10 +138,10 @@ impl Component {
 
     /// Get a list of component names that are required by this component.
     pub fn dependencies(&self) -> &[String] {
-        self.tool_installer
-            .as_ref()
-            .map(|info| info.dependencies())
-            .unwrap_or_default()
+        match &self.tool_installer {
+            Some(info) => info.dependencies(),
+            None => &self.requires,
+        }
     }
 
     /// Get a list of component names that are obsoleted (replaced) by this component.
@@ -149,19 +168,24 @@ impl Component {
     setter!(required(self.required, bool));
     setter!(optional(self.optional, bool));
     setter!(installed(self.installed, bool));
+    setter!(held(self.held, bool));
     setter!(with_category(self.category, name: impl ToString) { name.to_string() });
     setter!(with_tool_installer(self.tool_installer, installer: &ToolInfo) { Some(installer.clone()) });
+    setter!(with_requires(self.requires, Vec<String>));
     setter!(with_version(self.version, version: Option<&str>) { version.map(ToOwned::to_owned) });
     setter!(with_display_name(self.display_name, name: impl ToString) { name.to_string() });
     setter!(with_description(self.desc, desc: Option<&str>) { desc.map(ToOwned::to_owned) });
 }
 
-/// A Rust toolchain component, such as `rustc`, `cargo`, `rust-docs`
-/// or even toolchain profile as as `minimal`, `default`.
+/// A Rust toolchain component, such as `rustc`, `cargo`, `rust-docs`,
+/// toolchain profile as as `minimal`, `default`,
+/// or a compilation target such as `wasm32-unknown-unknown`.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ToolchainComponent {
     pub name: String,
     pub is_profile: bool,
+    #[serde(default)]
+    pub is_target: bool,
 }
 
 impl ToolchainComponent {
@@ -169,21 +193,50 @@ impl ToolchainComponent {
         Self {
             name: name.to_string(),
             is_profile: false,
+            is_target: false,
         }
     }
     setter!(is_profile(self.is_profile, bool));
+    setter!(is_target(self.is_target, bool));
 }
 
 impl<'c> From<&'c ToolchainComponent> for Component {
     fn from(value: &'c ToolchainComponent) -> Self {
         Component::new(&value.name).with_type(if value.is_profile {
             ComponentType::ToolchainProfile
+        } else if value.is_target {
+            ComponentType::ToolchainTarget
         } else {
             ComponentType::ToolchainComponent
         })
     }
 }
 
+/// Fill the [`required_by`](Component::required_by) of each component in the list,
+/// so that the full set of dependencies of a selected component can be known.
+pub(crate) fn resolve_required_by(components: &mut [Component]) {
+    let required_by = {
+        let nodes = components
+            .iter()
+            .map(|c| (c.name.as_str(), c.dependencies()))
+            .collect::<Vec<_>>();
+        components
+            .iter()
+            .map(|c| {
+                let dependents = collect_dependents(nodes.iter().copied(), &[c.name.as_str()]);
+                components
+                    .iter()
+                    .filter(|other| dependents.contains(other.name.as_str()))
+                    .map(|other| other.name.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    for (comp, names) in components.iter_mut().zip(required_by) {
+        comp.required_by = names;
+    }
+}
+
 /// Get a combined list of tools and toolchain components in Vec<[Component]> format,
 /// whether it's installed or not.
 ///
@@ -214,6 +267,15 @@ pub(crate) fn all_components_from_installation(
                 comp.installed = installed_toolchain.is_some();
                 comp.version = installed_toolchain.map(|(channel, _)| channel.to_string());
             }
+            ComponentType::ToolchainTarget => {
+                if let Some((tc, _)) = installed_toolchain {
+                    comp.version = Some(tc.into());
+                    comp.installed = record
+                        .installed_toolchain_targets()
+                        .iter()
+                        .any(|t| t == &comp.name);
+                }
+            }
             // third-party tools
             ComponentType::Tool => {
                 if installed_tools.remove(&comp.name).is_some() {
@@ -226,11 +288,21 @@ pub(crate) fn all_components_from_installation(
         }
     }
 
+    // toolchain components cannot be updated separately, they are held along with the toolchain
+    let toolchain_held = record.toolchain_is_held()
+        || full_components
+            .iter()
+            .any(|c| c.kind == ComponentType::ToolchainProfile && record.is_held(&c.name));
+    for comp in &mut full_components {
+        comp.held = record.is_held(&comp.name) || (toolchain_held && comp.kind.is_from_toolchain());
+    }
+
     // we might still have some tool name's left from `installed_tools` that
     // are previously installed from another toolkit, we need to create a component base of it.
     for (key, val) in installed_tools {
         let mut comp = Component::new(&key)
             .installed(true)
+            .held(record.is_held(&key))
             .with_type(ComponentType::Tool)
             .with_version(val.version())
             .with_category(manifest.group_name(&key).unwrap_or(&*t!("others")));
@@ -281,6 +353,9 @@ pub fn split_components(components: Vec<Component>) -> (Vec<ToolchainComponent>,
             ComponentType::ToolchainProfile => {
                 Some(ToolchainComponent::new(&comp.name).is_profile(true))
             }
+            ComponentType::ToolchainTarget => {
+                Some(ToolchainComponent::new(&comp.name).is_target(true))
+            }
             _ => None,
         })
         .collect();
@@ -3,7 +3,7 @@
 
 use indexmap::IndexMap;
 use rim_common::types::ToolInfo;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use super::tools::{Tool, ToolWithDeps};
 
@@ -28,6 +28,91 @@ pub trait DependencyHandler<T> {
     fn sorted(&self) -> Vec<T> {
         self.topological_sorted()
     }
+
+    /// Find a dependency cycle and return the names along its path, where the
+    /// first name is repeated at the end, such as `["a", "b", "a"]`.
+    ///
+    /// Items in a cycle can never be reached by [`topological_sorted`](DependencyHandler::topological_sorted),
+    /// so this should be checked before relying on the sorted result.
+    fn find_cycle(&self) -> Option<Vec<&str>>;
+}
+
+/// Walk the reversed dependency graph from `targets`, return the names of every
+/// node that can reach any of the `targets`.
+pub(crate) fn collect_dependents<'a, I>(nodes: I, targets: &[&str]) -> HashSet<&'a str>
+where
+    I: IntoIterator<Item = (&'a str, &'a [String])>,
+{
+    // map each node to the nodes that require it
+    let mut required_by: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (name, dependencies) in nodes {
+        for dep in dependencies {
+            required_by.entry(dep.as_str()).or_default().push(name);
+        }
+    }
+
+    let mut dependents = HashSet::new();
+    let mut queue = targets.iter().copied().collect::<VecDeque<_>>();
+    while let Some(name) = queue.pop_front() {
+        for dependent in required_by.get(name).into_iter().flatten() {
+            if !targets.contains(dependent) && dependents.insert(*dependent) {
+                queue.push_back(*dependent);
+            }
+        }
+    }
+    dependents
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    Visiting,
+    Visited,
+}
+
+/// Perform a depth-first search on the dependency graph, return the path of the
+/// first cycle found. Dependencies that are not in `nodes` are ignored.
+fn find_cycle_<'a, I>(nodes: I) -> Option<Vec<&'a str>>
+where
+    I: IntoIterator<Item = (&'a str, &'a [String])>,
+{
+    fn visit<'a>(
+        name: &'a str,
+        graph: &IndexMap<&'a str, &'a [String]>,
+        states: &mut HashMap<&'a str, VisitState>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<&'a str>> {
+        match states.get(name) {
+            Some(VisitState::Visited) => return None,
+            Some(VisitState::Visiting) => {
+                let start = path.iter().position(|n| *n == name).unwrap_or_default();
+                let mut cycle = path[start..].to_vec();
+                cycle.push(name);
+                return Some(cycle);
+            }
+            None => (),
+        }
+        let dependencies = *graph.get(name)?;
+
+        states.insert(name, VisitState::Visiting);
+        path.push(name);
+        for dep in dependencies {
+            if let Some(cycle) = visit(dep, graph, states, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        states.insert(name, VisitState::Visited);
+        None
+    }
+
+    // use `IndexMap` to make the reported cycle stable
+    let graph: IndexMap<&str, &[String]> = nodes.into_iter().collect();
+    let mut states = HashMap::new();
+    let mut path = vec![];
+    graph
+        .keys()
+        .copied()
+        .find_map(|name| visit(name, &graph, &mut states, &mut path))
 }
 
 impl<'a> DependencyHandler<(&'a str, &'a ToolInfo)> for Vec<(&'a str, &'a ToolInfo)> {
@@ -73,6 +158,10 @@ impl<'a> DependencyHandler<(&'a str, &'a ToolInfo)> for Vec<(&'a str, &'a ToolIn
 
         res
     }
+
+    fn find_cycle(&self) -> Option<Vec<&str>> {
+        find_cycle_(self.iter().map(|(name, info)| (*name, info.dependencies())))
+    }
 }
 
 // TODO: Refractor duplicated code
@@ -126,6 +215,10 @@ impl<'a> DependencyHandler<Tool<'a>> for Vec<ToolWithDeps<'a>> {
         tools.sort_by(|a, b| b.kind.cmp(&a.kind));
         tools
     }
+
+    fn find_cycle(&self) -> Option<Vec<&str>> {
+        find_cycle_(self.iter().map(|t| (t.tool.name(), t.dependencies)))
+    }
 }
 
 #[cfg(test)]
@@ -230,6 +323,70 @@ mod tests {
         assert_eq!(iter.next().unwrap().name(), "some-exe");
     }
 
+    #[test]
+    fn find_dependents() {
+        let deps = |names: &[&str]| names.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let nodes = [
+            ("a", deps(&["b"])),
+            ("b", deps(&["c"])),
+            ("c", vec![]),
+            ("d", deps(&["c", "e"])),
+            ("e", vec![]),
+        ];
+        let names_of = |targets: &[&str]| {
+            let dependents = collect_dependents(
+                nodes.iter().map(|(name, deps)| (*name, deps.as_slice())),
+                targets,
+            );
+            let mut names = dependents.into_iter().collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names_of(&["c"]), ["a", "b", "d"]);
+        assert_eq!(names_of(&["b"]), ["a"]);
+        assert_eq!(names_of(&["b", "a"]), Vec::<&str>::new());
+        assert_eq!(names_of(&["e"]), ["d"]);
+        assert!(names_of(&["a"]).is_empty());
+    }
+
+    #[test]
+    fn detect_dependency_cycle() {
+        let requires = |deps: &[&str]| {
+            ToolInfo::Complex(Box::new(ToolInfoDetails {
+                requires: deps.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            }))
+        };
+        let tools: Vec<(&str, ToolInfo)> = vec![
+            ("a", requires(&["b"])),
+            ("b", requires(&["c", "x"])),
+            ("c", requires(&["d"])),
+            ("d", requires(&["b"])),
+        ];
+        let tools = tools
+            .iter()
+            .map(|(name, info)| (*name, info))
+            .collect::<Vec<_>>();
+        assert_eq!(tools.find_cycle().unwrap(), ["b", "c", "d", "b"]);
+        // cycle nodes are silently dropped by sorting
+        assert_eq!(tools.topological_sorted().len(), 1);
+
+        let self_required = [("a", requires(&["a"]))];
+        let self_required = self_required
+            .iter()
+            .map(|(name, info)| (*name, info))
+            .collect::<Vec<_>>();
+        assert_eq!(self_required.find_cycle().unwrap(), ["a", "a"]);
+
+        let acyclic = [("a", requires(&["b", "c"])), ("b", requires(&["c"]))];
+        let acyclic = acyclic
+            .iter()
+            .map(|(name, info)| (*name, info))
+            .collect::<Vec<_>>();
+        assert!(acyclic.find_cycle().is_none());
+    }
+
     #[test]
     fn extension_sorting() {
         let tools: Vec<(&str, ToolInfo)> = vec![
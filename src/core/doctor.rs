@@ -0,0 +1,417 @@
+//! Diagnose an existing installation, and repair the problems that can be fixed automatically.
+
+use super::directories::RimDir;
+use super::install::{EnvConfig, InstallConfiguration};
+use super::os::add_to_path;
+use super::parser::{cargo_config::CargoConfig, fingerprint::InstallationRecord};
+use super::{CARGO_HOME, RUSTUP_HOME};
+use anyhow::{anyhow, Result};
+use rim_common::types::{TomlParser, ToolkitManifest};
+use rim_common::utils::{self, HiddenProgress};
+use serde::Serialize;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// The version of [`Report`]'s schema, bump this when making breaking changes to it.
+pub(crate) const REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// An automatic fix of the problem found by a check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Fix {
+    /// Write the env vars (and the rc files on unix) again.
+    ConfigEnv,
+    /// Add `cargo/bin` to `PATH`.
+    AddCargoBinToPath,
+    /// Write the `config.toml` of cargo again.
+    ConfigCargo,
+}
+
+/// The result of a single check.
+#[derive(Debug, Serialize)]
+pub(crate) struct Diagnosis {
+    /// A short and stable identifier of the check, such as `env-vars`.
+    pub(crate) check: &'static str,
+    pub(crate) status: Status,
+    pub(crate) message: String,
+    /// Whether the problem can be fixed by `doctor --fix`.
+    pub(crate) fixable: bool,
+    /// Whether the problem was fixed.
+    pub(crate) fixed: bool,
+    #[serde(skip)]
+    fix: Option<Fix>,
+}
+
+impl Diagnosis {
+    fn new(check: &'static str, status: Status, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            status,
+            message: message.into(),
+            fixable: false,
+            fixed: false,
+            fix: None,
+        }
+    }
+
+    fn pass(check: &'static str, message: impl Into<String>) -> Self {
+        Self::new(check, Status::Pass, message)
+    }
+
+    fn warn(check: &'static str, message: impl Into<String>) -> Self {
+        Self::new(check, Status::Warn, message)
+    }
+
+    fn fail(check: &'static str, message: impl Into<String>) -> Self {
+        Self::new(check, Status::Fail, message)
+    }
+
+    fn with_fix(mut self, fix: Fix) -> Self {
+        self.fixable = true;
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// The report of every check that was run by [`diagnose`].
+#[derive(Debug, Serialize)]
+pub(crate) struct Report {
+    pub(crate) version: u32,
+    pub(crate) install_dir: Option<PathBuf>,
+    pub(crate) checks: Vec<Diagnosis>,
+}
+
+/// The paths of the expected installation layout.
+///
+/// NB: The paths are joined manually instead of using [`RimDir`], because those methods
+/// create the directories if they are missing, which hides the problem that we're looking for.
+struct Layout {
+    install_dir: PathBuf,
+    cargo_home: PathBuf,
+    cargo_bin: PathBuf,
+    rustup_home: PathBuf,
+}
+
+impl Layout {
+    fn new(install_dir: &Path) -> Self {
+        let cargo_home = install_dir.join("cargo");
+        Self {
+            install_dir: install_dir.to_path_buf(),
+            cargo_bin: cargo_home.join("bin"),
+            cargo_home,
+            rustup_home: install_dir.join("rustup"),
+        }
+    }
+}
+
+/// Run every check against the current installation.
+pub(crate) fn diagnose() -> Report {
+    let record = match InstallationRecord::load_from_config_dir() {
+        Ok(record) => record,
+        Err(e) => {
+            return Report {
+                version: REPORT_VERSION,
+                install_dir: None,
+                checks: vec![Diagnosis::fail("install-record", format!("{e:#}"))],
+            };
+        }
+    };
+
+    let layout = Layout::new(&record.install_dir);
+    let mut checks = vec![Diagnosis::pass(
+        "install-record",
+        t!("doctor_record_ok", path = layout.install_dir.display()),
+    )];
+    checks.push(check_layout(&layout));
+    checks.push(check_tools(&record));
+    checks.push(check_env_vars(&layout));
+    checks.push(check_path(&layout));
+    #[cfg(unix)]
+    checks.push(check_shell_rcs(&layout));
+    checks.push(check_toolchain(&layout, &record));
+    checks.push(check_cargo_config(&layout));
+
+    Report {
+        version: REPORT_VERSION,
+        install_dir: Some(layout.install_dir),
+        checks,
+    }
+}
+
+fn check_layout(layout: &Layout) -> Diagnosis {
+    let missing = [
+        &layout.install_dir,
+        &layout.cargo_home,
+        &layout.cargo_bin,
+        &layout.rustup_home,
+    ]
+    .into_iter()
+    .filter(|dir| !dir.is_dir())
+    .map(|dir| dir.display().to_string())
+    .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Diagnosis::pass("layout", t!("doctor_layout_ok"))
+    } else {
+        Diagnosis::fail(
+            "layout",
+            t!("doctor_missing_dirs", list = missing.join(", ")),
+        )
+    }
+}
+
+fn check_tools(record: &InstallationRecord) -> Diagnosis {
+    let mut missing = record
+        .tools
+        .iter()
+        .filter(|(_, rec)| rec.paths.iter().any(|p| !p.exists()))
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    missing.sort();
+
+    // keep the records, so that the tools can still be repaired or uninstalled
+    if missing.is_empty() {
+        Diagnosis::pass("tools", t!("doctor_tools_ok", count = record.tools.len()))
+    } else {
+        Diagnosis::warn(
+            "tools",
+            t!("doctor_missing_tools", list = missing.join(", ")),
+        )
+    }
+}
+
+fn check_env_vars(layout: &Layout) -> Diagnosis {
+    let mismatched = [
+        (CARGO_HOME, &layout.cargo_home),
+        (RUSTUP_HOME, &layout.rustup_home),
+    ]
+    .into_iter()
+    .filter_map(|(key, expected)| {
+        let actual = env::var_os(key).map(PathBuf::from);
+        (actual.as_ref() != Some(expected)).then(|| {
+            t!(
+                "doctor_env_var_mismatch",
+                key = key,
+                actual = actual.map(|p| p.display().to_string()).unwrap_or_default(),
+                expected = expected.display()
+            )
+            .to_string()
+        })
+    })
+    .collect::<Vec<_>>();
+
+    if mismatched.is_empty() {
+        Diagnosis::pass("env-vars", t!("doctor_env_vars_ok"))
+    } else {
+        Diagnosis::warn("env-vars", mismatched.join("; ")).with_fix(Fix::ConfigEnv)
+    }
+}
+
+fn check_path(layout: &Layout) -> Diagnosis {
+    let in_path = env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|p| p == layout.cargo_bin))
+        .unwrap_or_default();
+
+    if in_path {
+        Diagnosis::pass(
+            "path",
+            t!("doctor_path_ok", path = layout.cargo_bin.display()),
+        )
+    } else {
+        Diagnosis::fail(
+            "path",
+            t!("doctor_not_in_path", path = layout.cargo_bin.display()),
+        )
+        .with_fix(Fix::AddCargoBinToPath)
+    }
+}
+
+#[cfg(unix)]
+fn check_shell_rcs(layout: &Layout) -> Diagnosis {
+    match super::os::unix::rcs_without_env_config(&layout.install_dir) {
+        Ok(rcs) if rcs.is_empty() => Diagnosis::pass("shell-rc", t!("doctor_shell_rcs_ok")),
+        Ok(rcs) => {
+            let list = rcs
+                .iter()
+                .map(|rc| rc.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            Diagnosis::warn("shell-rc", t!("doctor_shell_rcs_missing", list = list))
+                .with_fix(Fix::ConfigEnv)
+        }
+        Err(e) => Diagnosis::warn("shell-rc", format!("{e:#}")),
+    }
+}
+
+fn check_toolchain(layout: &Layout, record: &InstallationRecord) -> Diagnosis {
+    let Some((channel, _)) = record.installed_toolchain() else {
+        return Diagnosis::pass("toolchain", t!("doctor_no_toolchain"));
+    };
+
+    let rustup = layout.cargo_bin.join(exe!("rustup"));
+    if !rustup.is_file() {
+        return Diagnosis::fail(
+            "toolchain",
+            t!("doctor_rustup_missing", path = rustup.display()),
+        );
+    }
+
+    let cmd = cmd!(
+        [CARGO_HOME = &layout.cargo_home, RUSTUP_HOME = &layout.rustup_home]
+        rustup, "toolchain", "list"
+    );
+    match utils::command_output(cmd) {
+        Ok(output) if output.lines().any(|ln| ln.starts_with(channel)) => {
+            Diagnosis::pass("toolchain", t!("doctor_toolchain_ok", channel = channel))
+        }
+        Ok(_) => Diagnosis::fail(
+            "toolchain",
+            t!("doctor_toolchain_missing", channel = channel),
+        ),
+        Err(e) => Diagnosis::fail("toolchain", format!("{e:#}")),
+    }
+}
+
+fn check_cargo_config(layout: &Layout) -> Diagnosis {
+    let path = layout.cargo_home.join(CargoConfig::FILENAME);
+    if !path.is_file() {
+        return Diagnosis::warn(
+            "cargo-config",
+            t!("doctor_cargo_config_missing", path = path.display()),
+        )
+        .with_fix(Fix::ConfigCargo);
+    }
+
+    match CargoConfig::load(&path) {
+        Ok(_) => Diagnosis::pass(
+            "cargo-config",
+            t!("doctor_cargo_config_ok", path = path.display()),
+        ),
+        // don't fix it by overriding, the user might have modified it manually
+        Err(e) => Diagnosis::fail("cargo-config", format!("{e:#}")),
+    }
+}
+
+impl Report {
+    /// Return `true` if any check failed and was not fixed.
+    pub(crate) fn has_failures(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|diag| diag.status == Status::Fail && !diag.fixed)
+    }
+
+    /// Try fixing every problem that can be fixed automatically.
+    ///
+    /// A fix that failed will not stop the others, instead a warning will be shown.
+    pub(crate) fn fix(&mut self) -> Result<()> {
+        let Some(install_dir) = &self.install_dir else {
+            return Ok(());
+        };
+        if self.checks.iter().all(|diag| diag.fix.is_none()) {
+            return Ok(());
+        }
+
+        let manifest_path = install_dir.join(ToolkitManifest::FILENAME);
+        let manifest = ToolkitManifest::load(&manifest_path).ok();
+        let config = manifest
+            .as_ref()
+            .map(|m| InstallConfiguration::new(install_dir, m, HiddenProgress))
+            .transpose()?;
+        let require_config = || {
+            config.as_ref().ok_or_else(|| {
+                anyhow!(t!(
+                    "doctor_manifest_missing",
+                    path = manifest_path.display()
+                ))
+            })
+        };
+
+        // some checks share the same fix, which only need to be applied once
+        let mut applied: Vec<(Fix, bool)> = vec![];
+        for diag in self.checks.iter_mut() {
+            let Some(fix) = diag.fix.take() else {
+                continue;
+            };
+            if let Some((_, fixed)) = applied.iter().find(|(f, _)| f == &fix) {
+                diag.fixed = *fixed;
+                continue;
+            }
+
+            info!("{}", t!("doctor_fixing", check = diag.check));
+            let result = match &fix {
+                Fix::ConfigEnv => require_config().and_then(|c| c.config_env_vars()),
+                Fix::AddCargoBinToPath => {
+                    require_config().and_then(|c| add_to_path(c, c.cargo_bin()))
+                }
+                Fix::ConfigCargo => require_config().and_then(|c| c.config_cargo()),
+            };
+            if let Err(e) = &result {
+                warn!(
+                    "{}",
+                    t!(
+                        "doctor_fix_failed",
+                        check = diag.check,
+                        reason = format!("{e:#}")
+                    )
+                );
+            }
+            diag.fixed = result.is_ok();
+            applied.push((fix, diag.fixed));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_tools_are_reported() {
+        let record: InstallationRecord = toml::from_str(
+            r#"
+install_dir = "/path/to/rust"
+
+[tools.foo]
+kind = "dir-with-bin"
+paths = ["/path/that/does/not/exist/foo"]
+
+[tools.bar]
+kind = "cargo-tool"
+"#,
+        )
+        .unwrap();
+
+        let diag = check_tools(&record);
+        assert_eq!(diag.status, Status::Warn);
+        assert!(diag.message.contains("foo") && !diag.message.contains("bar"));
+        // the records are needed to repair or uninstall them
+        assert!(!diag.fixable);
+        assert!(diag.fix.is_none());
+    }
+
+    #[test]
+    fn report_failures() {
+        let mut report = Report {
+            version: REPORT_VERSION,
+            install_dir: None,
+            checks: vec![
+                Diagnosis::pass("a", ""),
+                Diagnosis::warn("b", ""),
+                Diagnosis::fail("c", ""),
+            ],
+        };
+        assert!(report.has_failures());
+
+        report.checks[2].fixed = true;
+        assert!(!report.has_failures());
+    }
+}
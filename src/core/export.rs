@@ -0,0 +1,375 @@
+//! Export the current installation as a reproducible [`ToolkitManifest`].
+
+use anyhow::Result;
+use rim_common::types::{
+    ExtraToolchain, RustToolchain, RustupInit, TomlParser, ToolInfo, ToolKind, ToolMap, ToolSource,
+    ToolkitManifest, Tools,
+};
+use url::Url;
+
+use super::parser::cargo_config::CargoConfig;
+use super::parser::fingerprint::{InstallationRecord, ToolRecord};
+use super::toolkit::toolkits_from_server;
+use super::{get_toolkit_manifest, AppInfo, ToolkitManifestExt};
+
+/// Export what was actually installed into a [`ToolkitManifest`],
+/// which can be used to reproduce the installation on another machine.
+///
+/// Tools that were installed from local packages will be resolved back to the URLs
+/// from the online manifest of the installed toolkit, if they can be found.
+pub(crate) async fn export_manifest(insecure: bool) -> Result<ToolkitManifest> {
+    let install_dir = AppInfo::get_installed_dir();
+    let record = InstallationRecord::load_from_config_dir()?;
+    // the installed manifest is not required, as it might be missing in older installations
+    let installed = ToolkitManifest::load_from_install_dir()
+        .inspect_err(|e| debug!("unable to load the installed manifest: {e}"))
+        .ok();
+    let cargo_config = CargoConfig::load_from_dir(install_dir.join("cargo"))?;
+
+    let mut manifest = manifest_from_record(&record, installed.as_ref(), &cargo_config);
+    if local_tools(&manifest).next().is_some() {
+        match online_manifest(&record, insecure).await {
+            Ok(Some(online)) => resolve_local_sources(&mut manifest, &online),
+            Ok(None) => debug!("no online manifest found for the installed toolkit"),
+            Err(e) => warn!(
+                "{}",
+                t!("export_fetch_manifest_failed", reason = format!("{e:#}"))
+            ),
+        }
+    }
+    for name in local_tools(&manifest) {
+        warn!("{}", t!("export_local_source", name = name));
+    }
+
+    Ok(manifest)
+}
+
+/// Create a manifest from the installation `record`.
+///
+/// The `installed` manifest, which is the manifest that the installation was made from,
+/// is used to keep the toolkit configurations and to find the package source of each tool.
+fn manifest_from_record(
+    record: &InstallationRecord,
+    installed: Option<&ToolkitManifest>,
+    cargo_config: &CargoConfig,
+) -> ToolkitManifest {
+    let mut manifest = ToolkitManifest {
+        config: installed.map(|m| m.config.clone()).unwrap_or_default(),
+        name: record.name.clone(),
+        version: record.version.clone(),
+        edition: record.edition.clone(),
+        ..Default::default()
+    };
+    if let Some(registry) = cargo_config.replaced_registry() {
+        manifest.config.cargo_registry = Some(registry);
+    }
+
+    if let Some((channel, components)) = record.installed_toolchain() {
+        let mut toolchain = installed
+            .map(|m| m.toolchain.clone())
+            .unwrap_or_else(|| RustToolchain::new(channel));
+        toolchain.channel = channel.to_string();
+        toolchain.components = components.to_vec();
+        toolchain.optional_components.clear();
+        toolchain.targets = record.installed_toolchain_targets().to_vec();
+        toolchain.optional_targets.clear();
+        // only keep the requirements of the installed targets
+        toolchain
+            .target_requires
+            .retain(|target, _| toolchain.targets.contains(target));
+        // local dist server and bundled `rustup-init` are not available on other machines,
+        // but the checksums are still useful when downloading `rustup-init`.
+        toolchain.offline_dist_server = None;
+        toolchain.rustup = toolchain
+            .rustup
+            .into_iter()
+            .filter_map(|(target, init)| {
+                let sha256 = init.sha256()?.to_string();
+                Some((
+                    target,
+                    RustupInit::Detailed {
+                        path: None,
+                        sha256: Some(sha256),
+                    },
+                ))
+            })
+            .collect();
+        // only the additional toolchains that were actually installed are kept
+        toolchain.extra_toolchains = record
+            .extra_toolchains
+            .iter()
+            .map(|(name, rec)| {
+                let profile = installed
+                    .and_then(|m| m.toolchain.extra_toolchains.get(name))
+                    .and_then(|tc| tc.profile.clone());
+                let extra = ExtraToolchain {
+                    channel: rec.version().to_string(),
+                    profile,
+                    components: rec.components.clone(),
+                    alias: rec.alias().map(ToString::to_string),
+                };
+                (name.clone(), extra)
+            })
+            .collect();
+        manifest.toolchain = toolchain;
+    }
+
+    let mut names = record.tools.keys().collect::<Vec<_>>();
+    names.sort();
+    let tools = names
+        .into_iter()
+        .filter_map(|name| {
+            let known = installed.and_then(|m| {
+                m.current_target_tools()
+                    .find_map(|(n, info)| (n == name).then_some(info))
+            });
+            tool_info_from_record(name, &record.tools[name], known).map(|info| (name.clone(), info))
+        })
+        .collect::<ToolMap>();
+    manifest.tools = installed.map(|m| m.tools.clone()).unwrap_or_default();
+    manifest.tools.target = [(env!("TARGET").to_string(), tools)].into_iter().collect();
+    if manifest.tools.target[env!("TARGET")].is_empty() {
+        manifest.tools = Tools::default();
+    }
+
+    manifest
+}
+
+/// Get the information to install a tool, with its version locked to the installed one.
+///
+/// Return `None` if the tool cannot be exported because its source is unknown.
+fn tool_info_from_record(
+    name: &str,
+    record: &ToolRecord,
+    known: Option<&ToolInfo>,
+) -> Option<ToolInfo> {
+    let Some(mut info) = known.cloned() else {
+        // cargo tools can always be installed with their versions
+        if record.tool_kind() == ToolKind::CargoTool {
+            if let Some(version) = record.version() {
+                return Some(ToolInfo::Basic(version.to_string()));
+            }
+        }
+        warn!("{}", t!("export_unknown_source", name = name));
+        return None;
+    };
+
+    let Some(version) = record.version() else {
+        return Some(info);
+    };
+    match &mut info {
+        ToolInfo::Basic(ver) => *ver = version.to_string(),
+        ToolInfo::Complex(details) => match &mut details.source {
+            Some(ToolSource::Version { version: ver }) => *ver = version.to_string(),
+            // the package provided by user is not available on other machines
+            Some(ToolSource::Restricted { source, .. }) => *source = None,
+            // the package source cannot be changed to match a different version
+            _ if info_version_differs(known, version) => {
+                warn!(
+                    "{}",
+                    t!("export_version_mismatch", name = name, version = version)
+                );
+            }
+            _ => (),
+        },
+    }
+    Some(info)
+}
+
+fn info_version_differs(known: Option<&ToolInfo>, version: &str) -> bool {
+    known
+        .and_then(|info| info.version())
+        .is_some_and(|ver| ver != version)
+}
+
+/// Get the names of tools that are installed from local packages.
+fn local_tools(manifest: &ToolkitManifest) -> impl Iterator<Item = &str> {
+    manifest
+        .tools
+        .target
+        .values()
+        .flat_map(|map| map.iter())
+        .filter(|(_, info)| is_local(info))
+        .map(|(name, _)| name)
+}
+
+fn is_local(info: &ToolInfo) -> bool {
+    info.details()
+        .is_some_and(|d| matches!(d.source, Some(ToolSource::Path { .. })))
+}
+
+/// Get the manifest of the installed toolkit from server.
+async fn online_manifest(
+    record: &InstallationRecord,
+    insecure: bool,
+) -> Result<Option<ToolkitManifest>> {
+    let Some(url) = toolkits_from_server(insecure)
+        .await?
+        .into_iter()
+        .find(|tk| {
+            Some(&tk.name) == record.name.as_ref() && Some(&tk.version) == record.version.as_ref()
+        })
+        .and_then(|tk| tk.manifest_url)
+    else {
+        return Ok(None);
+    };
+    let manifest = get_toolkit_manifest(Some(Url::parse(&url)?), insecure).await?;
+    Ok(Some(manifest))
+}
+
+/// Replace the local package sources with the ones from `online` manifest,
+/// if the same version of the tool can be downloaded from a URL.
+fn resolve_local_sources(manifest: &mut ToolkitManifest, online: &ToolkitManifest) {
+    for tools in manifest.tools.target.values_mut() {
+        for (name, info) in tools.iter_mut() {
+            if !is_local(info) {
+                continue;
+            }
+            let name = info.identifier().unwrap_or(name.as_str());
+            let Some(online_info) = online
+                .current_target_tools()
+                .find_map(|(n, online_info)| (n == name).then_some(online_info))
+            else {
+                continue;
+            };
+            let has_url = online_info
+                .details()
+                .is_some_and(|d| matches!(d.source, Some(ToolSource::Url { .. })));
+            if has_url && online_info.version() == info.version() {
+                *info = online_info.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORD: &str = r#"
+install_dir = "/path/to/rust"
+name = "My Rust"
+version = "1.0.0"
+
+[rust]
+version = "1.87.0"
+components = ["clippy"]
+
+[extra_toolchains.nightly]
+version = "nightly-2025-05-01"
+components = ["miri"]
+alias = "fuzz"
+
+[tools.cargo-expand]
+kind = "cargo-tool"
+version = "1.0.101"
+paths = []
+
+[tools.hello]
+kind = "dir-with-bin"
+version = "0.1.0"
+paths = ["/path/to/rust/tools/hello"]
+
+[tools.unknown]
+kind = "dir-with-bin"
+paths = ["/path/to/rust/tools/unknown"]
+"#;
+
+    fn installed_manifest() -> ToolkitManifest {
+        let raw = format!(
+            r#"
+name = "My Rust"
+version = "1.0.0"
+
+[rust]
+channel = "1.87.0"
+profile = "minimal"
+components = ["clippy", "rustfmt"]
+optional-components = ["llvm-tools"]
+offline-dist-server = "toolchain"
+
+[rust.rustup]
+x86_64-unknown-linux-gnu = "tools/rustup-init"
+
+[rust.extra-toolchains.nightly]
+channel = "nightly-2025-05-01"
+profile = "minimal"
+components = ["miri", "rust-src"]
+
+[rust.extra-toolchains.beta]
+channel = "beta"
+
+[tools.target.{}]
+cargo-expand = "1.0.100"
+hello = {{ version = "0.1.0", url = "https://example.com/hello.zip" }}
+"#,
+            env!("TARGET")
+        );
+        ToolkitManifest::from_str(&raw).unwrap()
+    }
+
+    #[test]
+    fn export_installed_components() {
+        let record = InstallationRecord::from_str(RECORD).unwrap();
+        let mut cargo_config = CargoConfig::new();
+        cargo_config.add_source("mirror", "https://example.com/registry", true);
+        let installed = installed_manifest();
+
+        let manifest = manifest_from_record(&record, Some(&installed), &cargo_config);
+
+        assert_eq!(manifest.name.as_deref(), Some("My Rust"));
+        assert_eq!(
+            manifest.config.cargo_registry,
+            Some(("mirror", "https://example.com/registry").into())
+        );
+        assert_eq!(manifest.toolchain.channel, "1.87.0");
+        assert_eq!(manifest.toolchain.profile(), Some("minimal"));
+        assert_eq!(manifest.toolchain.components, ["clippy"]);
+        assert!(manifest.toolchain.optional_components.is_empty());
+        assert!(manifest.toolchain.offline_dist_server.is_none());
+        assert!(manifest.toolchain.rustup.is_empty());
+        assert_eq!(
+            manifest
+                .toolchain
+                .extra_toolchains
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [(
+                "nightly".to_string(),
+                ExtraToolchain {
+                    channel: "nightly-2025-05-01".into(),
+                    profile: Some("minimal".into()),
+                    components: vec!["miri".into()],
+                    alias: Some("fuzz".into()),
+                }
+            )]
+        );
+
+        let tools = &manifest.tools.target[env!("TARGET")];
+        assert_eq!(tools.len(), 2);
+        // version of cargo tools are locked to the installed one
+        assert_eq!(tools["cargo-expand"], ToolInfo::Basic("1.0.101".into()));
+        assert_eq!(
+            tools["hello"],
+            installed.tools.target[env!("TARGET")]["hello"]
+        );
+    }
+
+    #[test]
+    fn export_without_installed_manifest() {
+        let record = InstallationRecord::from_str(RECORD).unwrap();
+
+        let manifest = manifest_from_record(&record, None, &CargoConfig::new());
+
+        assert!(manifest.config.cargo_registry.is_none());
+        assert_eq!(manifest.toolchain.channel, "1.87.0");
+        assert_eq!(manifest.toolchain.profile(), None);
+        let tools = &manifest.tools.target[env!("TARGET")];
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools["cargo-expand"], ToolInfo::Basic("1.0.101".into()));
+
+        // the exported manifest should be valid
+        let exported = manifest.to_toml().unwrap();
+        assert_eq!(ToolkitManifest::from_str(&exported).unwrap(), manifest);
+    }
+}
@@ -0,0 +1,743 @@
+//! A history of the operations made to the installation, such as installing, updating
+//! and removing components, which can be used for auditing or undoing them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use rim_common::dirs::{rim_cache_dir, rim_config_dir};
+use rim_common::types::{TomlParser, ToolInfo, ToolInfoDetails, ToolKind, ToolkitManifest};
+use rim_common::utils::{self, ProgressHandler};
+use serde::{Deserialize, Serialize};
+
+use super::apply::{Action, ApplyPlan, Step};
+use super::parser::fingerprint::{InstallationRecord, ToolRecord};
+use super::parser::lockfile::{Lockfile, RustLock, ToolkitLock};
+use super::ToolkitManifestExt;
+
+/// Whether an operation is being tracked, so that the operations performed as
+/// part of it (such as the update in `apply`) are not recorded separately.
+static TRACKING: AtomicBool = AtomicBool::new(false);
+
+/// The maximum number of entries to keep in [`History`], older ones are pruned.
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Operation {
+    Install,
+    Update,
+    Switch,
+    AddComponents,
+    RemoveComponents,
+    Uninstall,
+    Apply,
+    Repair,
+    Undo,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Install => "install",
+            Self::Update => "update",
+            Self::Switch => "switch",
+            Self::AddComponents => "add-components",
+            Self::RemoveComponents => "remove-components",
+            Self::Uninstall => "uninstall",
+            Self::Apply => "apply",
+            Self::Repair => "repair",
+            Self::Undo => "undo",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Outcome {
+    Success,
+    Failure,
+}
+
+/// A single operation in the [`History`].
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Entry {
+    /// A sequential number that identifies this entry.
+    pub(crate) id: u32,
+    pub(crate) time: DateTime<Local>,
+    pub(crate) operation: Operation,
+    /// The version of this program that performed the operation.
+    pub(crate) rim_version: String,
+    pub(crate) toolkit_before: Option<String>,
+    pub(crate) toolkit_after: Option<String>,
+    /// The components that were installed, updated or removed.
+    #[serde(default)]
+    pub(crate) changes: Vec<Step>,
+    pub(crate) result: Outcome,
+    /// The error message if the operation failed.
+    pub(crate) error: Option<String>,
+    /// The id of the entry that was undone by this operation.
+    pub(crate) undoes: Option<u32>,
+    /// The id of the operation that undid this one.
+    pub(crate) undone_by: Option<u32>,
+    /// The parts of the installation changed by this operation, in their state before it,
+    /// which are restored when undoing it. Check [`changed_parts`] for what's included.
+    pub(crate) before: Option<Lockfile>,
+}
+
+impl Entry {
+    /// Create a plan to restore the parts of installation changed by this operation
+    /// to their state before it, the other parts of the current installation are kept.
+    ///
+    /// `profile` is the name of toolchain profile, check [`ApplyPlan::new`] for more info.
+    ///
+    /// # Error
+    /// Return error if the state before this operation is unknown, or any of the tools
+    /// that need to be reinstalled has an unknown source.
+    pub(crate) fn undo_plan(
+        &self,
+        record: &InstallationRecord,
+        profile: &str,
+    ) -> Result<ApplyPlan> {
+        let Some(before) = &self.before else {
+            bail!(t!(
+                "undo_unsupported",
+                id = self.id,
+                operation = self.operation
+            ));
+        };
+
+        let mut target = snapshot(record);
+        if before.toolkit.is_some() {
+            target.toolkit.clone_from(&before.toolkit);
+        }
+        if before.rust.is_some() {
+            target.rust.clone_from(&before.rust);
+        }
+        // tools installed by this operation are removed
+        for step in self.changes.iter().filter(|s| s.action == Action::Install) {
+            target.tools.shift_remove(&step.name);
+        }
+        for (name, info) in before.tools.iter() {
+            target.tools.insert(name.to_string(), info.clone());
+        }
+
+        let plan = ApplyPlan::new(&target, record, profile);
+        let unknown_source = plan
+            .steps
+            .iter()
+            .filter(|step| !step.held && step.action != Action::Remove)
+            .find(|step| {
+                target
+                    .tools
+                    .get(&step.name)
+                    .and_then(ToolInfo::details)
+                    .is_some_and(|details| details.source.is_none())
+            });
+        if let Some(step) = unknown_source {
+            bail!(t!(
+                "undo_unknown_source",
+                id = self.id,
+                operation = self.operation,
+                name = step.name
+            ));
+        }
+        Ok(plan)
+    }
+}
+
+/// The operations made to the installation, stored in [`rim_config_dir`].
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct History {
+    #[serde(default, rename = "entry")]
+    pub(crate) entries: Vec<Entry>,
+}
+
+impl TomlParser for History {
+    const FILENAME: &'static str = "history.toml";
+}
+
+impl History {
+    /// Load the history from [`rim_config_dir`], or from the [archive](Self::archive_path)
+    /// of a previous installation, return an empty one if there's none.
+    pub(crate) fn load_from_config_dir() -> Result<Self> {
+        let path = rim_config_dir().join(Self::FILENAME);
+        if path.is_file() {
+            return Self::load(path);
+        }
+        let archived = Self::archive_path();
+        if archived.is_file() {
+            return Self::load(archived);
+        }
+        Ok(Self::default())
+    }
+
+    /// The history is moved to [`rim_cache_dir`] before [`rim_config_dir`] is removed by
+    /// uninstalling everything, so that it can still be recorded and continued.
+    fn archive_path() -> PathBuf {
+        rim_cache_dir().join(Self::FILENAME)
+    }
+
+    /// Move the history out of [`rim_config_dir`], which is about to be removed.
+    pub(crate) fn archive() -> Result<()> {
+        let path = rim_config_dir().join(Self::FILENAME);
+        if path.is_file() {
+            utils::copy_as(&path, Self::archive_path())?;
+        }
+        Ok(())
+    }
+
+    /// Write the history into [`rim_config_dir`] if the installation still exists,
+    /// otherwise, into the [archive](Self::archive_path).
+    fn write_(&self) -> Result<()> {
+        if !InstallationRecord::exists() {
+            return utils::write_file(Self::archive_path(), &self.to_toml()?, false);
+        }
+        self.write_to_dir(rim_config_dir())?;
+        utils::remove(Self::archive_path())
+    }
+
+    /// Add an entry, the oldest entries are pruned to keep at most [`MAX_ENTRIES`] of them.
+    fn push(&mut self, entry: Entry) {
+        self.entries.push(entry);
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    /// Get the last successful operation that is not undone yet.
+    ///
+    /// Undo operations are skipped, so that undoing repeatedly steps further back in history.
+    pub(crate) fn last_undoable(&self) -> Option<&Entry> {
+        self.entries.iter().rev().find(|entry| {
+            entry.result == Outcome::Success
+                && entry.undone_by.is_none()
+                && entry.operation != Operation::Undo
+        })
+    }
+}
+
+/// Records an operation into the [`History`] once it's finished.
+///
+/// Operations started while another one is being tracked are considered as part of it,
+/// thus will not be recorded.
+#[must_use = "the operation will not be recorded unless `finish` is called"]
+pub(crate) struct Tracker {
+    operation: Operation,
+    active: bool,
+    time: DateTime<Local>,
+    record: Option<InstallationRecord>,
+    before: Option<Lockfile>,
+    undoes: Option<u32>,
+}
+
+impl Tracker {
+    /// Start tracking an operation, this should be called before making any changes.
+    pub(crate) fn start(operation: Operation) -> Self {
+        let active = !TRACKING.swap(true, Ordering::SeqCst);
+        let record = active
+            .then(|| InstallationRecord::load_from_config_dir().ok())
+            .flatten();
+        let before = record.as_ref().map(snapshot);
+        Self {
+            operation,
+            active,
+            time: Local::now(),
+            record,
+            before,
+            undoes: None,
+        }
+    }
+
+    /// Mark this operation as undoing the entry with the given `id`.
+    pub(crate) fn undoing(mut self, id: u32) -> Self {
+        self.undoes = Some(id);
+        self
+    }
+
+    /// Record the operation with its `result`.
+    ///
+    /// Failing to write the history will not fail the operation, only a warning will be shown.
+    pub(crate) fn finish<T>(mut self, result: &Result<T>) {
+        if !self.active {
+            return;
+        }
+        if let Err(e) = self.record_(result) {
+            warn!("{}", t!("history_write_failed", reason = format!("{e:#}")));
+        }
+    }
+
+    fn record_<T>(&mut self, result: &Result<T>) -> Result<()> {
+        // the record is gone after uninstalling everything, which cannot be undone
+        let (after, before) = if InstallationRecord::exists() {
+            let after = InstallationRecord::load_from_config_dir()?;
+            let before = self
+                .before
+                .take()
+                .map(|before| changed_parts(before, &snapshot(&after)));
+            (after, before)
+        } else {
+            (InstallationRecord::default(), None)
+        };
+        let mut history = History::load_from_config_dir()?;
+        let id = history.entries.last().map_or(1, |entry| entry.id + 1);
+
+        if let (Ok(_), Some(undone)) = (result, self.undoes) {
+            if let Some(entry) = history.entries.iter_mut().find(|e| e.id == undone) {
+                entry.undone_by = Some(id);
+            }
+        }
+        history.push(Entry {
+            id,
+            time: self.time,
+            operation: self.operation,
+            rim_version: env!("CARGO_PKG_VERSION").to_string(),
+            toolkit_before: self.record.as_ref().and_then(|r| r.version.clone()),
+            toolkit_after: after.version.clone(),
+            changes: changes_between(self.record.as_ref(), &after),
+            result: if result.is_ok() {
+                Outcome::Success
+            } else {
+                Outcome::Failure
+            },
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+            undoes: self.undoes,
+            undone_by: None,
+            before,
+        });
+        history.write_()
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        if self.active {
+            TRACKING.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Undo the operation of the entry with the given `id`,
+/// by executing the `plan` created with [`Entry::undo_plan`].
+pub(crate) async fn undo<T: ProgressHandler + Clone + 'static>(
+    id: u32,
+    plan: ApplyPlan,
+    handler: T,
+    insecure: bool,
+) -> Result<()> {
+    let tracker = Tracker::start(Operation::Undo).undoing(id);
+    let result = plan.execute(handler, insecure).await;
+    tracker.finish(&result);
+    result
+}
+
+/// Describe the state of an installation as a [`Lockfile`], so that it can be restored later.
+fn snapshot(record: &InstallationRecord) -> Lockfile {
+    // the manifest that the installation was made from, which has the sources of tools
+    let manifest = ToolkitManifest::load(record.install_dir.join(ToolkitManifest::FILENAME))
+        .inspect_err(|e| debug!("unable to load the installed manifest: {e}"))
+        .ok();
+
+    let mut names = record.tools.keys().collect::<Vec<_>>();
+    names.sort();
+    let tools = names
+        .into_iter()
+        .map(|name| {
+            let known = manifest.as_ref().and_then(|m| {
+                m.current_target_tools()
+                    .find_map(|(n, info)| (n == name).then_some(info))
+            });
+            (name.clone(), tool_info(&record.tools[name], known))
+        })
+        .collect();
+
+    Lockfile {
+        toolkit: Some(ToolkitLock {
+            name: record.name.clone(),
+            version: record.version.clone(),
+            edition: record.edition.clone(),
+        }),
+        rust: record
+            .installed_toolchain()
+            .map(|(channel, components)| RustLock {
+                channel: channel.to_string(),
+                components: components.to_vec(),
+            }),
+        tools,
+    }
+}
+
+/// Get the parts of the `before` state that are different in the `after` state,
+/// which are enough to restore `before`, along with the tools installed in between:
+///
+/// - `toolkit` and `rust` are kept only if they were changed.
+/// - `tools` only contains the ones that were updated or removed.
+fn changed_parts(before: Lockfile, after: &Lockfile) -> Lockfile {
+    Lockfile {
+        toolkit: before
+            .toolkit
+            .filter(|toolkit| Some(toolkit) != after.toolkit.as_ref()),
+        rust: before.rust.filter(|rust| Some(rust) != after.rust.as_ref()),
+        tools: before
+            .tools
+            .iter()
+            .filter(|(name, info)| after.tools.get(*name) != Some(*info))
+            .map(|(name, info)| (name.to_string(), info.clone()))
+            .collect(),
+    }
+}
+
+/// Get the information to reinstall the version of a tool in `record`,
+/// `known` is the information of this tool in the installed manifest.
+fn tool_info(record: &ToolRecord, known: Option<&ToolInfo>) -> ToolInfo {
+    match (known, record.version()) {
+        (Some(info), None) => info.clone(),
+        (Some(info), version) if info.version() == version => info.clone(),
+        (_, Some(version)) if record.tool_kind() == ToolKind::CargoTool => {
+            ToolInfo::Basic(version.to_string())
+        }
+        // the source is unknown, such tool can only be kept as it is
+        _ => ToolInfo::new_detailed(ToolInfoDetails {
+            kind: Some(record.tool_kind()),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Get the components that were changed from the `before` record to the `after` one.
+fn changes_between(before: Option<&InstallationRecord>, after: &InstallationRecord) -> Vec<Step> {
+    fn change(name: &str, action: Action, from: Option<&str>, to: Option<&str>) -> Step {
+        Step {
+            name: name.to_string(),
+            action,
+            from: from.map(ToString::to_string),
+            to: to.map(ToString::to_string),
+            held: false,
+        }
+    }
+
+    let mut changes = vec![];
+    let (old_channel, old_comps) = before.and_then(|r| r.installed_toolchain()).unzip();
+    let (new_channel, new_comps) = after.installed_toolchain().unzip();
+    let (old_comps, new_comps) = (old_comps.unwrap_or_default(), new_comps.unwrap_or_default());
+    match (old_channel, new_channel) {
+        (None, Some(_)) => changes.push(change("rust", Action::Install, None, new_channel)),
+        (Some(_), None) => changes.push(change("rust", Action::Remove, old_channel, None)),
+        (Some(old), Some(new)) if old != new => {
+            changes.push(change("rust", Action::Update, old_channel, new_channel));
+        }
+        _ => (),
+    }
+    for comp in new_comps.iter().filter(|c| !old_comps.contains(c)) {
+        changes.push(change(comp, Action::Install, None, new_channel));
+    }
+    for comp in old_comps.iter().filter(|c| !new_comps.contains(c)) {
+        changes.push(change(comp, Action::Remove, old_channel, None));
+    }
+    let old_targets = before
+        .map(|r| r.installed_toolchain_targets())
+        .unwrap_or_default();
+    let new_targets = after.installed_toolchain_targets();
+    for target in new_targets.iter().filter(|t| !old_targets.contains(t)) {
+        changes.push(change(target, Action::Install, None, new_channel));
+    }
+    for target in old_targets.iter().filter(|t| !new_targets.contains(t)) {
+        changes.push(change(target, Action::Remove, old_channel, None));
+    }
+
+    let no_tools = HashMap::new();
+    let old_tools = before.map_or(&no_tools, |r| &r.tools);
+    let names = old_tools
+        .keys()
+        .chain(after.tools.keys())
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        let step = match (old_tools.get(name), after.tools.get(name)) {
+            (None, Some(new)) => change(name, Action::Install, None, new.version()),
+            (Some(old), None) => change(name, Action::Remove, old.version(), None),
+            (Some(old), Some(new)) if old.version() != new.version() => {
+                change(name, Action::Update, old.version(), new.version())
+            }
+            _ => continue,
+        };
+        changes.push(step);
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(raw: &str) -> InstallationRecord {
+        InstallationRecord::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn record_changes_between_states() {
+        let before = record(
+            r#"
+install_dir = "/path/to/rust"
+version = "1.0.0"
+
+[rust]
+version = "1.86.0"
+components = ["clippy", "rust-docs"]
+
+[tools.cargo-expand]
+kind = "cargo-tool"
+version = "1.0.90"
+
+[tools.hello]
+kind = "executables"
+version = "0.1.0"
+"#,
+        );
+        let after = record(
+            r#"
+install_dir = "/path/to/rust"
+version = "1.1.0"
+
+[rust]
+version = "1.87.0"
+components = ["clippy", "rustfmt"]
+
+[tools.cargo-expand]
+kind = "cargo-tool"
+version = "1.0.100"
+
+[tools.world]
+kind = "executables"
+"#,
+        );
+
+        let changes = changes_between(Some(&before), &after)
+            .into_iter()
+            .map(|s| (s.name, s.action, s.from, s.to))
+            .collect::<Vec<_>>();
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(
+            changes,
+            [
+                (
+                    "rust".into(),
+                    Action::Update,
+                    some("1.86.0"),
+                    some("1.87.0")
+                ),
+                ("rustfmt".into(), Action::Install, None, some("1.87.0")),
+                ("rust-docs".into(), Action::Remove, some("1.86.0"), None),
+                (
+                    "cargo-expand".into(),
+                    Action::Update,
+                    some("1.0.90"),
+                    some("1.0.100")
+                ),
+                ("hello".into(), Action::Remove, some("0.1.0"), None),
+                ("world".into(), Action::Install, None, None),
+            ]
+        );
+
+        let installed = changes_between(None, &before);
+        assert_eq!(installed.len(), 5);
+        assert!(installed.iter().all(|s| s.action == Action::Install));
+    }
+
+    #[test]
+    fn snapshot_keeps_installed_versions() {
+        let before = record(
+            r#"
+install_dir = "/path/that/does/not/exist"
+name = "my-rust"
+version = "1.0.0"
+
+[rust]
+version = "1.86.0"
+components = ["clippy"]
+
+[tools.cargo-expand]
+kind = "cargo-tool"
+version = "1.0.90"
+
+[tools.hello]
+kind = "executables"
+version = "0.1.0"
+"#,
+        );
+
+        let lockfile = snapshot(&before);
+        let toolkit = lockfile.toolkit.unwrap();
+        assert_eq!(toolkit.name.as_deref(), Some("my-rust"));
+        assert_eq!(toolkit.version.as_deref(), Some("1.0.0"));
+        let rust = lockfile.rust.unwrap();
+        assert_eq!(rust.channel, "1.86.0");
+        assert_eq!(rust.components, ["clippy"]);
+        assert_eq!(
+            lockfile.tools.get("cargo-expand"),
+            Some(&ToolInfo::Basic("1.0.90".into()))
+        );
+        // the source of `hello` is unknown without the installed manifest
+        assert_eq!(lockfile.tools.get("hello").unwrap().version(), None);
+    }
+
+    #[test]
+    fn undo_changed_parts_only() {
+        let before = record(
+            r#"
+install_dir = "/path/that/does/not/exist"
+name = "my-rust"
+version = "1.0.0"
+
+[rust]
+version = "1.86.0"
+components = ["clippy"]
+
+[tools.cargo-expand]
+kind = "cargo-tool"
+version = "1.0.90"
+
+[tools.cargo-nextest]
+kind = "cargo-tool"
+version = "0.9.0"
+
+[tools.hello]
+kind = "executables"
+version = "0.1.0"
+"#,
+        );
+        let after = record(
+            r#"
+install_dir = "/path/that/does/not/exist"
+name = "my-rust"
+version = "1.0.0"
+
+[rust]
+version = "1.86.0"
+components = ["clippy"]
+
+[tools.cargo-expand]
+kind = "cargo-tool"
+version = "1.0.100"
+
+[tools.cargo-nextest]
+kind = "cargo-tool"
+version = "0.9.0"
+
+[tools.world]
+kind = "executables"
+"#,
+        );
+
+        let diff = changed_parts(snapshot(&before), &snapshot(&after));
+        assert!(diff.toolkit.is_none());
+        assert!(diff.rust.is_none());
+        assert_eq!(
+            diff.tools.keys().collect::<Vec<_>>(),
+            ["cargo-expand", "hello"]
+        );
+
+        let mut entry = Entry {
+            id: 1,
+            time: Local::now(),
+            operation: Operation::Update,
+            rim_version: "0.1.0".into(),
+            toolkit_before: None,
+            toolkit_after: None,
+            changes: changes_between(Some(&before), &after),
+            result: Outcome::Success,
+            error: None,
+            undoes: None,
+            undone_by: None,
+            before: Some(diff),
+        };
+        // `hello` cannot be reinstalled without knowing its source
+        let err = entry.undo_plan(&after, "minimal").unwrap_err();
+        assert!(err.to_string().contains("'hello'"));
+
+        entry.before.as_mut().unwrap().tools.shift_remove("hello");
+        let plan = entry.undo_plan(&after, "minimal").unwrap();
+        let steps = plan
+            .steps
+            .iter()
+            .map(|s| (s.name.as_str(), s.action))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [("cargo-expand", Action::Update), ("world", Action::Remove)]
+        );
+    }
+
+    #[test]
+    fn prune_old_entries() {
+        let mut history = History::default();
+        for id in 1..=MAX_ENTRIES as u32 + 5 {
+            history.push(Entry {
+                id,
+                time: Local::now(),
+                operation: Operation::Install,
+                rim_version: "0.1.0".into(),
+                toolkit_before: None,
+                toolkit_after: None,
+                changes: vec![],
+                result: Outcome::Success,
+                error: None,
+                undoes: None,
+                undone_by: None,
+                before: None,
+            });
+        }
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries[0].id, 6);
+    }
+
+    #[test]
+    fn find_last_undoable_entry() {
+        let history: History = toml::from_str(
+            r#"
+[[entry]]
+id = 1
+time = "2025-01-01T00:00:00+08:00"
+operation = "install"
+rim-version = "0.1.0"
+result = "success"
+
+[[entry]]
+id = 2
+time = "2025-01-02T00:00:00+08:00"
+operation = "update"
+rim-version = "0.1.0"
+result = "success"
+undone-by = 4
+
+[[entry]]
+id = 3
+time = "2025-01-03T00:00:00+08:00"
+operation = "add-components"
+rim-version = "0.1.0"
+result = "failure"
+error = "network error"
+
+[[entry]]
+id = 4
+time = "2025-01-04T00:00:00+08:00"
+operation = "undo"
+rim-version = "0.1.0"
+result = "success"
+undoes = 2
+"#,
+        )
+        .unwrap();
+
+        let entry = history.last_undoable().unwrap();
+        assert_eq!(entry.id, 1);
+        // the state before installation is unknown
+        let err = entry
+            .undo_plan(&InstallationRecord::default(), "minimal")
+            .unwrap_err();
+        assert!(err.to_string().contains("#1"));
+    }
+}
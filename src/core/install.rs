@@ -1,28 +1,37 @@
-use super::components::{split_components, ToolchainComponent};
+use super::components::{split_components, ComponentType, ToolchainComponent};
 use super::dependency_handler::DependencyHandler;
 use super::{
     components::Component,
     directories::RimDir,
+    history::{Operation, Tracker},
+    journal::{Change, Journal},
+    lock::InstallationLock,
     parser::{
         cargo_config::CargoConfig,
         fingerprint::{InstallationRecord, ToolRecord},
     },
+    preflight,
     rustup::ToolchainInstaller,
+    toolkit_manifest_ext::RUST_TOOLCHAIN_DEPENDENCY,
     tools::Tool,
-    GlobalOpts, CARGO_HOME, RUSTUP_DIST_SERVER, RUSTUP_HOME, RUSTUP_UPDATE_ROOT,
+    vendored_crates, GlobalOpts, ToolkitManifestExt, CARGO_HOME, RUSTUP_DIST_SERVER, RUSTUP_HOME,
+    RUSTUP_UPDATE_ROOT,
 };
 use crate::core::baked_in_manifest_raw;
 use crate::core::os::add_to_path;
 use crate::{default_cargo_registry, default_rustup_dist_server, default_rustup_update_root};
 use anyhow::{anyhow, bail, Context, Result};
+use rim_common::dirs::rim_config_dir;
 use rim_common::types::{
-    CargoRegistry, TomlParser, ToolInfo, ToolMap, ToolSource, ToolkitManifest,
+    CargoRegistry, Configuration, ExtractConfig, TomlParser, ToolInfo, ToolKind, ToolMap,
+    ToolSource, ToolkitManifest,
 };
-use rim_common::utils::ProgressHandler;
+use rim_common::utils::{DownloadCache, ProgressHandler, RetryPolicy};
 use rim_common::{build_config, utils};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tempfile::TempDir;
+use tokio::task::JoinSet;
 use url::Url;
 
 const DEFAULT_FOLDER_NAME: &str = "rust";
@@ -59,6 +68,26 @@ pub struct InstallConfiguration<'a, T> {
     pub(crate) progress_handler: T,
     pub(crate) manifest: &'a ToolkitManifest,
     insecure: bool,
+    /// The maximum number of packages to download at the same time.
+    max_concurrent_downloads: usize,
+    /// Policy of retrying failed downloads.
+    download_retry: RetryPolicy,
+    /// Cache of downloaded packages, `None` if caching was disabled.
+    download_cache: Option<DownloadCache>,
+    /// Limits of extracting the packages of tools.
+    extract_config: ExtractConfig,
+    /// Changes made so far, which will be reverted if the installation fails.
+    pub(crate) journal: Journal,
+    /// Prevents other processes from modifying the installation at the same time,
+    /// `None` before a fresh installation passes the [`preflight`](Self::preflight) check.
+    lock: Option<InstallationLock>,
+}
+
+/// A package that was downloaded before installing the tool.
+struct DownloadedPackage {
+    path: PathBuf,
+    /// The directory containing the package, which will be deleted once dropped.
+    temp_dir: TempDir,
 }
 
 impl<T> RimDir for &InstallConfiguration<'_, T> {
@@ -131,6 +160,33 @@ impl<T> InstallConfiguration<'_, T> {
     setter!(with_rustup_dist_server(self.rustup_dist_server, Option<Url>));
     setter!(with_rustup_update_root(self.rustup_update_root, Option<Url>));
     setter!(insecure(self.insecure, bool));
+    setter!(max_concurrent_downloads(
+        self.max_concurrent_downloads,
+        usize
+    ));
+    setter!(with_download_retry(self.download_retry, RetryPolicy));
+    setter!(with_download_cache(self.download_cache, Option<DownloadCache>));
+    setter!(with_extract_config(self.extract_config, ExtractConfig));
+
+    pub(crate) fn download_retry(&self) -> RetryPolicy {
+        self.download_retry
+    }
+
+    /// Create a [`DownloadOpt`](utils::DownloadOpt) to download the package of a tool,
+    /// with the proxy and mirrors configured in the manifest.
+    fn download_opt(
+        &self,
+        name: &str,
+        info: &ToolInfo,
+        handler: Box<dyn ProgressHandler>,
+    ) -> utils::DownloadOpt {
+        utils::DownloadOpt::new(name, handler)
+            .with_proxy(self.manifest.proxy_config().cloned())
+            .with_mirrors(self.manifest.config.mirrors.clone())
+            .with_retry(self.download_retry)
+            .with_cache(self.download_cache.clone())
+            .with_sha256(info.sha256().map(ToOwned::to_owned))
+    }
 
     pub(crate) fn env_vars(&self) -> Result<Vec<(&'static str, String)>> {
         let cargo_home = self
@@ -200,6 +256,8 @@ impl<T> InstallConfiguration<'_, T> {
         if let Ok(extractable) = utils::Extractable::load(maybe_file, None) {
             extractable
                 .quiet(GlobalOpts::get().quiet)
+                .max_size(self.extract_config.max_size_in_bytes())
+                .max_entries(self.extract_config.max_entries)
                 .extract_then_skip_solo_dir(dest, Some("bin"))
         } else {
             utils::copy_into(maybe_file, dest)
@@ -209,15 +267,24 @@ impl<T> InstallConfiguration<'_, T> {
 
 impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
     pub fn new(install_dir: &'a Path, manifest: &'a ToolkitManifest, handler: T) -> Result<Self> {
-        let install_record = if InstallationRecord::exists() {
+        // a fresh installation is locked in `install`, after the preflight check,
+        // so that nothing gets written if the installation cannot proceed.
+        let (install_record, lock) = if InstallationRecord::exists() {
+            let lock = InstallationLock::acquire()?;
             // TODO: handle existing record, maybe we want to enter manager mode directly?
-            InstallationRecord::load_from_config_dir()?
+            (InstallationRecord::load_from_config_dir()?, Some(lock))
         } else {
-            InstallationRecord {
+            let record = InstallationRecord {
                 install_dir: install_dir.to_path_buf(),
                 ..Default::default()
-            }
+            };
+            (record, None)
         };
+        let Configuration {
+            download: download_config,
+            extract: extract_config,
+            ..
+        } = Configuration::load_from_config_dir();
         Ok(Self {
             install_dir: install_dir.to_path_buf(),
             install_record,
@@ -228,6 +295,12 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
             progress_handler: handler,
             manifest,
             insecure: false,
+            max_concurrent_downloads: download_config.max_concurrent,
+            download_retry: download_config.retry_policy(),
+            download_cache: download_config.cache.then(DownloadCache::open),
+            extract_config,
+            journal: Journal::default(),
+            lock,
         })
     }
     /// Creating install directory and other preparations related to filesystem.
@@ -236,6 +309,7 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
     pub fn setup(&mut self) -> Result<()> {
         let install_dir = &self.install_dir;
         info!("{}", t!("install_init", dir = install_dir.display()));
+        self.journal.record_new_dir(install_dir);
         utils::ensure_dir(install_dir)?;
 
         // Create a copy of the manifest which is later used for component management.
@@ -244,6 +318,8 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         // NB: If this is an offline build, meaning the manifest is likely to contain
         // local paths, which is not useful for adding components afterwards, therefore
         // we better store the online version instead,
+        self.journal
+            .record_file_write(&install_dir.join(ToolkitManifest::FILENAME))?;
         if self.manifest.is_offline {
             ToolkitManifest::from_str(baked_in_manifest_raw(false))?.write_to_dir(install_dir)?;
         } else {
@@ -255,6 +331,7 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         let app_name = build_config().app_name();
         let manager_name = exe!(&app_name);
         let manager_exe = install_dir.join(&manager_name);
+        self.journal.record_file_write(&manager_exe)?;
         utils::copy_as(self_exe, &manager_exe)?;
 
         // Write application icon (name: <APP_NAME>.ico) to the install dir for shortcut.
@@ -262,6 +339,7 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         // it there to be future-proof.
         let ico_content = include_bytes!("../../rim_gui/public/favicon.ico");
         let ico_file_dest = install_dir.join(format!("{app_name}.ico"));
+        self.journal.record_file_write(&ico_file_dest)?;
         utils::write_bytes(ico_file_dest, ico_content, false)?;
 
         // soft-link this binary into cargo bin, so it will be in th PATH
@@ -269,6 +347,8 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         // and one with shorter name (rim)
         let link_full = self.cargo_bin().join(manager_name);
         let link_short = self.cargo_bin().join(exe!("rim"));
+        self.journal.record_file_write(&link_full)?;
+        self.journal.record_file_write(&link_short)?;
         utils::create_link(&manager_exe, &link_full)
             .with_context(|| format!("unable to create a link as '{}'", link_full.display()))?;
         utils::create_link(&manager_exe, &link_short)
@@ -276,7 +356,10 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
 
         #[cfg(windows)]
         // Create registry entry to add this program into "installed programs".
-        super::os::windows::do_add_to_programs(&manager_exe)?;
+        {
+            self.journal.record(Change::AddToPrograms);
+            super::os::windows::do_add_to_programs(&manager_exe)?;
+        }
 
         self.inc_progress(5)?;
 
@@ -284,13 +367,19 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
     }
 
     pub async fn install(mut self, components: Vec<Component>) -> Result<()> {
+        let tracker = Tracker::start(Operation::Install);
         let result = async {
+            self.preflight(&components)?;
+            if self.lock.is_none() {
+                self.lock = Some(InstallationLock::acquire()?);
+            }
             let (tc_components, tools) = split_components(components);
             reject_conflicting_tools(&tools)?;
 
             self.progress_handler
                 .start_master(t!("installing").into(), utils::ProgressKind::Len(100))?;
 
+            self.record_install_record_write()?;
             self.setup()?;
             self.config_env_vars()?;
             self.config_cargo()?;
@@ -307,12 +396,62 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
 
         if let Err(e) = &result {
             error!("{e:?}");
-            // TODO: revert changes
+            self.journal.rollback(&self);
         }
+        tracker.finish(&result);
 
         result
     }
 
+    /// Install a selection of toolchain components and tools into an existing installation,
+    /// typically used in `component install`.
+    ///
+    /// Changes will be reverted if any of them fails to install.
+    pub async fn install_components(
+        &mut self,
+        components: &[ToolchainComponent],
+        tools: &ToolMap,
+    ) -> Result<()> {
+        let tracker = Tracker::start(Operation::AddComponents);
+        let result = async {
+            let to_install = components
+                .iter()
+                .map(Component::from)
+                .chain(tools.iter().map(|(name, info)| {
+                    Component::new(name)
+                        .with_tool_installer(info)
+                        .with_type(ComponentType::Tool)
+                }))
+                .collect::<Vec<_>>();
+            self.preflight(&to_install)?;
+            self.record_install_record_write()?;
+            self.install_toolchain_components(components).await?;
+            self.install_tools(tools).await
+        }
+        .await;
+
+        if let Err(e) = &result {
+            error!("{e:?}");
+            self.journal.rollback(&*self);
+        }
+        tracker.finish(&result);
+
+        result
+    }
+
+    /// Make sure the installation could proceed before changing anything,
+    /// by checking the write permission and free disk space of the install directory.
+    fn preflight(&self, components: &[Component]) -> Result<()> {
+        let required = preflight::estimated_size(self.manifest, components);
+        preflight::check(&self.install_dir, required)
+    }
+
+    /// Take a snapshot of the installation record before it gets (re)written.
+    fn record_install_record_write(&self) -> Result<()> {
+        self.journal
+            .record_file_write(&rim_config_dir().join(InstallationRecord::FILENAME))
+    }
+
     pub(crate) fn inc_progress(&self, val: u64) -> Result<()> {
         self.progress_handler.update_master(Some(val))
     }
@@ -321,8 +460,10 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         let mut to_install = tools
             .iter()
             .filter(|(_, t)| {
-                let requires_toolchain =
-                    t.is_cargo_tool() || t.dependencies().iter().any(|s| s == "rust");
+                let requires_toolchain = t.is_cargo_tool()
+                    || t.dependencies()
+                        .iter()
+                        .any(|s| s == RUST_TOOLCHAIN_DEPENDENCY);
                 if use_rust {
                     requires_toolchain
                 } else {
@@ -334,9 +475,16 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         if to_install.is_empty() {
             return self.inc_progress(weight);
         }
+        // tools in a cycle cannot be sorted, and would be silently skipped otherwise
+        if let Some(cycle) = to_install.find_cycle() {
+            bail!(t!("dependency_cycle", path = cycle.join(" -> ")));
+        }
 
         let sub_progress_delta = weight / to_install.len() as u64;
 
+        // downloading doesn't care about the order, so do it concurrently before installing
+        let mut downloaded = self.download_tools(&to_install).await?;
+
         to_install = to_install.topological_sorted();
         // topological sort place the tool with more dependencies at the back,
         // which is what we need to install first, therefore we need to reverse it.
@@ -344,7 +492,8 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
 
         for (name, tool) in to_install {
             info!("{}", t!("installing_tool_info", name = name));
-            self.install_tool(name, tool).await?;
+            self.install_tool(name, tool, downloaded.remove(name))
+                .await?;
             self.inc_progress(sub_progress_delta)?;
         }
 
@@ -375,6 +524,8 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
             .rustup_dist_server(Some(self.rustup_dist_server().clone()))
             .install(self, components)
             .await?;
+        self.journal
+            .record(Change::AddToPath(self.cargo_bin().to_path_buf()));
         add_to_path(&*self, self.cargo_bin())?;
         self.toolchain_is_installed = true;
 
@@ -388,15 +539,102 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         // write changes
         self.install_record.write()?;
 
+        self.sync_extra_toolchains().await?;
+
         self.inc_progress(30)?;
         Ok(())
     }
 
+    /// Make the installed additional toolchains match the ones in manifest,
+    /// by installing (or updating) every one of them, and uninstalling the ones
+    /// that are no longer listed or have their channel changed.
+    ///
+    /// This is skipped for offline manifests, as the offline dist server
+    /// only contains the default toolchain.
+    async fn sync_extra_toolchains(&mut self) -> Result<()> {
+        let manifest = self.manifest;
+        let extra_toolchains = &manifest.toolchain.extra_toolchains;
+        if manifest.is_offline {
+            if !extra_toolchains.is_empty() {
+                let names = extra_toolchains.keys().cloned().collect::<Vec<_>>();
+                warn!(
+                    "{}",
+                    t!("extra_toolchains_skipped_offline", names = names.join(", "))
+                );
+            }
+            return Ok(());
+        }
+
+        let outdated = self
+            .install_record
+            .extra_toolchains
+            .iter()
+            .filter(|(name, rec)| {
+                extra_toolchains.get(name.as_str()).map_or(true, |tc| {
+                    tc.channel != rec.version() || tc.alias.as_deref() != rec.alias()
+                })
+            })
+            .map(|(name, rec)| {
+                (
+                    name.clone(),
+                    rec.version().to_string(),
+                    rec.alias().map(ToString::to_string),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (name, channel, alias) in outdated {
+            info!("{}", t!("uninstalling_extra_toolchain", name = name));
+            ToolchainInstaller::init(&*self).uninstall_extra_toolchain(
+                &*self,
+                &channel,
+                alias.as_deref(),
+            )?;
+            self.install_record.remove_extra_toolchain_record(&name);
+            self.install_record.write()?;
+        }
+
+        for (name, toolchain) in extra_toolchains {
+            // toolchains of the same channel were installed before, unless more components are required
+            let up_to_date = self
+                .install_record
+                .extra_toolchains
+                .get(name)
+                .is_some_and(|rec| {
+                    toolchain
+                        .components
+                        .iter()
+                        .all(|c| rec.components.contains(c))
+                });
+            if up_to_date {
+                continue;
+            }
+            info!(
+                "{}",
+                t!(
+                    "installing_extra_toolchain",
+                    name = name,
+                    channel = toolchain.channel
+                )
+            );
+            ToolchainInstaller::init(&*self)
+                .insecure(self.insecure)
+                .rustup_dist_server(Some(self.rustup_dist_server().clone()))
+                .install_extra_toolchain(self, toolchain)
+                .await?;
+            self.install_record
+                .add_extra_toolchain_record(name, toolchain);
+            self.install_record.write()?;
+        }
+        Ok(())
+    }
+
     /// Add toolchain components separately, typically used in `component add`.
     pub async fn install_toolchain_components(
         &mut self,
         components: &[ToolchainComponent],
     ) -> Result<()> {
+        self.journal
+            .record(Change::AddToolchainComponents(components.to_vec()));
         ToolchainInstaller::init(&*self)
             .insecure(self.insecure)
             .rustup_dist_server(Some(self.rustup_dist_server().clone()))
@@ -409,8 +647,60 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         Ok(())
     }
 
-    async fn install_tool(&mut self, name: &str, tool: &ToolInfo) -> Result<()> {
+    /// Download the packages of tools that have [`ToolSource::Url`] concurrently,
+    /// at most [`max_concurrent_downloads`](Self::max_concurrent_downloads) at a time.
+    ///
+    /// Return the downloaded packages mapped by tool names.
+    async fn download_tools(
+        &self,
+        tools: &[(&str, &ToolInfo)],
+    ) -> Result<HashMap<String, DownloadedPackage>> {
+        let mut tasks = JoinSet::new();
+        let mut downloaded = HashMap::new();
+
+        let to_download =
+            tools
+                .iter()
+                .filter_map(|(name, info)| match info.details()?.source.as_ref()? {
+                    ToolSource::Url { url, .. } => Some((*name, *info, url)),
+                    _ => None,
+                });
+        for (name, info, url) in to_download {
+            if tasks.len() >= self.max_concurrent_downloads.max(1) {
+                if let Some(res) = tasks.join_next().await {
+                    let (name, package) = res??;
+                    downloaded.insert(name, package);
+                }
+            }
+
+            let temp_dir = self.create_temp_dir("download")?;
+            let path = temp_dir.path().join(package_filename(url, info)?);
+            let opt = self.download_opt(name, info, self.progress_handler.concurrent());
+            let (name, url) = (name.to_string(), url.clone());
+            tasks.spawn(async move {
+                opt.download(&url, &path).await?;
+                anyhow::Ok((name, DownloadedPackage { path, temp_dir }))
+            });
+        }
+        // NB: the remaining downloads will be aborted if any of them fails
+        while let Some(res) = tasks.join_next().await {
+            let (name, package) = res??;
+            downloaded.insert(name, package);
+        }
+
+        Ok(downloaded)
+    }
+
+    async fn install_tool(
+        &mut self,
+        name: &str,
+        tool: &ToolInfo,
+        downloaded: Option<DownloadedPackage>,
+    ) -> Result<()> {
         self.remove_obsoleted_tools(tool)?;
+        if let Some(installed) = self.install_record.tools.get(name) {
+            self.backup_tool(installed)?;
+        }
 
         let record = match tool {
             ToolInfo::Basic(version) => {
@@ -446,9 +736,15 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
                 ToolSource::Path { path, .. } => {
                     self.try_install_from_path(name, path, tool, None)?
                 }
-                ToolSource::Url { url, .. } => {
-                    self.download_and_try_install(name, url, tool).await?
-                }
+                ToolSource::Url { url, .. } => match downloaded {
+                    Some(package) => self.try_install_from_path(
+                        name,
+                        &package.path,
+                        tool,
+                        Some(package.temp_dir),
+                    )?,
+                    None => self.download_and_try_install(name, url, tool).await?,
+                },
                 ToolSource::Restricted { source, .. } => {
                     // the source should be filled before installation, if not, then it means
                     // the program hasn't ask for user input yet, which we should through an error.
@@ -472,11 +768,39 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
             },
         };
 
+        // Tools that were already installed are being updated, their previous version
+        // will be restored from the backup instead.
+        if !self.install_record.tools.contains_key(name) {
+            self.journal.record(Change::InstallTool {
+                name: name.to_string(),
+                record: record.clone(),
+            });
+        }
         self.install_record.add_tool_record(name, record);
 
         Ok(())
     }
 
+    /// Back up the files of an installed tool before updating it,
+    /// so that they could be restored if the update fails.
+    fn backup_tool(&self, installed: &ToolRecord) -> Result<()> {
+        if installed.tool_kind() == ToolKind::CargoTool {
+            for listing in [".crates.toml", ".crates2.json"] {
+                self.journal
+                    .record_file_write(&self.cargo_home().join(listing))?;
+            }
+        }
+        let paths = installed
+            .paths
+            .iter()
+            .chain(installed.files.iter().map(|file| &file.path));
+        for path in paths {
+            self.journal
+                .record_replace(path, self.create_temp_dir("backup")?)?;
+        }
+        Ok(())
+    }
+
     async fn download_and_try_install(
         &self,
         name: &str,
@@ -484,19 +808,8 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         info: &ToolInfo,
     ) -> Result<ToolRecord> {
         let temp_dir = self.create_temp_dir("download")?;
-        let downloaded_file_name = if let Some(name) = info.filename() {
-            name
-        } else {
-            url.path_segments()
-                .ok_or_else(|| anyhow!("unsupported url format '{url}'"))?
-                .next_back()
-                // Sadly, a path segment could be empty string, so we need to filter that out
-                .filter(|seg| !seg.is_empty())
-                .ok_or_else(|| anyhow!("'{url}' doesn't appear to be a downloadable file"))?
-        };
-        let dest = temp_dir.path().join(downloaded_file_name);
-        utils::DownloadOpt::new(name, Box::new(self.progress_handler.clone()))
-            .with_proxy(self.manifest.proxy_config().cloned())
+        let dest = temp_dir.path().join(package_filename(url, info)?);
+        self.download_opt(name, info, Box::new(self.progress_handler.clone()))
             .download(url, &dest)
             .await?;
 
@@ -511,6 +824,15 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         dl_temp: Option<TempDir>,
     ) -> Result<ToolRecord> {
         let mut maybe_temp = dl_temp;
+        // Make sure the package is not corrupted or tampered before doing anything with it,
+        // this covers downloaded packages as well as local ones.
+        let checksum = match info.sha256().filter(|_| path.is_file()) {
+            Some(expected) => {
+                info!("{}", t!("verifying_checksum", file = path.display()));
+                Some(utils::verify_sha256(path, expected)?)
+            }
+            None => None,
+        };
         let tool_installer_path = if path.is_dir() {
             path.to_path_buf()
         } else if utils::Extractable::is_supported(path) {
@@ -536,7 +858,9 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
                 .with_context(|| format!("no install method for tool '{name}'"))?
         };
 
-        let res = tool_installer.install(self, info);
+        let res = tool_installer
+            .install(self, info)
+            .map(|rec| rec.with_sha256(checksum));
         drop(maybe_temp);
         res
     }
@@ -551,9 +875,19 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
         let registry = self.cargo_registry();
         config.add_source(&registry.name, &registry.index, true);
 
+        // use the bundled crates in place of `crates-io` if there are any
+        if let Some(rel_path) = &self.manifest.crates.path {
+            info!("{}", t!("install_vendored_crates"));
+            let bundled = self.manifest.package_root()?.join(rel_path);
+            let dest = vendored_crates::vendored_crates_dir(&self.install_dir);
+            vendored_crates::merge_crates(&bundled, &dest)?;
+            config.add_directory_source(vendored_crates::VENDORED_SOURCE_NAME, &dest, true);
+        }
+
         let config_toml = config.to_toml()?;
         if !config_toml.trim().is_empty() {
             let config_path = self.cargo_home().join(CargoConfig::FILENAME);
+            self.journal.record_file_write(&config_path)?;
             utils::write_file(config_path, &config_toml, false)?;
         }
 
@@ -564,38 +898,89 @@ impl<'a, T: ProgressHandler + Clone + 'static> InstallConfiguration<'a, T> {
 
 // For updates
 impl<T: ProgressHandler + Clone + 'static> InstallConfiguration<'_, T> {
+    /// Update the toolchain and tools of an existing installation.
+    ///
+    /// Changes will be reverted if this fails, the toolchain is switched back to the
+    /// previous one, and the updated tools are restored from their backups.
+    ///
+    /// Components that are held at their installed version will be left untouched,
+    /// check [`InstallationRecord::is_held`].
     pub async fn update(mut self, components: Vec<Component>) -> Result<()> {
-        self.progress_handler
-            .start_master(t!("installing").into(), utils::ProgressKind::Len(100))?;
+        let components = self.skip_held_components(components);
+        let tracker = Tracker::start(Operation::Update);
+        let result = async {
+            self.preflight(&components)?;
+            self.progress_handler
+                .start_master(t!("installing").into(), utils::ProgressKind::Len(100))?;
 
-        // Create a copy of the manifest which is later used for component management.
-        self.manifest.write_to_dir(&self.install_dir)?;
+            // Create a copy of the manifest which is later used for component management.
+            self.journal
+                .record_file_write(&self.install_dir.join(ToolkitManifest::FILENAME))?;
+            self.manifest.write_to_dir(&self.install_dir)?;
+            self.record_install_record_write()?;
+
+            let (toolchain, tools) = split_components(components);
+            // setup env for current process
+            for (key, val) in self.env_vars()? {
+                self.journal.record_env_var(key, std::env::var(key).ok());
+                std::env::set_var(key, val);
+            }
+            self.inc_progress(10)?;
 
-        let (toolchain, tools) = split_components(components);
-        // setup env for current process
-        for (key, val) in self.env_vars()? {
-            std::env::set_var(key, val);
+            // don't update toolchain if no toolchain components are selected
+            if !toolchain.is_empty() {
+                self.update_toolchain(&toolchain).await?;
+            }
+            self.update_tools(&tools).await?;
+
+            // the toolkit version has changed even if the toolchain stays the same
+            self.install_record
+                .clone_toolkit_meta_from_manifest(self.manifest);
+            self.install_record.write()?;
+
+            self.progress_handler
+                .finish_master(t!("install_finished").into())?;
+            Ok(())
         }
-        self.inc_progress(10)?;
+        .await;
 
-        // don't update toolchain if no toolchain components are selected
-        if !toolchain.is_empty() {
-            self.update_toolchain(&toolchain).await?;
+        if let Err(e) = &result {
+            error!("{e:?}");
+            self.journal.rollback(&self);
         }
-        self.update_tools(&tools).await?;
+        tracker.finish(&result);
 
-        self.progress_handler
-            .finish_master(t!("install_finished").into())?;
-        Ok(())
+        result
+    }
+
+    /// Filter out the held components, the toolchain components are held along with
+    /// the toolchain, as they cannot be updated separately.
+    fn skip_held_components(&self, components: Vec<Component>) -> Vec<Component> {
+        let record = &self.install_record;
+        // records written by older versions don't have the profile name
+        let toolchain_held = record.toolchain_is_held()
+            || components
+                .iter()
+                .any(|c| c.kind == ComponentType::ToolchainProfile && record.is_held(&c.name));
+        components
+            .into_iter()
+            .filter(|c| {
+                let held =
+                    record.is_held(&c.name) || (toolchain_held && c.kind.is_from_toolchain());
+                if held {
+                    info!("{}", t!("skip_held_component", name = c.name));
+                }
+                !held
+            })
+            .collect()
     }
 
     async fn update_toolchain(&mut self, components: &[ToolchainComponent]) -> Result<()> {
         info!("{}", t!("update_toolchain"));
 
-        ToolchainInstaller::init(&*self)
-            .insecure(self.insecure)
-            .update(self, components)
-            .await?;
+        let installer = ToolchainInstaller::init(&*self).insecure(self.insecure);
+        self.record_toolchain_update(&installer, components);
+        installer.update(self, components).await?;
 
         let record = &mut self.install_record;
         // Add the rust info to the fingerprint.
@@ -605,10 +990,38 @@ impl<T: ProgressHandler + Clone + 'static> InstallConfiguration<'_, T> {
         // write changes
         record.write()?;
 
+        self.sync_extra_toolchains().await?;
+
         self.inc_progress(60)?;
         Ok(())
     }
 
+    /// Record how to go back to the installed toolchain before updating it to `components`.
+    fn record_toolchain_update(
+        &self,
+        installer: &ToolchainInstaller,
+        components: &[ToolchainComponent],
+    ) {
+        let Some((previous, installed)) = self.install_record.installed_toolchain() else {
+            return;
+        };
+        let channel = &self.manifest.toolchain.channel;
+        if previous == channel {
+            let added = components
+                .iter()
+                .filter(|c| !c.is_profile && !installed.contains(&c.name))
+                .cloned()
+                .collect::<Vec<_>>();
+            self.journal.record(Change::AddToolchainComponents(added));
+        } else {
+            self.journal.record(Change::SwitchToolchain {
+                previous: previous.to_string(),
+                channel: channel.clone(),
+                newly_installed: !installer.is_toolchain_installed(self, channel),
+            });
+        }
+    }
+
     async fn update_tools(&mut self, tools: &ToolMap) -> Result<()> {
         info!("{}", t!("update_tools"));
         self.install_tools_(false, tools, 15).await?;
@@ -636,6 +1049,19 @@ impl<T: ProgressHandler + Clone + 'static> InstallConfiguration<'_, T> {
     }
 }
 
+/// Get the name of the file to save the downloaded package of a tool as.
+fn package_filename<'a>(url: &'a Url, info: &'a ToolInfo) -> Result<&'a str> {
+    if let Some(name) = info.filename() {
+        return Ok(name);
+    }
+    url.path_segments()
+        .ok_or_else(|| anyhow!("unsupported url format '{url}'"))?
+        .next_back()
+        // Sadly, a path segment could be empty string, so we need to filter that out
+        .filter(|seg| !seg.is_empty())
+        .ok_or_else(|| anyhow!("'{url}' doesn't appear to be a downloadable file"))
+}
+
 // TODO: Conflict resolve should take place during user interaction, not here,
 // but it's kind hard to do with how we handle CLI interaction now, figure out a way.
 fn reject_conflicting_tools(tools: &ToolMap) -> Result<()> {
@@ -754,4 +1180,85 @@ no_proxy = "localhost,.example.com,.foo.com"
             std::env::set_var("no_proxy", bck);
         }
     }
+
+    #[test]
+    fn skip_components_of_held_toolchain() {
+        let mut cache_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        cache_dir.push("tests");
+        cache_dir.push("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let root = tempfile::Builder::new().tempdir_in(&cache_dir).unwrap();
+
+        let manifest = ToolkitManifest::default();
+        let mut install_cfg =
+            InstallConfiguration::new(root.path(), &manifest, HiddenProgress).unwrap();
+        install_cfg.install_record.add_rust_record(
+            "1.0.0",
+            &[
+                ToolchainComponent::new("Rust").is_profile(true),
+                ToolchainComponent::new("clippy"),
+            ],
+        );
+        install_cfg.install_record.hold("Rust");
+
+        // the profile is not part of the update, but the toolchain is still held
+        let components = vec![
+            Component::new("clippy").with_type(ComponentType::ToolchainComponent),
+            Component::new("aaa"),
+        ];
+        let remaining = install_cfg.skip_held_components(components);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "aaa");
+
+        install_cfg.install_record.unhold("Rust");
+        let components =
+            vec![Component::new("clippy").with_type(ComponentType::ToolchainComponent)];
+        assert_eq!(install_cfg.skip_held_components(components).len(), 1);
+    }
+
+    #[test]
+    fn download_tools_concurrently() {
+        let mut cache_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        cache_dir.push("tests");
+        cache_dir.push("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let root = tempfile::Builder::new().tempdir_in(&cache_dir).unwrap();
+
+        let packages = ["a", "b", "c"].map(|name| {
+            let path = root.path().join(format!("{name}.zip"));
+            std::fs::write(&path, name).unwrap();
+            Url::from_file_path(path).unwrap()
+        });
+        let raw = format!(
+            r#"
+a = {{ url = "{}" }}
+b = {{ url = "{}", filename = "renamed.zip" }}
+c = {{ url = "{}" }}
+d = "0.1.0"
+"#,
+            packages[0], packages[1], packages[2]
+        );
+        let tools: ToolMap = toml::from_str(&raw).unwrap();
+        let tools = tools.iter().collect::<Vec<_>>();
+
+        let manifest = ToolkitManifest::default();
+        let install_cfg = InstallConfiguration::new(root.path(), &manifest, HiddenProgress)
+            .unwrap()
+            .max_concurrent_downloads(2);
+        let downloaded = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(install_cfg.download_tools(&tools))
+            .unwrap();
+
+        assert_eq!(downloaded.len(), 3);
+        assert!(!downloaded.contains_key("d"));
+        let b = &downloaded["b"];
+        assert!(b.path.ends_with("renamed.zip"));
+        assert_eq!(std::fs::read_to_string(&b.path).unwrap(), "b");
+        for name in ["a", "c"] {
+            let package = &downloaded[name];
+            assert!(package.path.ends_with(format!("{name}.zip")));
+            assert_eq!(std::fs::read_to_string(&package.path).unwrap(), name);
+        }
+    }
 }
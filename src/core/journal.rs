@@ -0,0 +1,295 @@
+//! Journal of the changes made to user's machine during an installation.
+//!
+//! Each step that modifies the machine (creating directories, writing files,
+//! setting environment variables, installing tools, etc.) records a [`Change`]
+//! in the [`Journal`] **before** applying it, so that when any later step fails,
+//! the recorded changes can be reverted in reverse order.
+
+use super::components::ToolchainComponent;
+use super::directories::RimDir;
+use super::parser::fingerprint::ToolRecord;
+use super::rustup::ToolchainInstaller;
+use super::tools::Tool;
+use anyhow::{Context, Result};
+use rim_common::utils;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tempfile::TempDir;
+
+/// A single change that was made to user's machine.
+#[derive(Debug)]
+pub(crate) enum Change {
+    /// A new directory was created, it will be removed entirely.
+    CreateDir(PathBuf),
+    /// A file was written, `original` is its content before the modification,
+    /// or `None` if the file did not exist.
+    WriteFile {
+        path: PathBuf,
+        original: Option<Vec<u8>>,
+    },
+    /// An environment variable was set, `original` is its previous value.
+    SetEnvVar {
+        key: String,
+        original: Option<String>,
+    },
+    /// A path was added to the `PATH` environment variable.
+    AddToPath(PathBuf),
+    /// Components were added to an existing toolchain.
+    AddToolchainComponents(Vec<ToolchainComponent>),
+    /// The default toolchain was switched from `previous` to the toolchain of `channel`,
+    /// which will be uninstalled if it was `newly_installed`.
+    SwitchToolchain {
+        previous: String,
+        channel: String,
+        newly_installed: bool,
+    },
+    /// An existing file or directory was about to be replaced, such as when updating a tool,
+    /// `backup` contains a copy of it, which will be put back in place.
+    ReplacePath { path: PathBuf, backup: TempDir },
+    /// A tool that was not installed before was installed.
+    InstallTool { name: String, record: ToolRecord },
+    /// This program was added to the "installed programs" list.
+    #[cfg(windows)]
+    AddToPrograms,
+}
+
+impl Change {
+    fn revert<T: RimDir + Copy>(&self, config: T) -> Result<()> {
+        debug!("reverting change: {self:?}");
+        match self {
+            Self::CreateDir(dir) => utils::remove(dir)?,
+            Self::WriteFile {
+                path,
+                original: Some(content),
+            } => utils::write_bytes(path, content, false)?,
+            Self::WriteFile {
+                path,
+                original: None,
+            } => utils::remove(path)?,
+            Self::SetEnvVar { key, original } => {
+                super::os::restore_env_var(key, original.as_deref())?
+            }
+            Self::AddToPath(path) => super::os::remove_from_path(config, path)?,
+            Self::AddToolchainComponents(components) => {
+                ToolchainInstaller::init(config).remove_components(config, components)?
+            }
+            Self::SwitchToolchain {
+                previous,
+                channel,
+                newly_installed,
+            } => {
+                let installer = ToolchainInstaller::init(config);
+                installer.set_default_toolchain(config, previous)?;
+                if *newly_installed {
+                    installer.uninstall_toolchain(config, channel)?;
+                }
+            }
+            Self::ReplacePath { path, backup } => {
+                utils::remove(path)?;
+                utils::copy_as(backup_of(backup), path)?;
+            }
+            Self::InstallTool { name, record } => {
+                if let Some(tool) = Tool::from_installed(name, record) {
+                    tool.uninstall(config)?;
+                }
+            }
+            #[cfg(windows)]
+            Self::AddToPrograms => super::os::windows::remove_from_programs()?,
+        }
+        Ok(())
+    }
+}
+
+/// Keeps track of the [`Change`]s made during an installation.
+///
+/// Changes are stored behind a [`Mutex`], so that they can be recorded
+/// with a shared reference while the installation is still in progress.
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+    changes: Mutex<Vec<Change>>,
+}
+
+impl Journal {
+    fn changes(&self) -> MutexGuard<'_, Vec<Change>> {
+        // a poisoned lock only means a panic happened while recording,
+        // the recorded changes are still valid.
+        self.changes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn record(&self, change: Change) {
+        self.changes().push(change);
+    }
+
+    /// Record the creation of `dir`, nothing will be recorded if it already exists.
+    pub(crate) fn record_new_dir(&self, dir: &Path) {
+        if !dir.exists() {
+            self.record(Change::CreateDir(dir.to_path_buf()));
+        }
+    }
+
+    /// Take a snapshot of the file at `path` before writing it.
+    ///
+    /// Only the first snapshot of each file is kept, as that's the original state of it.
+    pub(crate) fn record_file_write(&self, path: &Path) -> Result<()> {
+        let mut changes = self.changes();
+        if changes
+            .iter()
+            .any(|c| matches!(c, Change::WriteFile { path: p, .. } if p == path))
+        {
+            return Ok(());
+        }
+
+        let original = path
+            .is_file()
+            .then(|| fs::read(path))
+            .transpose()
+            .with_context(|| format!("unable to backup the content of '{}'", path.display()))?;
+        changes.push(Change::WriteFile {
+            path: path.to_path_buf(),
+            original,
+        });
+        Ok(())
+    }
+
+    /// Make a copy of the file or directory at `path` into `backup` before replacing it,
+    /// nothing will be recorded if it doesn't exist, or it's already backed up.
+    pub(crate) fn record_replace(&self, path: &Path, backup: TempDir) -> Result<()> {
+        let mut changes = self.changes();
+        if !path.exists()
+            || changes
+                .iter()
+                .any(|c| matches!(c, Change::ReplacePath { path: p, .. } if path.starts_with(p)))
+        {
+            return Ok(());
+        }
+
+        utils::copy_as(path, backup_of(&backup))
+            .with_context(|| format!("unable to backup '{}'", path.display()))?;
+        changes.push(Change::ReplacePath {
+            path: path.to_path_buf(),
+            backup,
+        });
+        Ok(())
+    }
+
+    /// Record the `original` value of an environment variable before setting it.
+    ///
+    /// Only the first value of each variable is kept, as that's the original state of it.
+    pub(crate) fn record_env_var(&self, key: &str, original: Option<String>) {
+        let mut changes = self.changes();
+        if changes
+            .iter()
+            .any(|c| matches!(c, Change::SetEnvVar { key: k, .. } if k == key))
+        {
+            return;
+        }
+        changes.push(Change::SetEnvVar {
+            key: key.to_string(),
+            original,
+        });
+    }
+
+    /// Revert every recorded change in reverse order, then clear the journal.
+    ///
+    /// A change that failed to revert will not stop the rollback, instead a warning
+    /// will be shown, as we want to restore as much as possible.
+    pub(crate) fn rollback<T: RimDir + Copy>(&self, config: T) {
+        let changes = std::mem::take(&mut *self.changes());
+        if changes.is_empty() {
+            return;
+        }
+
+        warn!("{}", t!("rollback_started"));
+        for change in changes.iter().rev() {
+            if let Err(e) = change.revert(config) {
+                warn!("{}", t!("rollback_step_failed", reason = format!("{e:#}")));
+            }
+        }
+        info!("{}", t!("rollback_finished"));
+    }
+}
+
+/// The location of the copy made by [`Journal::record_replace`].
+fn backup_of(backup: &TempDir) -> PathBuf {
+    backup.path().join("backup")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    struct TestDir<'a>(&'a Path);
+
+    impl RimDir for TestDir<'_> {
+        fn install_dir(&self) -> &Path {
+            self.0
+        }
+    }
+
+    #[test]
+    fn rollback_files_and_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let journal = Journal::default();
+
+        let existing = root.path().join("existing.txt");
+        fs::write(&existing, "original").unwrap();
+        let new_dir = root.path().join("new_dir");
+        let new_file = new_dir.join("new.txt");
+
+        journal.record_new_dir(&new_dir);
+        fs::create_dir(&new_dir).unwrap();
+        journal.record_file_write(&new_file).unwrap();
+        fs::write(&new_file, "new").unwrap();
+        journal.record_file_write(&existing).unwrap();
+        fs::write(&existing, "modified").unwrap();
+        // the second snapshot should not override the original one
+        journal.record_file_write(&existing).unwrap();
+        fs::write(&existing, "modified again").unwrap();
+
+        journal.rollback(TestDir(root.path()));
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "original");
+        assert!(!new_dir.exists());
+        assert!(journal.changes().is_empty());
+    }
+
+    #[test]
+    fn rollback_replaced_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let journal = Journal::default();
+
+        let tool_dir = root.path().join("tools").join("hello");
+        fs::create_dir_all(tool_dir.join("bin")).unwrap();
+        fs::write(tool_dir.join("bin").join("hello"), "0.1.0").unwrap();
+        let exe = root.path().join("hello");
+        fs::write(&exe, "0.1.0").unwrap();
+
+        journal
+            .record_replace(&tool_dir, tempfile::tempdir().unwrap())
+            .unwrap();
+        journal
+            .record_replace(&exe, tempfile::tempdir().unwrap())
+            .unwrap();
+        // paths inside of a backed up directory are already covered
+        journal
+            .record_replace(&tool_dir.join("bin"), tempfile::tempdir().unwrap())
+            .unwrap();
+        assert_eq!(journal.changes().len(), 2);
+
+        utils::remove(&tool_dir).unwrap();
+        fs::create_dir_all(&tool_dir).unwrap();
+        fs::write(tool_dir.join("new-file"), "0.2.0").unwrap();
+        fs::write(&exe, "0.2.0").unwrap();
+
+        journal.rollback(TestDir(root.path()));
+
+        assert_eq!(
+            fs::read_to_string(tool_dir.join("bin").join("hello")).unwrap(),
+            "0.1.0"
+        );
+        assert!(!tool_dir.join("new-file").exists());
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "0.1.0");
+    }
+}
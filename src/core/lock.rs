@@ -0,0 +1,220 @@
+//! An installation-wide advisory lock, preventing multiple operations
+//! (such as installing components in GUI while updating in CLI) from
+//! modifying the same installation at the same time.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use fs2::FileExt;
+use rim_common::dirs::rim_config_dir;
+use rim_common::types::TomlParser;
+use serde::{Deserialize, Serialize};
+
+/// Lock files that are held by the current process, and how many times each of them
+/// was acquired, so the same process can acquire the lock again without blocking itself.
+static HELD_LOCKS: Mutex<Option<HashMap<PathBuf, HeldLock>>> = Mutex::new(None);
+
+/// An opened lock file that the OS lock was put on, the lock is released once it gets closed.
+#[derive(Debug)]
+struct HeldLock {
+    file: File,
+    count: usize,
+}
+
+/// Information about the operation that holds the lock.
+///
+/// This is written into the lock file for informational purpose only,
+/// whether the lock is held or not is determined by the OS file lock.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LockInfo {
+    /// The process id of the holder.
+    pub pid: u32,
+    /// The time when the lock was acquired.
+    pub started: DateTime<Local>,
+}
+
+impl TomlParser for LockInfo {
+    const FILENAME: &'static str = "operation.lock";
+}
+
+impl LockInfo {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            started: Local::now(),
+        }
+    }
+}
+
+/// A guard of the installation lock, which will be released once dropped.
+#[derive(Debug)]
+#[must_use = "the lock will be released immediately if not used"]
+pub struct InstallationLock {
+    path: PathBuf,
+}
+
+impl InstallationLock {
+    /// Acquire the lock of the current installation.
+    ///
+    /// # Error
+    /// Return error if another process is performing operations on the installation.
+    pub fn acquire() -> Result<Self> {
+        Self::acquire_in(rim_config_dir())
+    }
+
+    /// Get the information of the running operation, return `None` if there's none.
+    pub fn state() -> Option<LockInfo> {
+        Self::state_in(rim_config_dir())
+    }
+
+    fn state_in(dir: &Path) -> Option<LockInfo> {
+        let path = dir.join(LockInfo::FILENAME);
+        let is_held_by_self = HELD_LOCKS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_some_and(|held| held.contains_key(&path));
+        if !is_held_by_self {
+            let file = File::open(&path).ok()?;
+            // nobody is holding the lock if we can get it
+            if file.try_lock_exclusive().is_ok() {
+                _ = FileExt::unlock(&file);
+                return None;
+            }
+        }
+        LockInfo::load(&path).ok()
+    }
+
+    fn acquire_in(dir: &Path) -> Result<Self> {
+        let path = dir.join(LockInfo::FILENAME);
+        let mut guard = HELD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+        let held = guard.get_or_insert_with(HashMap::new);
+        if let Some(lock) = held.get_mut(&path) {
+            lock.count += 1;
+            return Ok(Self { path });
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("unable to open lock file '{}'", path.display()))?;
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.kind() != fs2::lock_contended_error().kind() {
+                return Err(e).with_context(|| format!("unable to lock file '{}'", path.display()));
+            }
+            match LockInfo::load(&path) {
+                Ok(info) => bail!(t!(
+                    "operation_in_progress",
+                    pid = info.pid,
+                    started = info.started.format("%Y-%m-%d %H:%M:%S"),
+                    path = path.display()
+                )),
+                // the holder might not have written its information yet,
+                // or the content cannot be read while being locked (on Windows)
+                Err(_) => bail!(t!("operation_in_progress_unknown", path = path.display())),
+            }
+        }
+
+        let content = LockInfo::current().to_toml()?;
+        file.set_len(0)
+            .and_then(|_| file.write_all(content.as_bytes()))
+            .with_context(|| format!("unable to write lock file '{}'", path.display()))?;
+        held.insert(path.clone(), HeldLock { file, count: 1 });
+        Ok(Self { path })
+    }
+}
+
+impl Drop for InstallationLock {
+    fn drop(&mut self) {
+        let mut guard = HELD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(held) = guard.as_mut() else {
+            return;
+        };
+        let Some(lock) = held.get_mut(&self.path) else {
+            return;
+        };
+        lock.count -= 1;
+        if lock.count == 0 {
+            let Some(lock) = held.remove(&self.path) else {
+                return;
+            };
+            // The lock file is kept, as removing it would allow another process that has
+            // already opened it to lock a file that no longer exists, while a third one
+            // creates and locks a new file. Only clear the stale information before unlocking.
+            if let Err(e) = lock.file.set_len(0) {
+                // the lock file might already be removed along with the config dir after uninstallation
+                if e.kind() != ErrorKind::NotFound {
+                    warn!("unable to clear lock '{}': {e}", self.path.display());
+                }
+            }
+            if let Err(e) = FileExt::unlock(&lock.file) {
+                warn!("unable to release lock '{}': {e}", self.path.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reentrant_in_same_process() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let first = InstallationLock::acquire_in(dir.path()).unwrap();
+        let second = InstallationLock::acquire_in(dir.path()).unwrap();
+        let state = InstallationLock::state_in(dir.path()).unwrap();
+        assert_eq!(state.pid, std::process::id());
+
+        drop(first);
+        assert!(InstallationLock::state_in(dir.path()).is_some());
+        drop(second);
+        assert!(InstallationLock::state_in(dir.path()).is_none());
+    }
+
+    #[test]
+    fn ignore_unlocked_lock_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // the information left by an interrupted operation, but nobody holds the lock
+        let stale = LockInfo {
+            pid: u32::MAX - 1,
+            started: Local::now(),
+        };
+        stale.write_to_dir(dir.path()).unwrap();
+        assert!(InstallationLock::state_in(dir.path()).is_none());
+
+        let _lock = InstallationLock::acquire_in(dir.path()).unwrap();
+        let state = InstallationLock::state_in(dir.path()).unwrap();
+        assert_eq!(state.pid, std::process::id());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reject_lock_held_by_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let info = LockInfo {
+            pid: 1,
+            started: Local::now(),
+        };
+        info.write_to_dir(dir.path()).unwrap();
+        // a lock on another file description conflicts as if it was from another process
+        let other = File::open(dir.path().join(LockInfo::FILENAME)).unwrap();
+        other.lock_exclusive().unwrap();
+
+        assert_eq!(InstallationLock::state_in(dir.path()), Some(info));
+        let err = InstallationLock::acquire_in(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("pid 1"));
+
+        FileExt::unlock(&other).unwrap();
+        assert!(InstallationLock::acquire_in(dir.path()).is_ok());
+    }
+}
@@ -2,28 +2,42 @@
 //!
 //! Including configuration, toolchain, toolset management.
 
+pub(crate) mod apply;
 pub(crate) mod check;
 pub mod components;
 mod custom_instructions;
 mod dependency_handler;
 pub(crate) mod directories;
+pub(crate) mod doctor;
+pub(crate) mod export;
+pub(crate) mod history;
 pub mod install;
+pub(crate) mod journal;
+pub mod lock;
 pub(crate) mod os;
 pub(crate) mod parser;
 mod path_ext;
+pub mod preflight;
+pub(crate) mod repair;
 pub(crate) mod rustup;
+pub(crate) mod signature;
 pub mod toolkit;
 mod toolkit_manifest_ext;
 pub(crate) mod tools;
 pub mod try_it;
 pub(crate) mod uninstall;
 pub mod update;
+pub(crate) mod vendored_crates;
+pub(crate) mod verify;
 
 // re-exports
 pub(crate) use path_ext::PathExt;
 pub use toolkit_manifest_ext::*;
 
-use crate::{cli, fingerprint::InstallationRecord};
+use crate::{
+    cli::{self, OutputFormat},
+    fingerprint::InstallationRecord,
+};
 use anyhow::{bail, Result};
 use rim_common::{
     build_config,
@@ -99,20 +113,41 @@ pub struct GlobalOpts {
     pub yes_to_all: bool,
     no_modify_env: bool,
     no_modify_path: bool,
+    allow_unsigned: bool,
+    output_format: OutputFormat,
 }
 
 impl GlobalOpts {
-    /// Initialize a new object and store it globally
+    /// Initialize a new object and store it globally.
+    ///
+    /// The options that are set separately, such as [`allow_unsigned`](GlobalOpts::allow_unsigned)
+    /// and the output format, are kept.
     pub fn set(verbose: bool, quiet: bool, yes: bool, no_modify_env: bool, no_modify_path: bool) {
-        let opts = Self {
-            verbose,
-            quiet,
-            yes_to_all: yes,
-            no_modify_env,
-            no_modify_path,
-        };
+        let mut guard = GLOBAL_OPTS.lock().unwrap();
+        let opts = guard.get_or_insert_with(Self::default);
+        opts.verbose = verbose;
+        opts.quiet = quiet;
+        opts.yes_to_all = yes;
+        opts.no_modify_env = no_modify_env;
+        opts.no_modify_path = no_modify_path;
+    }
+
+    /// Allow using unsigned or badly signed manifests from server.
+    pub fn set_allow_unsigned(yes: bool) {
+        GLOBAL_OPTS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Self::default)
+            .allow_unsigned = yes;
+    }
 
-        *GLOBAL_OPTS.lock().unwrap() = Some(opts);
+    /// Set the format of command output.
+    pub fn set_output_format(format: OutputFormat) {
+        GLOBAL_OPTS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Self::default)
+            .output_format = format;
     }
 
     /// Get the stored global options.
@@ -131,6 +166,16 @@ impl GlobalOpts {
     pub fn no_modify_env(&self) -> bool {
         self.no_modify_env
     }
+
+    /// Return `true` if `allow-unsigned` was set to `true`
+    pub fn allow_unsigned(&self) -> bool {
+        self.allow_unsigned
+    }
+
+    /// Return `true` if the command output should be printed as JSON
+    pub fn json_output(&self) -> bool {
+        self.output_format == OutputFormat::Json
+    }
 }
 
 /// Representing the execution mode of this program.
@@ -42,3 +42,28 @@ pub(crate) fn remove_from_path<R: RimDir + Copy>(_config: R, path: &Path) -> Res
 
     Ok(())
 }
+
+/// Restore an environment variable to its `original` value,
+/// or remove it if it wasn't set originally.
+pub(crate) fn restore_env_var(key: &str, original: Option<&str>) -> Result<()> {
+    #[cfg(windows)]
+    windows::restore_env_var(key, original)?;
+
+    #[cfg(unix)]
+    unix::restore_env_var(key, original)?;
+
+    Ok(())
+}
+
+/// Get the available disk space (in bytes) of the filesystem that `path` is on.
+pub(crate) fn available_space(path: &Path) -> Result<u64> {
+    #[cfg(windows)]
+    {
+        windows::available_space(path)
+    }
+
+    #[cfg(unix)]
+    {
+        unix::available_space(path)
+    }
+}
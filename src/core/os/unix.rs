@@ -32,6 +32,7 @@ impl<T> EnvConfig for InstallConfiguration<'_, T> {
             for (key, val) in &vars_raw {
                 update_content(&mut env_content, &sh.export_string(key, val), false);
             }
+            self.journal.record_file_write(&script_path)?;
             utils::write_file(&script_path, &env_content, false)?;
 
             // secondly, insert a source command to rc files if needed
@@ -40,6 +41,9 @@ impl<T> EnvConfig for InstallConfiguration<'_, T> {
             } else {
                 let rcs = sh.update_rcs();
                 create_rc_backup(&rcs, self.backup_dir())?;
+                for rc in &rcs {
+                    self.journal.record_file_write(rc)?;
+                }
                 ensure_env_config_in_rcs(self, &sh, rcs.iter())?;
             }
         }
@@ -47,6 +51,7 @@ impl<T> EnvConfig for InstallConfiguration<'_, T> {
         // Update vars for current process, this is a MUST to ensure this installation
         // can be done correctly.
         for (key, val) in vars_raw {
+            self.journal.record_env_var(key, env::var(key).ok());
             env::set_var(key, val);
         }
 
@@ -259,6 +264,49 @@ pub(super) fn remove_from_path<T: RimDir + Copy>(config: T, path: &Path) -> Resu
     modify_path(config, path, true)
 }
 
+/// Restore an environment variable for current process.
+///
+/// Persistent env vars are written in the env script on unix,
+/// which should be restored separately.
+pub(super) fn restore_env_var(key: &str, original: Option<&str>) -> Result<()> {
+    match original {
+        Some(val) => env::set_var(key, val),
+        None => env::remove_var(key),
+    }
+    Ok(())
+}
+
+/// Get the available disk space (in bytes) of the filesystem that `path` is on.
+pub(super) fn available_space(path: &Path) -> Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    // the field types vary between platforms
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Return the rc files of available shells that do not source the env script yet.
+pub(crate) fn rcs_without_env_config(install_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut res = vec![];
+    for sh in shell::get_available_shells() {
+        let script_path = install_dir.join(sh.env_script().name);
+        let source_cmd = sh.source_string(utils::path_to_str(&script_path)?);
+        for rc in sh.update_rcs() {
+            let content = utils::read_to_string("rc", &rc).unwrap_or_default();
+            if !content.lines().any(|ln| ln == source_cmd) {
+                res.push(rc);
+            }
+        }
+    }
+    Ok(res)
+}
+
 /// Returns a string that looks like `source [rc]` where `[rc]` is a path
 /// to any rc file of any available shell in the user mechine.
 pub(crate) fn env_script_path(install_dir: &Path) -> Option<PathBuf> {
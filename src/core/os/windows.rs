@@ -14,6 +14,12 @@ impl<T> EnvConfig for InstallConfiguration<'_, T> {
         info!("{}", t!("install_env_config"));
 
         for (key, val) in self.env_vars()? {
+            let original = if GlobalOpts::get().no_modify_env() {
+                std::env::var(key).ok()
+            } else {
+                get_persist_env_var(key)?
+            };
+            self.journal.record_env_var(key, original);
             set_env_var(key, val.encode_utf16().collect())?;
         }
         update_env();
@@ -75,6 +81,47 @@ impl<T> Uninstallation for UninstallConfiguration<T> {
     }
 }
 
+/// Restore an environment variable to its `original` value, or remove it if it wasn't set.
+pub(super) fn restore_env_var(key: &str, original: Option<&str>) -> Result<()> {
+    match original {
+        Some(val) => set_env_var(key, val.encode_utf16().collect())?,
+        None => unset_env_var(key)?,
+    }
+    update_env();
+    Ok(())
+}
+
+/// Get the available disk space (in bytes) of the filesystem that `path` is on.
+pub(super) fn available_space(path: &std::path::Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::shared::ntdef::ULARGE_INTEGER;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+
+    let wide_path = path
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect::<Vec<_>>();
+    unsafe {
+        let mut available: ULARGE_INTEGER = std::mem::zeroed();
+        let succeed = GetDiskFreeSpaceExW(
+            wide_path.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        ) != 0;
+        if !succeed {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(*available.QuadPart())
+    }
+}
+
+/// Remove this program from the "installed programs" list.
+pub(crate) fn remove_from_programs() -> Result<()> {
+    do_remove_from_programs(uninstall_entry())
+}
+
 /// A module that contains functions that are modified from `rustup`:
 /// https://github.com/rust-lang/rustup/blob/master/src/cli/self_update/windows.rs
 pub(crate) mod rustup {
@@ -204,6 +251,16 @@ pub(crate) mod rustup {
         }
     }
 
+    /// Get the value of environment variable `key` of current user,
+    /// return `None` if it was not set.
+    pub(super) fn get_persist_env_var(key: &str) -> Result<Option<String>> {
+        match environment()?.get_raw_value(key) {
+            Ok(val) => Ok(from_winreg_value(&val).map(|v| String::from_utf16_lossy(&v))),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(anyhow!(e)),
+        }
+    }
+
     /// Set the environment variable `key` with a given `value`.
     ///
     /// This will modify the environment permanently for current user,
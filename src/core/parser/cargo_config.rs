@@ -1,9 +1,9 @@
 //! Module defining types that could be serialized to a working `config.toml` for cargo.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use indexmap::IndexMap;
-use rim_common::types::TomlParser;
+use rim_common::types::{CargoRegistry, TomlParser};
 use serde::{Deserialize, Serialize};
 
 /// A simple struct representing the fields in `config.toml`.
@@ -68,6 +68,44 @@ impl CargoConfig {
     /// - `as_default` specify whether this source is used as a replaced source of `crates-io`,
     ///   note the first `add_source` call will always be default.
     pub(crate) fn add_source(&mut self, key: &str, url: &str, as_default: bool) -> &mut Self {
+        self.replace_crates_io(key, as_default);
+        self.source.insert(
+            key.to_string(),
+            Source {
+                registry: Some(url.into()),
+                ..Default::default()
+            },
+        );
+
+        self
+    }
+
+    /// Insert a directory source, which contains crates in the same layout as
+    /// the output of `cargo vendor`.
+    ///
+    /// Just like [`add_source`](CargoConfig::add_source), the first source inserted
+    /// will always be used as a replaced source of `crates-io`.
+    pub(crate) fn add_directory_source<P: AsRef<Path>>(
+        &mut self,
+        key: &str,
+        dir: P,
+        as_default: bool,
+    ) -> &mut Self {
+        self.replace_crates_io(key, as_default);
+        // make sure `cargo` can read this config on Windows
+        let dir = dir.as_ref().display().to_string().replace('\\', "/");
+        self.source.insert(
+            key.to_string(),
+            Source {
+                directory: Some(dir),
+                ..Default::default()
+            },
+        );
+
+        self
+    }
+
+    fn replace_crates_io(&mut self, key: &str, as_default: bool) {
         self.source
             .entry("crates-io".to_string())
             .and_modify(|s| {
@@ -79,16 +117,13 @@ impl CargoConfig {
                 replace_with: Some(key.to_string()),
                 ..Default::default()
             });
+    }
 
-        self.source.insert(
-            key.to_string(),
-            Source {
-                registry: Some(url.into()),
-                ..Default::default()
-            },
-        );
-
-        self
+    /// Get the registry that replaces `crates-io`, if there is one.
+    pub(crate) fn replaced_registry(&self) -> Option<CargoRegistry> {
+        let name = self.source.get("crates-io")?.replace_with.as_deref()?;
+        let index = self.source.get(name)?.registry.as_deref()?;
+        Some((name, index).into())
     }
 
     /// Insert a dependency patch ([`DependencyPatch`]) into the patch section.
@@ -153,6 +188,7 @@ pub(crate) struct CargoHttpConfig {
 pub(crate) struct Source {
     pub(crate) replace_with: Option<String>,
     pub(crate) registry: Option<String>,
+    pub(crate) directory: Option<String>,
 }
 
 /// Flip all backward splashes (`\`) to forward splash (`/`) when serializing paths.
@@ -203,6 +239,41 @@ registry = "https://example.com/registry"
         );
     }
 
+    #[test]
+    fn cargo_config_with_directory_source() {
+        let config = CargoConfig::new()
+            .add_source("mirror", "https://example.com/registry", true)
+            .add_directory_source("vendored-crates", "/path/to/crates", true)
+            .to_toml()
+            .unwrap();
+
+        assert_eq!(
+            config,
+            r#"[source.crates-io]
+replace-with = "vendored-crates"
+
+[source.mirror]
+registry = "https://example.com/registry"
+
+[source.vendored-crates]
+directory = "/path/to/crates"
+"#
+        );
+    }
+
+    #[test]
+    fn cargo_config_replaced_registry() {
+        assert!(CargoConfig::new().replaced_registry().is_none());
+
+        let mut config = CargoConfig::new();
+        config
+            .add_source("mirror", "https://example.com/registry", true)
+            .add_source("another", "https://example.com/another", false);
+        let registry = config.replaced_registry().unwrap();
+        assert_eq!(registry.name, "mirror");
+        assert_eq!(registry.index, "https://example.com/registry");
+    }
+
     #[test]
     fn cargo_config_insert_patch() {
         let config = CargoConfig::new()
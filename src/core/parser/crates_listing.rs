@@ -0,0 +1,145 @@
+//! Module for reading the lists of crates installed by `cargo install`,
+//! which are `.crates2.json` and the legacy `.crates.toml` under `CARGO_HOME`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rim_common::utils;
+use serde::Deserialize;
+
+/// Crates installed by `cargo install` and the binaries each of them provides,
+/// keyed by their package id, such as `foo 0.1.0 (registry+https://...)`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct CratesListing(BTreeMap<String, Vec<String>>);
+
+#[derive(Deserialize)]
+struct CratesListingV2 {
+    #[serde(default)]
+    installs: BTreeMap<String, InstallInfoV2>,
+}
+
+#[derive(Deserialize)]
+struct InstallInfoV2 {
+    #[serde(default)]
+    bins: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CratesListingV1 {
+    #[serde(default)]
+    v1: BTreeMap<String, Vec<String>>,
+}
+
+impl CratesListing {
+    const V2_FILENAME: &'static str = ".crates2.json";
+    const V1_FILENAME: &'static str = ".crates.toml";
+
+    /// Load the list of installed crates from `cargo_home`,
+    /// return an empty list if nothing was installed yet.
+    pub(crate) fn load(cargo_home: &Path) -> Result<Self> {
+        let v2_path = cargo_home.join(Self::V2_FILENAME);
+        if v2_path.is_file() {
+            let content = utils::read_to_string("installed crates", &v2_path)?;
+            let listing: CratesListingV2 = serde_json::from_str(&content)
+                .with_context(|| format!("failed to parse '{}'", v2_path.display()))?;
+            return Ok(Self(
+                listing
+                    .installs
+                    .into_iter()
+                    .map(|(id, info)| (id, info.bins))
+                    .collect(),
+            ));
+        }
+
+        let v1_path = cargo_home.join(Self::V1_FILENAME);
+        if v1_path.is_file() {
+            let content = utils::read_to_string("installed crates", &v1_path)?;
+            let listing: CratesListingV1 = toml::from_str(&content)
+                .with_context(|| format!("failed to parse '{}'", v1_path.display()))?;
+            return Ok(Self(listing.v1));
+        }
+
+        Ok(Self::default())
+    }
+
+    /// Return the binaries (file names under `CARGO_HOME/bin`) installed by `package`.
+    ///
+    /// If `package` is not listed, such as a crate installed from git under a different
+    /// name, fallback to the binaries of the crates that are new or changed compared
+    /// to the listing `before` the installation.
+    pub(crate) fn installed_bins(&self, package: &str, before: &Self) -> Vec<String> {
+        let by_name = self
+            .0
+            .iter()
+            .filter(|(id, _)| id.split(' ').next() == Some(package))
+            .flat_map(|(_, bins)| bins.clone())
+            .collect::<Vec<_>>();
+        if !by_name.is_empty() {
+            return by_name;
+        }
+
+        self.0
+            .iter()
+            .filter(|(id, bins)| before.0.get(*id) != Some(*bins))
+            .flat_map(|(_, bins)| bins.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_crates2_json() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        std::fs::write(
+            cargo_home.path().join(".crates2.json"),
+            r#"{"installs":{
+"foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)":{"version_req":null,"bins":["foo","foo-cli"],"features":[],"all_features":false,"no_default_features":false,"profile":"release","target":"x86_64-unknown-linux-gnu","rustc":"rustc 1.86.0"},
+"bar 0.2.0 (git+https://example.com/bar#abcdef)":{"bins":["bar"]}
+}}"#,
+        )
+        .unwrap();
+
+        let listing = CratesListing::load(cargo_home.path()).unwrap();
+        assert_eq!(
+            listing.installed_bins("foo", &CratesListing::default()),
+            ["foo", "foo-cli"]
+        );
+
+        // `bar` was installed from git with a tool named differently
+        let before = CratesListing(BTreeMap::from([(
+            "foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)".to_string(),
+            vec!["foo".to_string(), "foo-cli".to_string()],
+        )]));
+        assert_eq!(listing.installed_bins("bar-tool", &before), ["bar"]);
+    }
+
+    #[test]
+    fn read_legacy_crates_toml() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        std::fs::write(
+            cargo_home.path().join(".crates.toml"),
+            r#"[v1]
+"foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)" = ["foo"]
+"#,
+        )
+        .unwrap();
+
+        let listing = CratesListing::load(cargo_home.path()).unwrap();
+        assert_eq!(
+            listing.installed_bins("foo", &CratesListing::default()),
+            ["foo"]
+        );
+        assert!(listing.installed_bins("baz", &listing).is_empty());
+    }
+
+    #[test]
+    fn nothing_installed() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let listing = CratesListing::load(cargo_home.path()).unwrap();
+        assert_eq!(listing, CratesListing::default());
+    }
+}
@@ -1,9 +1,11 @@
 use anyhow::{anyhow, Context, Result};
 use rim_common::dirs::rim_config_dir;
-use rim_common::types::{TomlParser, ToolKind, ToolkitManifest};
+use rim_common::types::{ExtraToolchain, TomlParser, ToolKind, ToolkitManifest};
 use rim_common::utils;
 use serde::{de::Visitor, Deserialize, Deserializer, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
 use std::{collections::HashMap, path::PathBuf};
 
 use crate::components::ToolchainComponent;
@@ -19,7 +21,15 @@ pub struct InstallationRecord {
     pub name: Option<String>,
     pub version: Option<String>,
     pub edition: Option<String>,
+    /// Names of the components that are held at their installed version,
+    /// which will be left untouched when updating.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub held: BTreeSet<String>,
     pub rust: Option<RustRecord>,
+    /// Additional toolchains installed along with the default one, mapped by their names
+    /// in toolkit manifest.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_toolchains: BTreeMap<String, RustRecord>,
     #[serde(default)]
     pub tools: HashMap<String, ToolRecord>,
 }
@@ -76,12 +86,20 @@ impl InstallationRecord {
     pub(crate) fn add_rust_record(&mut self, version: &str, components: &[ToolchainComponent]) {
         let entry = self.rust.get_or_insert_with(RustRecord::default);
         entry.version = version.to_string();
+        if let Some(profile) = components.iter().find(|c| c.is_profile) {
+            entry.profile = Some(profile.name.clone());
+        }
 
         for component in components.iter().filter(|c| !c.is_profile) {
-            if entry.components.iter().any(|c| c == &component.name) {
+            let list = if component.is_target {
+                &mut entry.targets
+            } else {
+                &mut entry.components
+            };
+            if list.iter().any(|c| c == &component.name) {
                 continue;
             }
-            entry.components.push(component.name.clone());
+            list.push(component.name.clone());
         }
     }
 
@@ -89,8 +107,27 @@ impl InstallationRecord {
         self.tools.insert(name.into(), record);
     }
 
+    /// Adds installation record for an additional toolchain, replacing the previous one.
+    pub(crate) fn add_extra_toolchain_record(&mut self, name: &str, toolchain: &ExtraToolchain) {
+        let record = RustRecord {
+            version: toolchain.channel.clone(),
+            profile: None,
+            components: toolchain.components.clone(),
+            targets: vec![],
+            alias: toolchain.alias.clone(),
+        };
+        self.extra_toolchains.insert(name.into(), record);
+    }
+
+    pub(crate) fn remove_extra_toolchain_record(&mut self, name: &str) {
+        self.extra_toolchains.remove(name);
+    }
+
+    /// Remove the record of rust toolchain, including the additional ones,
+    /// as they cannot be used without `rustup`.
     pub fn remove_rust_record(&mut self) {
         self.rust = None;
+        self.extra_toolchains.clear();
     }
 
     /// Remove a list of toolchain components from record
@@ -104,24 +141,63 @@ impl InstallationRecord {
         if components_to_remove.is_empty() {
             return;
         }
-        let components_after_remove = rust
-            .components
-            .iter()
-            .filter_map(|c| (!components_to_remove.contains(c)).then_some(c.clone()))
-            .collect();
-
-        rust.components = components_after_remove;
+        rust.components
+            .retain(|c| !components_to_remove.contains(c));
+        rust.targets.retain(|t| !components_to_remove.contains(t));
     }
 
     pub fn remove_tool_record(&mut self, tool_name: &str) {
         self.tools.remove(tool_name);
+        self.held.remove(tool_name);
+    }
+
+    /// Check if a component is held at its installed version.
+    pub fn is_held(&self, name: &str) -> bool {
+        self.held.contains(name)
     }
 
-    /// Retrieve a list of installed toolchain components only
+    /// Check if the rust toolchain is held, which is done by holding its profile component.
+    ///
+    /// The toolchain components and targets are held along with the toolchain,
+    /// as they cannot be updated separately.
+    pub fn toolchain_is_held(&self) -> bool {
+        self.rust
+            .as_ref()
+            .and_then(|rr| rr.profile.as_deref())
+            .is_some_and(|profile| self.is_held(profile))
+    }
+
+    /// Hold a component at its installed version, return `false` if it was already held.
+    pub(crate) fn hold(&mut self, name: &str) -> bool {
+        self.held.insert(name.to_string())
+    }
+
+    /// Release the hold of a component, return `false` if it wasn't held.
+    pub(crate) fn unhold(&mut self, name: &str) -> bool {
+        self.held.remove(name)
+    }
+
+    /// Retrieve a list of installed toolchain components only, including the targets.
     pub fn installed_toolchain_components(&self) -> Vec<ToolchainComponent> {
         let Some(rr) = &self.rust else { return vec![] };
 
-        rr.components.iter().map(ToolchainComponent::new).collect()
+        rr.components
+            .iter()
+            .map(ToolchainComponent::new)
+            .chain(
+                rr.targets
+                    .iter()
+                    .map(|t| ToolchainComponent::new(t).is_target(true)),
+            )
+            .collect()
+    }
+
+    /// Returns the additional compilation targets installed for rust toolchain.
+    pub fn installed_toolchain_targets(&self) -> &[String] {
+        self.rust
+            .as_ref()
+            .map(|rr| rr.targets.as_slice())
+            .unwrap_or_default()
     }
 
     /// Returns the rust toolchain channel name (such as `stable`, `nightly`, `1.80.1`, etc.),
@@ -137,6 +213,10 @@ impl InstallationRecord {
         if let Some(rust) = &self.rust {
             installed.push_str(&rust.print_rust_info());
         }
+        for (name, rust) in &self.extra_toolchains {
+            installed.push_str(&format!("extra-toolchain: {name:?}\n"));
+            installed.push_str(&rust.print_rust_info());
+        }
         for tool in self.tools.iter() {
             installed.push_str(&format!("tools: {:?} \n", tool.0));
         }
@@ -157,18 +237,40 @@ impl InstallationRecord {
 #[serde(rename_all = "kebab-case")]
 pub struct RustRecord {
     version: String,
+    /// Name of the toolchain profile component, which is used to hold the whole toolchain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
     /// Rust toolchain components, including the base profile (minimal/default/etc.),
     /// and extra components selected by user.
     #[serde(default)]
     pub(crate) components: Vec<String>,
+    /// Additional compilation targets, such as `wasm32-unknown-unknown`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) targets: Vec<String>,
+    /// Custom toolchain name that links to this toolchain, only used by additional toolchains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
 }
 
 impl RustRecord {
+    /// The toolchain channel name, such as `stable`, `nightly-2024-10-01`, `1.80.1`, etc.
+    pub(crate) fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub(crate) fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
     pub(crate) fn print_rust_info(&self) -> String {
-        format!(
+        let mut info = format!(
             "rust-version: {}\ncomponents: {:?}\n",
             self.version, self.components
-        )
+        );
+        if !self.targets.is_empty() {
+            info.push_str(&format!("targets: {:?}\n", self.targets));
+        }
+        info
     }
 }
 
@@ -189,6 +291,12 @@ pub struct ToolRecord {
     pub(crate) paths: Vec<PathBuf>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub(crate) dependencies: Vec<String>,
+    /// The verified sha256 checksum of the package this tool was installed from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    /// The files that were placed by the installation of this tool.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) files: Vec<FileRecord>,
 }
 
 impl ToolRecord {
@@ -218,6 +326,83 @@ impl ToolRecord {
     setter!(with_paths(self.paths, Vec<PathBuf>));
     setter!(with_version(self.version, ver: Option<impl Into<String>>) { ver.map(Into::into) });
     setter!(with_dependencies(self.dependencies, Vec<String>));
+    setter!(with_sha256(self.sha256, Option<String>));
+    setter!(with_files(self.files, Vec<FileRecord>));
+}
+
+/// A file that was placed by the installation of a tool.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FileRecord {
+    pub(crate) path: PathBuf,
+    pub(crate) size: u64,
+    /// Checksum of the file content, symbolic links don't have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sha256: Option<String>,
+}
+
+/// The state of an installed file compared to its [`FileRecord`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileState {
+    Intact,
+    Missing,
+    Modified,
+}
+
+impl FileRecord {
+    /// Record every file under the given `paths`, directories will be walked recursively,
+    /// symbolic links will be recorded as is, without following them.
+    pub(crate) fn collect<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<Self>> {
+        fn collect_(path: &Path, files: &mut Vec<FileRecord>) -> Result<()> {
+            let meta = fs::symlink_metadata(path)
+                .with_context(|| format!("unable to read metadata of '{}'", path.display()))?;
+            if meta.is_dir() {
+                let mut entries = fs::read_dir(path)?
+                    .map(|entry| entry.map(|e| e.path()))
+                    .collect::<std::io::Result<Vec<_>>>()?;
+                entries.sort();
+                for entry in entries {
+                    collect_(&entry, files)?;
+                }
+            } else {
+                let sha256 = (!meta.is_symlink())
+                    .then(|| utils::sha256_of(path))
+                    .transpose()?;
+                files.push(FileRecord {
+                    path: path.to_path_buf(),
+                    size: meta.len(),
+                    sha256,
+                });
+            }
+            Ok(())
+        }
+
+        let mut files = vec![];
+        for path in paths {
+            // some of the paths could be missing, such as the ones removed by installers
+            if fs::symlink_metadata(path.as_ref()).is_ok() {
+                collect_(path.as_ref(), &mut files)?;
+            }
+        }
+        Ok(files)
+    }
+
+    /// Compare the file on disk with this record.
+    pub(crate) fn state(&self) -> FileState {
+        let Ok(meta) = fs::symlink_metadata(&self.path) else {
+            return FileState::Missing;
+        };
+        let Some(expected) = &self.sha256 else {
+            return FileState::Intact;
+        };
+        if meta.is_dir() || meta.len() != self.size {
+            return FileState::Modified;
+        }
+        match utils::sha256_of(&self.path) {
+            Ok(actual) if &actual == expected => FileState::Intact,
+            _ => FileState::Modified,
+        }
+    }
 }
 
 // `use-cargo = true/false` was used during [0.2.0, 0.3.0], in order not to break
@@ -292,6 +477,102 @@ paths = [{QUOTE}{}{QUOTE}]
         assert_eq!(v0, fp.to_toml().unwrap());
     }
 
+    #[test]
+    fn toolchain_target_records() {
+        let mut fp = InstallationRecord {
+            install_dir: "/some/path".into(),
+            ..Default::default()
+        };
+        fp.add_rust_record(
+            "stable",
+            &[
+                ToolchainComponent::new("clippy"),
+                ToolchainComponent::new("wasm32-unknown-unknown").is_target(true),
+            ],
+        );
+        let ser = fp.to_toml().unwrap();
+        assert!(ser.contains(
+            "[rust]\nversion = \"stable\"\ncomponents = [\"clippy\"]\ntargets = [\"wasm32-unknown-unknown\"]\n"
+        ));
+        assert_eq!(fp.installed_toolchain_targets(), ["wasm32-unknown-unknown"]);
+        assert_eq!(fp.installed_toolchain_components().len(), 2);
+
+        fp.remove_component_record(&[
+            ToolchainComponent::new("wasm32-unknown-unknown").is_target(true)
+        ]);
+        assert!(fp.installed_toolchain_targets().is_empty());
+        assert!(!fp.to_toml().unwrap().contains("targets"));
+    }
+
+    #[test]
+    fn extra_toolchain_records() {
+        let mut fp = InstallationRecord {
+            install_dir: "/some/path".into(),
+            ..Default::default()
+        };
+        fp.add_rust_record("stable", &[]);
+        let nightly = ExtraToolchain {
+            channel: "nightly-2024-10-01".into(),
+            components: vec!["miri".into()],
+            alias: Some("fuzz".into()),
+            ..Default::default()
+        };
+        fp.add_extra_toolchain_record("nightly", &nightly);
+
+        let ser = fp.to_toml().unwrap();
+        let expected = r#"
+[extra_toolchains.nightly]
+version = "nightly-2024-10-01"
+components = ["miri"]
+alias = "fuzz"
+"#;
+        assert!(ser.contains(expected));
+
+        let rec = InstallationRecord::from_str(&ser).unwrap();
+        let nightly_rec = &rec.extra_toolchains["nightly"];
+        assert_eq!(nightly_rec.version(), "nightly-2024-10-01");
+        assert_eq!(nightly_rec.alias(), Some("fuzz"));
+        assert!(rec.rust.unwrap().alias().is_none());
+
+        fp.remove_rust_record();
+        assert!(fp.extra_toolchains.is_empty());
+    }
+
+    #[test]
+    fn hold_components() {
+        let mut fp = InstallationRecord::from_str(
+            r#"
+root = '/path/to/something'
+held = ["aaa"]
+
+[tools.aaa]
+kind = "custom"
+paths = []
+
+[tools.bbb]
+kind = "custom"
+paths = []
+"#,
+        )
+        .unwrap();
+        assert!(fp.is_held("aaa"));
+        assert!(!fp.is_held("bbb"));
+
+        assert!(fp.hold("bbb"));
+        assert!(!fp.hold("bbb"));
+        assert!(fp
+            .to_toml()
+            .unwrap()
+            .contains("held = [\"aaa\", \"bbb\"]\n"));
+
+        // removed tools are no longer held
+        fp.remove_tool_record("aaa");
+        assert!(!fp.is_held("aaa"));
+        assert!(fp.unhold("bbb"));
+        assert!(!fp.unhold("bbb"));
+        assert!(!fp.to_toml().unwrap().contains("held"));
+    }
+
     #[test]
     fn with_name_and_ver() {
         let input = r#"
@@ -411,4 +692,58 @@ b = { kind = "executables", paths = ["/some/other/path"] }
         let a = rec.tools.get("a").unwrap();
         assert_eq!(a.dependencies, ["b"]);
     }
+
+    #[test]
+    fn with_checksum() {
+        let record = InstallationRecord {
+            install_dir: "/some/path".into(),
+            tools: HashMap::from([(
+                "a".into(),
+                ToolRecord::new(ToolKind::Executables).with_sha256(Some("abc".into())),
+            )]),
+            ..Default::default()
+        };
+        let ser = record.to_toml().unwrap();
+        let expected = r#"install_dir = "/some/path"
+
+[tools.a]
+kind = "executables"
+paths = []
+sha256 = "abc"
+"#;
+        assert_eq!(ser, expected);
+
+        let rec = InstallationRecord::from_str(&ser).unwrap();
+        assert_eq!(rec.tools["a"].sha256.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn record_installed_files() {
+        let root = tempfile::tempdir().unwrap();
+        let tool_dir = root.path().join("tool");
+        fs::create_dir_all(tool_dir.join("bin")).unwrap();
+        fs::write(tool_dir.join("bin").join("a"), "a").unwrap();
+        fs::write(tool_dir.join("README"), "readme").unwrap();
+        let single = root.path().join("b");
+        fs::write(&single, "b").unwrap();
+
+        let files = FileRecord::collect(&[tool_dir.clone(), single.clone()]).unwrap();
+        let paths = files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                tool_dir.join("README"),
+                tool_dir.join("bin").join("a"),
+                single
+            ]
+        );
+        assert_eq!(files[0].size, 6);
+        assert!(files.iter().all(|f| f.state() == FileState::Intact));
+
+        fs::write(tool_dir.join("README"), "modified").unwrap();
+        fs::remove_file(tool_dir.join("bin").join("a")).unwrap();
+        assert_eq!(files[0].state(), FileState::Modified);
+        assert_eq!(files[1].state(), FileState::Missing);
+        assert_eq!(files[2].state(), FileState::Intact);
+    }
 }
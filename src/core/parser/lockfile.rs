@@ -0,0 +1,106 @@
+use anyhow::Result;
+use rim_common::types::{TomlParser, ToolMap};
+use rim_common::utils;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A lockfile that describes the desired state of an installation, such as:
+///
+/// ```toml
+/// [toolkit]
+/// name = "My Rust"
+/// version = "1.0.0"
+///
+/// [rust]
+/// channel = "1.87.0"
+/// components = ["clippy", "rustfmt"]
+///
+/// [tools]
+/// cargo-expand = "1.0.100"
+/// hello = { version = "0.1.0", url = "https://example.com/hello.zip" }
+/// ```
+///
+/// Each entry in `tools` has the same format as the ones in toolkit manifest,
+/// relative `path`s are resolved from the directory of the lockfile.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Lockfile {
+    pub toolkit: Option<ToolkitLock>,
+    /// The toolchain will be left untouched if this is `None`.
+    pub rust: Option<RustLock>,
+    #[serde(default)]
+    pub tools: ToolMap,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ToolkitLock {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub edition: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RustLock {
+    pub channel: String,
+    /// Toolchain components, excluding the profile.
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+impl TomlParser for Lockfile {
+    const FILENAME: &'static str = "rim.lock";
+
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = utils::read_to_string("lockfile", path)?;
+        let mut lockfile = Self::from_str(&raw)?;
+
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        for info in lockfile.tools.values_mut() {
+            if let Some(tool_path) = info.path_mut() {
+                if tool_path.is_relative() {
+                    *tool_path = parent.join(&tool_path);
+                }
+            }
+        }
+        Ok(lockfile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_relative_tool_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(Lockfile::FILENAME);
+        std::fs::write(
+            &path,
+            r#"
+[rust]
+channel = "1.87.0"
+components = ["clippy"]
+
+[tools]
+cargo-expand = "1.0.100"
+hello = { version = "0.1.0", path = "tools/hello.zip" }
+"#,
+        )
+        .unwrap();
+
+        let mut lockfile = Lockfile::load(&path).unwrap();
+        assert!(lockfile.toolkit.is_none());
+        assert_eq!(lockfile.rust.as_ref().unwrap().channel, "1.87.0");
+        assert_eq!(
+            lockfile.tools.get_mut("hello").unwrap().path_mut().unwrap(),
+            &dir.path().join("tools/hello.zip")
+        );
+        assert_eq!(
+            lockfile.tools.get("cargo-expand").unwrap().version(),
+            Some("1.0.100")
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub(crate) mod cargo_config;
 pub(crate) mod cargo_manifest;
+pub(crate) mod crates_listing;
 pub mod dist_manifest;
 pub mod fingerprint;
+pub(crate) mod lockfile;
 pub(crate) mod release_info;
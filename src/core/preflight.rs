@@ -0,0 +1,143 @@
+//! Checks that run before installation or update, so that problems such as
+//! insufficient disk space or a read-only installation directory could be
+//! reported before anything gets changed.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use rim_common::types::ToolkitManifest;
+use rim_common::utils;
+
+use super::components::{Component, ComponentType};
+use super::os;
+
+/// Estimate the disk space (in bytes) required to install `components`,
+/// base on the optional size hints in the toolkit manifest.
+///
+/// Components without size hints are assumed to take no space,
+/// except for this program itself, which will always be copied into the installation.
+pub fn estimated_size(manifest: &ToolkitManifest, components: &[Component]) -> u64 {
+    let self_size = std::env::current_exe()
+        .and_then(|exe| exe.metadata())
+        .map(|meta| meta.len())
+        .unwrap_or_default();
+    let hints = components.iter().filter_map(|comp| match comp.kind {
+        ComponentType::ToolchainProfile => manifest.toolchain.size,
+        ComponentType::ToolchainComponent | ComponentType::ToolchainTarget => {
+            manifest.toolchain.component_sizes.get(&comp.name).copied()
+        }
+        ComponentType::Tool => comp.tool_installer.as_ref().and_then(|info| info.size()),
+    });
+    hints.fold(self_size, u64::saturating_add)
+}
+
+/// Check the write permission of the install directory, as well as `CARGO_HOME`
+/// and `RUSTUP_HOME` in it, then check if there's `required` bytes of free space
+/// on the target filesystem.
+///
+/// Return a list of problems found, which is empty if the installation could proceed.
+/// Nothing will be created during the check, the directories that do not exist yet are
+/// checked via their closest existing parent directory.
+pub fn diagnose(install_dir: &Path, required: u64) -> Vec<String> {
+    let mut problems = vec![];
+    let mut checked = HashSet::new();
+
+    let dirs = [
+        install_dir.to_path_buf(),
+        install_dir.join("cargo"),
+        install_dir.join("rustup"),
+    ];
+    for dir in &dirs {
+        let Some(existing) = closest_existing_dir(dir) else {
+            problems.push(t!("path_not_dir", path = dir.display()).to_string());
+            return problems;
+        };
+        if !checked.insert(existing.clone()) {
+            continue;
+        }
+        if !is_writable(&existing) {
+            problems.push(t!("dir_not_writable", path = existing.display()).to_string());
+        }
+    }
+
+    if let Some(existing) = closest_existing_dir(install_dir) {
+        match os::available_space(&existing) {
+            Ok(available) if available < required => problems.push(
+                t!(
+                    "insufficient_disk_space",
+                    path = existing.display(),
+                    required = utils::human_size(required),
+                    available = utils::human_size(available)
+                )
+                .to_string(),
+            ),
+            Ok(_) => (),
+            // not being able to tell the free space should not block the installation
+            Err(e) => warn!(
+                "unable to check free disk space of '{}': {e}",
+                existing.display()
+            ),
+        }
+    }
+
+    problems
+}
+
+/// Run [`diagnose`] and return an error containing every problem found.
+pub(crate) fn check(install_dir: &Path, required: u64) -> Result<()> {
+    let problems = diagnose(install_dir, required);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let problem_list = problems
+        .iter()
+        .map(|p| format!("\t{p}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    bail!("{}:\n{problem_list}", t!("preflight_check_failed"));
+}
+
+/// Find the closest existing directory of `path`, starting from `path` itself.
+///
+/// Return `None` if the closest existing entry is not a directory.
+fn closest_existing_dir(path: &Path) -> Option<PathBuf> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    existing.is_dir().then(|| existing.to_path_buf())
+}
+
+/// Check the write permission of `dir` by creating a temporary file in it,
+/// which will be removed right away.
+fn is_writable(dir: &Path) -> bool {
+    tempfile::Builder::new()
+        .prefix(".rim-write-test")
+        .tempfile_in(dir)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnose_writable_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let install_dir = root.path().join("not").join("created");
+
+        assert!(diagnose(&install_dir, 0).is_empty());
+        // nothing should be created
+        assert!(!root.path().join("not").exists());
+
+        let problems = diagnose(&install_dir, u64::MAX);
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[test]
+    fn diagnose_file_as_install_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("file");
+        std::fs::write(&file, "").unwrap();
+
+        assert_eq!(diagnose(&file.join("sub"), 0).len(), 1);
+    }
+}
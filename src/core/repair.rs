@@ -0,0 +1,292 @@
+//! Detect the broken parts of an installation, and reinstall them in place
+//! using the toolkit manifest the installation was made from.
+
+use anyhow::Result;
+use rim_common::types::{TomlParser, ToolKind, ToolMap, ToolkitManifest};
+use rim_common::utils::{self, ProgressHandler};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::directories::RimDir;
+use super::doctor::{self, Status};
+use super::history::{Operation, Tracker};
+use super::install::{EnvConfig, InstallConfiguration};
+use super::os::add_to_path;
+use super::parser::cargo_config::CargoConfig;
+use super::parser::crates_listing::CratesListing;
+use super::parser::fingerprint::{FileState, InstallationRecord, ToolRecord};
+use super::{ToolkitManifestExt, CARGO_HOME, RUSTUP_HOME};
+
+/// The broken parts of an installation.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct RepairPlan {
+    /// Whether the toolchain is missing and need to be reinstalled entirely.
+    pub(crate) toolchain: bool,
+    /// The toolchain components that are missing from an existing toolchain.
+    pub(crate) toolchain_components: Vec<String>,
+    /// The tools that are missing or have files modified.
+    pub(crate) tools: Vec<String>,
+    /// The broken tools that are not in the toolkit manifest, which cannot be repaired.
+    pub(crate) unrepairable_tools: Vec<String>,
+    /// Whether the environment configuration, including `PATH`, is broken.
+    pub(crate) env: bool,
+    /// Whether the `config.toml` of cargo is missing.
+    pub(crate) cargo_config: bool,
+}
+
+impl RepairPlan {
+    /// Return `true` if nothing needs to be repaired.
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.toolchain
+            && self.toolchain_components.is_empty()
+            && self.tools.is_empty()
+            && !self.env
+            && !self.cargo_config
+    }
+
+    /// Detect the broken parts of the installation described by `record`,
+    /// `manifest` is the toolkit manifest that the installation was made from.
+    pub(crate) fn detect(record: &InstallationRecord, manifest: &ToolkitManifest) -> Self {
+        let cargo_home = record.install_dir.join("cargo");
+        let rustup_home = record.install_dir.join("rustup");
+        let mut plan = Self::default();
+
+        if let Some((channel, components)) = record.installed_toolchain() {
+            match installed_toolchain_components(&cargo_home, &rustup_home, channel) {
+                Some(installed) => {
+                    plan.toolchain_components = components
+                        .iter()
+                        .filter(|comp| !is_component_installed(comp, &installed))
+                        .cloned()
+                        .collect();
+                }
+                None => plan.toolchain = true,
+            }
+        }
+
+        plan.cargo_config = !cargo_home.join(CargoConfig::FILENAME).is_file();
+        plan.env = doctor::diagnose().checks.iter().any(|diag| {
+            matches!(diag.check, "env-vars" | "path" | "shell-rc") && diag.status != Status::Pass
+        });
+
+        let cargo_tools = CratesListing::load(&cargo_home)
+            .inspect_err(|e| debug!("unable to load the list of installed crates: {e}"))
+            .unwrap_or_default();
+        let mut broken = record
+            .tools
+            .iter()
+            .filter(|(name, rec)| {
+                // crates are patched in cargo config, which need to be written again
+                (plan.cargo_config && rec.tool_kind() == ToolKind::Crate)
+                    || is_tool_broken(name, rec, &cargo_home, &cargo_tools)
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        broken.sort();
+
+        let in_manifest = manifest
+            .current_target_tools()
+            .map(|(name, _)| name)
+            .collect::<HashSet<_>>();
+        (plan.tools, plan.unrepairable_tools) = broken
+            .into_iter()
+            .partition(|name| in_manifest.contains(name.as_str()));
+
+        plan
+    }
+
+    /// Reinstall the broken parts in place, healthy components will not be touched.
+    pub(crate) async fn apply<T: ProgressHandler + Clone + 'static>(
+        &self,
+        record: &InstallationRecord,
+        manifest: ToolkitManifest,
+        handler: T,
+        insecure: bool,
+    ) -> Result<()> {
+        let tracker = Tracker::start(Operation::Repair);
+        let result = self.apply_(record, manifest, handler, insecure).await;
+        tracker.finish(&result);
+        result
+    }
+
+    async fn apply_<T: ProgressHandler + Clone + 'static>(
+        &self,
+        record: &InstallationRecord,
+        mut manifest: ToolkitManifest,
+        handler: T,
+        insecure: bool,
+    ) -> Result<()> {
+        // the toolchain should be repaired to the recorded one, which might be
+        // different from the manifest's after switching channels with `apply`.
+        if let Some((channel, _)) = record.installed_toolchain() {
+            manifest.toolchain.channel = channel.to_string();
+        }
+        let mut config =
+            InstallConfiguration::new(&record.install_dir, &manifest, handler)?.insecure(insecure);
+        config.toolchain_is_installed = record.rust.is_some() && !self.toolchain;
+
+        if self.env {
+            info!("{}", t!("repairing", part = t!("env_config")));
+            config.config_env_vars()?;
+            add_to_path(&config, config.cargo_bin())?;
+        }
+        if self.cargo_config {
+            info!("{}", t!("repairing", part = t!("cargo_config")));
+            config.config_cargo()?;
+        }
+
+        let components = record.installed_toolchain_components();
+        if self.toolchain {
+            info!("{}", t!("repairing", part = t!("toolchain")));
+            config.install_rust(&components).await?;
+        } else if !self.toolchain_components.is_empty() {
+            info!(
+                "{}",
+                t!("repairing", part = self.toolchain_components.join(", "))
+            );
+            let missing = components
+                .into_iter()
+                .filter(|comp| self.toolchain_components.contains(&comp.name))
+                .collect::<Vec<_>>();
+            config.install_toolchain_components(&missing).await?;
+        }
+
+        if !self.tools.is_empty() {
+            info!("{}", t!("repairing", part = self.tools.join(", ")));
+            let tools = manifest
+                .current_target_tools()
+                .filter(|(name, _)| self.tools.iter().any(|tool| tool == name))
+                .map(|(name, info)| (name.to_string(), info.clone()))
+                .collect::<ToolMap>();
+            config.install_tools(&tools).await?;
+            config.install_tools_late(&tools).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the names of installed components of a toolchain,
+/// return `None` if `rustup` or the toolchain itself is missing.
+fn installed_toolchain_components(
+    cargo_home: &Path,
+    rustup_home: &Path,
+    channel: &str,
+) -> Option<Vec<String>> {
+    let rustup = cargo_home.join("bin").join(exe!("rustup"));
+    if !rustup.is_file() {
+        return None;
+    }
+    let cmd = cmd!(
+        [CARGO_HOME = cargo_home, RUSTUP_HOME = rustup_home]
+        rustup, "component", "list", "--installed", "--toolchain", channel
+    );
+    let output = utils::command_output(cmd)
+        .inspect_err(|e| debug!("unable to list installed components: {e}"))
+        .ok()?;
+    Some(output.lines().map(|ln| ln.trim().to_string()).collect())
+}
+
+/// Check if `component` is in the `installed` list of `rustup component list --installed`,
+/// which might have target triple suffixes, such as `rust-std-x86_64-unknown-linux-gnu`.
+fn is_component_installed(component: &str, installed: &[String]) -> bool {
+    let with_target = format!("{component}-{}", env!("TARGET"));
+    installed
+        .iter()
+        .any(|name| name == component || name == &with_target)
+}
+
+/// Check if a tool is missing, or has any of its recorded files missing or modified.
+///
+/// Tools installed before files were recorded are checked by their paths, or by the
+/// binaries listed in `cargo_tools` if they were installed by `cargo install`.
+fn is_tool_broken(
+    name: &str,
+    record: &ToolRecord,
+    cargo_home: &Path,
+    cargo_tools: &CratesListing,
+) -> bool {
+    if !record.files.is_empty() {
+        return record
+            .files
+            .iter()
+            .any(|file| file.state() != FileState::Intact);
+    }
+    if record.tool_kind() == ToolKind::CargoTool {
+        let cargo_bin = cargo_home.join("bin");
+        // comparing with itself, so only the binaries of the crate named `name` are returned
+        let bins = cargo_tools.installed_bins(name, cargo_tools);
+        return bins.is_empty() || bins.iter().any(|bin| !cargo_bin.join(bin).is_file());
+    }
+    record.paths.iter().any(|path| !path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::parser::fingerprint::FileRecord;
+
+    #[test]
+    fn match_installed_components() {
+        let installed = vec![
+            "cargo".to_string(),
+            format!("rust-std-{}", env!("TARGET")),
+            "rust-src".to_string(),
+        ];
+        assert!(is_component_installed("cargo", &installed));
+        assert!(is_component_installed("rust-std", &installed));
+        assert!(is_component_installed("rust-src", &installed));
+        assert!(!is_component_installed("clippy", &installed));
+        assert!(!is_component_installed("rust", &installed));
+    }
+
+    #[test]
+    fn detect_broken_tools() {
+        let cargo_home = tempfile::tempdir().unwrap();
+        let cargo_bin = cargo_home.path().join("bin");
+        std::fs::create_dir_all(&cargo_bin).unwrap();
+        std::fs::write(cargo_bin.join("foo"), "foo").unwrap();
+        std::fs::write(
+            cargo_home.path().join(".crates.toml"),
+            r#"
+[v1]
+"foo 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)" = ["foo"]
+"bar 0.2.0 (registry+https://github.com/rust-lang/crates.io-index)" = ["bar", "bar-cli"]
+"#,
+        )
+        .unwrap();
+
+        let cargo_tools = CratesListing::load(cargo_home.path()).unwrap();
+        let is_broken = |name: &str, rec: &ToolRecord| {
+            is_tool_broken(name, rec, cargo_home.path(), &cargo_tools)
+        };
+
+        assert!(!is_broken("foo", &ToolRecord::cargo_tool()));
+        // binaries are missing
+        assert!(is_broken("bar", &ToolRecord::cargo_tool()));
+        // not installed at all
+        assert!(is_broken("baz", &ToolRecord::cargo_tool()));
+
+        // recorded files are checked first, even if the crate is still listed
+        let exe = cargo_bin.join("foo");
+        let cargo_tool = ToolRecord::cargo_tool().with_files(FileRecord::collect(&[&exe]).unwrap());
+        assert!(!is_broken("foo", &cargo_tool));
+        std::fs::write(&exe, "modified").unwrap();
+        assert!(is_broken("foo", &cargo_tool));
+        std::fs::write(&exe, "foo").unwrap();
+
+        let exe = cargo_bin.join("foo");
+        let files = FileRecord::collect(&[&exe]).unwrap();
+        let recorded = ToolRecord::new(ToolKind::Executables)
+            .with_paths(vec![exe.clone()])
+            .with_files(files);
+        assert!(!is_broken("foo", &recorded));
+        std::fs::write(&exe, "modified").unwrap();
+        assert!(is_broken("foo", &recorded));
+
+        let unrecorded = ToolRecord::new(ToolKind::DirWithBin)
+            .with_paths(vec![cargo_home.path().join("tools").join("qux")]);
+        assert!(is_broken("qux", &unrecorded));
+    }
+}
@@ -1,8 +1,8 @@
 use std::path::Path;
 use std::path::PathBuf;
 
-use anyhow::{Context, Result};
-use rim_common::types::ToolkitManifest;
+use anyhow::{bail, Context, Result};
+use rim_common::types::{ExtraToolchain, ToolkitManifest};
 use rim_common::utils;
 use rim_common::utils::HiddenProgress;
 use rim_common::utils::ProgressHandler;
@@ -73,17 +73,18 @@ impl ToolchainInstaller {
         self.ensure_rustup_dist_server_env(config.manifest, first_install)?;
 
         let rustup = &ensure_rustup(config, self.insecure).await?;
-        let components_arg = components
-            .iter()
-            .filter_map(|c| (!c.is_profile).then_some(c.name.as_str()))
-            .collect::<Vec<_>>()
-            .join(",");
+        let (targets, components) = split_targets(components);
+        let components_arg = components.join(",");
+        let targets_arg = targets.join(",");
 
         let version = &config.manifest.toolchain.channel;
         let mut cmd = cmd!(rustup, "toolchain", "install", version, "--no-self-update",);
         if !components_arg.is_empty() {
             cmd.args(["-c", &components_arg]);
         }
+        if !targets_arg.is_empty() {
+            cmd.args(["-t", &targets_arg]);
+        }
         if let Some(profile) = config.manifest.toolchain.profile() {
             cmd.args(["--profile", profile]);
         }
@@ -147,24 +148,26 @@ impl ToolchainInstaller {
             .any(|line| line.starts_with(version))
         {
             // if toolchain is installed, add the component directly
-            let mut cmd = cmd!(rustup, "component", "add");
-            let comp_args = components
-                .iter()
-                .filter_map(|c| (!c.is_profile).then_some(&c.name));
-            info!(
-                "{}",
-                t!(
-                    "install_toolchain_components",
-                    list = comp_args
-                        .clone()
-                        .map(|s| s.as_str())
-                        .collect::<Vec<_>>()
-                        .join(",")
-                )
-            );
-
-            cmd.args(comp_args);
-            utils::execute(cmd)
+            let (targets, components) = split_targets(components);
+            if !components.is_empty() {
+                info!(
+                    "{}",
+                    t!("install_toolchain_components", list = components.join(","))
+                );
+                let mut cmd = cmd!(rustup, "component", "add");
+                cmd.args(components);
+                utils::execute(cmd)?;
+            }
+            if !targets.is_empty() {
+                info!(
+                    "{}",
+                    t!("install_toolchain_targets", list = targets.join(","))
+                );
+                let mut cmd = cmd!(rustup, "target", "add");
+                cmd.args(targets);
+                utils::execute(cmd)?;
+            }
+            Ok(())
         } else {
             // otherwise install the toolchain with the components
             self.install_toolchain_with_components(config, components, false)
@@ -172,9 +175,84 @@ impl ToolchainInstaller {
         }
     }
 
-    pub(crate) fn remove_components<T>(
+    /// Install an additional toolchain next to the default one, and link it to its
+    /// alias if there is one.
+    ///
+    /// Additional toolchains are always installed from the online dist server,
+    /// because the offline dist server only contains the default toolchain.
+    pub(crate) async fn install_extra_toolchain<T: ProgressHandler + Clone + 'static>(
         &self,
-        config: &UninstallConfiguration<T>,
+        config: &InstallConfiguration<'_, T>,
+        toolchain: &ExtraToolchain,
+    ) -> Result<()> {
+        if let Some(alias) = &toolchain.alias {
+            ensure_valid_alias(alias, &toolchains_dir(config))?;
+        }
+        self.ensure_rustup_dist_server_env(config.manifest, false)?;
+        let rustup = &ensure_rustup(config, self.insecure).await?;
+
+        let channel = &toolchain.channel;
+        let mut cmd = cmd!(rustup, "toolchain", "install", channel, "--no-self-update");
+        if !toolchain.components.is_empty() {
+            cmd.args(["-c", &toolchain.components.join(",")]);
+        }
+        if let Some(profile) = &toolchain.profile {
+            cmd.args(["--profile", profile]);
+        }
+        utils::execute(cmd)?;
+
+        if let Some(alias) = &toolchain.alias {
+            // remove the previous link, which might point to an older toolchain
+            remove_toolchain_link(&toolchains_dir(config).join(alias))?;
+            let toolchain_dir =
+                toolchains_dir(config).join(format!("{channel}-{}", env!("TARGET")));
+            run!(rustup, "toolchain", "link", alias, toolchain_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Uninstall an additional toolchain of `channel`, along with the `alias` linked to it.
+    pub(crate) fn uninstall_extra_toolchain<T: RimDir>(
+        &self,
+        config: T,
+        channel: &str,
+        alias: Option<&str>,
+    ) -> Result<()> {
+        if let Some(alias) = alias {
+            remove_toolchain_link(&toolchains_dir(&config).join(alias))?;
+        }
+        self.uninstall_toolchain(config, channel)
+    }
+
+    /// Return `true` if the toolchain of `channel` is installed.
+    pub(crate) fn is_toolchain_installed<T: RimDir>(&self, config: T, channel: &str) -> bool {
+        toolchains_dir(&config)
+            .join(format!("{channel}-{}", env!("TARGET")))
+            .is_dir()
+    }
+
+    /// Set the toolchain of `channel` as the default one, such as going back to the previous
+    /// channel after a failed update.
+    pub(crate) fn set_default_toolchain<T: RimDir>(&self, config: T, channel: &str) -> Result<()> {
+        let rustup_bin = config.cargo_bin().join(RUSTUP);
+        run!(rustup_bin, "-q", "default", channel)?;
+        Ok(())
+    }
+
+    /// Uninstall the toolchain of `channel`, such as the one replaced by another channel.
+    pub(crate) fn uninstall_toolchain<T: RimDir>(&self, config: T, channel: &str) -> Result<()> {
+        let rustup_bin = config.cargo_bin().join(RUSTUP);
+        if !rustup_bin.is_file() {
+            // the toolchain cannot exist without rustup
+            return Ok(());
+        }
+        run!(rustup_bin, "toolchain", "uninstall", channel)?;
+        Ok(())
+    }
+
+    pub(crate) fn remove_components<T: RimDir>(
+        &self,
+        config: T,
         components: &[ToolchainComponent],
     ) -> Result<()> {
         if components.is_empty() || components.iter().all(|c| c.is_profile) {
@@ -188,25 +266,28 @@ impl ToolchainInstaller {
             return Ok(());
         }
 
-        let comp_args = components
-            .iter()
-            .filter_map(|c| (!c.is_profile).then_some(&c.name));
-
-        info!(
-            "{}",
-            t!(
-                "uninstall_toolchain_components",
-                list = comp_args
-                    .clone()
-                    .map(|s| s.as_str())
-                    .collect::<Vec<_>>()
-                    .join(",")
-            )
-        );
-
-        let mut cmd = cmd!(rustup_bin, "component", "remove");
-        cmd.args(comp_args);
-        utils::execute(cmd)?;
+        let (targets, components) = split_targets(components);
+        if !components.is_empty() {
+            info!(
+                "{}",
+                t!(
+                    "uninstall_toolchain_components",
+                    list = components.join(",")
+                )
+            );
+            let mut cmd = cmd!(&rustup_bin, "component", "remove");
+            cmd.args(components);
+            utils::execute(cmd)?;
+        }
+        if !targets.is_empty() {
+            info!(
+                "{}",
+                t!("uninstall_toolchain_targets", list = targets.join(","))
+            );
+            let mut cmd = cmd!(&rustup_bin, "target", "remove");
+            cmd.args(targets);
+            utils::execute(cmd)?;
+        }
         Ok(())
     }
 
@@ -317,6 +398,63 @@ impl ToolchainInstaller {
     }
 }
 
+/// Split the names of toolchain `components` into compilation targets and regular
+/// components, toolchain profiles are excluded from both.
+fn split_targets(components: &[ToolchainComponent]) -> (Vec<&str>, Vec<&str>) {
+    let mut targets = vec![];
+    let mut others = vec![];
+    for comp in components.iter().filter(|c| !c.is_profile) {
+        if comp.is_target {
+            targets.push(comp.name.as_str());
+        } else {
+            others.push(comp.name.as_str());
+        }
+    }
+    (targets, others)
+}
+
+/// The directory where `rustup` stores its toolchains, a.k.a `<RUSTUP_HOME>/toolchains`.
+fn toolchains_dir<T: RimDir>(config: &T) -> PathBuf {
+    config.rustup_home().join("toolchains")
+}
+
+/// Make sure the `alias` of an additional toolchain is a custom toolchain name,
+/// which does not look like a channel (such as `nightly`, `1.80.0` or
+/// `stable-x86_64-unknown-linux-gnu`), nor is a toolchain installed under `toolchains_dir`.
+fn ensure_valid_alias(alias: &str, toolchains_dir: &Path) -> Result<()> {
+    let channel = alias.split('-').next().unwrap_or_default();
+    let is_version = channel
+        .split('.')
+        .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    let is_path = alias.is_empty() || alias.starts_with('.') || alias.contains(['/', '\\']);
+    let path = toolchains_dir.join(alias);
+    let is_installed = path.exists() && !is_symlink(&path);
+
+    if matches!(channel, "stable" | "beta" | "nightly") || is_version || is_path || is_installed {
+        bail!(t!("invalid_toolchain_alias", alias = alias));
+    }
+    Ok(())
+}
+
+fn is_symlink(path: &Path) -> bool {
+    path.symlink_metadata()
+        .is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+/// Remove a custom toolchain linked by `rustup toolchain link`,
+/// nothing will be removed if `path` is not a link.
+fn remove_toolchain_link(path: &Path) -> Result<()> {
+    if !is_symlink(path) {
+        return Ok(());
+    }
+    // links to directories are removed as directories on Windows
+    #[cfg(windows)]
+    let res = std::fs::remove_dir(path);
+    #[cfg(not(windows))]
+    let res = std::fs::remove_file(path);
+    res.with_context(|| format!("unable to remove toolchain link '{}'", path.display()))
+}
+
 async fn ensure_rustup<T: ProgressHandler + Clone + 'static>(
     config: &InstallConfiguration<'_, T>,
     insecure: bool,
@@ -342,6 +480,11 @@ async fn ensure_rustup<T: ProgressHandler + Clone + 'static>(
             (rustup_init, Some(temp_dir))
         };
 
+    if let Some(expected) = config.manifest.toolchain.rustup_init_sha256(env!("TARGET")) {
+        info!("{}", t!("verifying_checksum", file = RUSTUP_INIT));
+        utils::verify_sha256(&rustup_init, expected)?;
+    }
+
     install_rustup(&rustup_init)?;
     // We don't need the rustup-init anymore, drop the whole temp dir containing it.
     drop(maybe_temp_dir);
@@ -370,6 +513,7 @@ async fn download_rustup_init<T: ProgressHandler + Clone + 'static>(
     download_opt
         .insecure(insecure)
         .with_proxy(config.manifest.proxy_config().cloned())
+        .with_retry(config.download_retry())
         .download(&download_url, dest)
         .await
         .context("Failed to download rustup.")
@@ -398,3 +542,54 @@ fn install_rustup(rustup_init: &PathBuf) -> Result<()> {
     utils::execute(cmd)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_channel_like_aliases() {
+        let toolchains = tempfile::tempdir().unwrap();
+        std::fs::create_dir(toolchains.path().join("1.80.0-x86_64-unknown-linux-gnu")).unwrap();
+        std::fs::create_dir(toolchains.path().join("my-toolchain")).unwrap();
+
+        for alias in [
+            "stable",
+            "nightly",
+            "nightly-2024-10-01",
+            "stable-x86_64-unknown-linux-gnu",
+            "1.80.0",
+            "1.80",
+            "../escape",
+            "",
+            // installed toolchains that are not links
+            "my-toolchain",
+        ] {
+            assert!(
+                ensure_valid_alias(alias, toolchains.path()).is_err(),
+                "{alias}"
+            );
+        }
+        for alias in ["fuzz", "miri-nightly", "stable2"] {
+            assert!(ensure_valid_alias(alias, toolchains.path()).is_ok());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_remove_toolchain_links() {
+        let toolchains = tempfile::tempdir().unwrap();
+        let real = toolchains.path().join("nightly-x86_64-unknown-linux-gnu");
+        std::fs::create_dir(&real).unwrap();
+        let link = toolchains.path().join("fuzz");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        remove_toolchain_link(&real).unwrap();
+        assert!(real.is_dir());
+        // linked toolchains are allowed to be replaced
+        assert!(ensure_valid_alias("fuzz", toolchains.path()).is_ok());
+        remove_toolchain_link(&link).unwrap();
+        assert!(!link.exists());
+        assert!(real.is_dir());
+    }
+}
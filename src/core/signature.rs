@@ -0,0 +1,87 @@
+//! Verification of the manifests that are downloaded from server.
+//!
+//! Each manifest (`toolset-manifest.toml`, `distribution-manifest.toml`, `release.toml`)
+//! is expected to have a detached signature file next to it, with an additional `.sig`
+//! extension, such as `toolset-manifest.toml.sig`, which contains a base64 encoded
+//! ed25519 signature of the manifest file.
+
+use super::GlobalOpts;
+use anyhow::{bail, Context, Result};
+use rim_common::{
+    build_config,
+    utils::{self, HiddenProgress},
+};
+use url::Url;
+
+/// Verify the `content` of a manifest downloaded from `url`,
+/// by fetching its detached signature located at `<url>.sig`.
+///
+/// Unsigned or badly signed manifests will be rejected, as well as every manifest when
+/// no trusted key was configured at build time, unless `--allow-unsigned` was specified.
+pub(crate) async fn verify_manifest(
+    name: &str,
+    url: &Url,
+    content: &[u8],
+    insecure: bool,
+) -> Result<()> {
+    let trusted_keys = &build_config().trusted_keys;
+    let sig_url = signature_url(url);
+    debug!("verifying {name} using signature from '{sig_url}'");
+    let verified: Result<()> = async {
+        if trusted_keys.is_empty() {
+            bail!(t!("no_trusted_key", name = name));
+        }
+        let signature =
+            utils::DownloadOpt::new(format!("{name} signature"), Box::new(HiddenProgress))
+                .insecure(insecure)
+                .read(&sig_url)
+                .await
+                .with_context(|| t!("manifest_unsigned", name = name, url = url))?;
+        utils::verify_signature(content, &signature, trusted_keys)
+            .with_context(|| t!("manifest_bad_signature", name = name, url = url))
+    }
+    .await;
+
+    match verified {
+        Ok(()) => Ok(()),
+        Err(e) if GlobalOpts::get().allow_unsigned() => {
+            warn!(
+                "{}",
+                t!("allow_unsigned_manifest", reason = format!("{e:#}"))
+            );
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Get the URL of the detached signature file of a given URL, i.e. `<url>.sig`.
+fn signature_url(url: &Url) -> Url {
+    let mut sig_url = url.clone();
+    sig_url.set_path(&format!("{}.sig", url.path()));
+    sig_url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detached_signature_url() {
+        let url: Url = "https://example.com/dist/distribution-manifest.toml"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            signature_url(&url).as_str(),
+            "https://example.com/dist/distribution-manifest.toml.sig"
+        );
+
+        let url: Url = "https://example.com/manager/release.toml?version=1"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            signature_url(&url).as_str(),
+            "https://example.com/manager/release.toml.sig?version=1"
+        );
+    }
+}
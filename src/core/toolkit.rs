@@ -1,8 +1,8 @@
 use crate::components;
 use crate::core::parser::dist_manifest::DistManifest;
 use crate::fingerprint::InstallationRecord;
-use anyhow::Result;
-use rim_common::types::ToolkitManifest;
+use anyhow::{bail, Result};
+use rim_common::types::{Configuration, ToolkitManifest};
 use rim_common::utils::HiddenProgress;
 use rim_common::{types::TomlParser, utils};
 use semver::Version;
@@ -10,7 +10,7 @@ use serde::Serialize;
 use tokio::sync::{Mutex, OnceCell};
 
 use super::parser::dist_manifest::DistPackage;
-use super::ToolkitManifestExt;
+use super::{signature, ToolkitManifestExt};
 
 /// A cached installed [`Toolkit`] struct to prevent the program doing
 /// excessive IO operations as in [`installed`](Toolkit::installed).
@@ -135,6 +135,8 @@ pub async fn toolkits_from_server(insecure: bool) -> Result<Vec<Toolkit>> {
         .download(&dist_m_url, dist_m_file.path())
         .await?;
     debug!("distribution manifest file successfully downloaded!");
+    let content = std::fs::read(dist_m_file.path())?;
+    signature::verify_manifest("distribution manifest", &dist_m_url, &content, insecure).await?;
 
     // load dist "pacakges" then convert them into `toolkit`s
     let packages = DistManifest::load(dist_m_file.path())?.packages;
@@ -172,10 +174,21 @@ pub async fn installable_toolkits(reload_cache: bool, insecure: bool) -> Result<
 
 /// Get available toolkits from server, then return the latest one if it has
 /// not been installed yet.
+///
+/// No toolkit will be returned if user has pinned the installation to a specific
+/// toolkit version, check [`UpdateConfig::pinned_toolkit`](rim_common::types::UpdateConfig::pinned_toolkit).
 pub async fn latest_installable_toolkit(
     installed: &Toolkit,
     insecure: bool,
 ) -> Result<Option<Toolkit>> {
+    if let Some(pinned) = Configuration::load_from_config_dir().update.pinned_toolkit {
+        info!(
+            "{}",
+            t!("toolkit_pinned", name = installed.name, version = pinned)
+        );
+        return Ok(None);
+    }
+
     let Some(maybe_latest) = toolkits_from_server(insecure)
         .await?
         .into_iter()
@@ -205,6 +218,34 @@ pub async fn latest_installable_toolkit(
     }
 }
 
+/// Get the toolkit of a specific `version` from server, which could be older than
+/// the `installed` one.
+pub async fn toolkit_of_version(
+    installed: &Toolkit,
+    version: &str,
+    insecure: bool,
+) -> Result<Toolkit> {
+    let version = trim_version(version);
+    let Some(toolkit) = toolkits_from_server(insecure)
+        .await?
+        .into_iter()
+        .find(|tk| tk.edition == installed.edition && trim_version(&tk.version) == version)
+    else {
+        bail!(t!(
+            "toolkit_version_not_found",
+            name = installed.name,
+            version = version
+        ));
+    };
+    Ok(toolkit)
+}
+
+/// Check if two toolkit version strings are referring to the same version,
+/// ignoring any prefixes such as "stable ".
+pub fn is_same_version(a: &str, b: &str) -> bool {
+    trim_version(a) == trim_version(b)
+}
+
 // For some reason, the version might contains prefixes such as "stable 1.80.1",
 // therefore we need to trim them so that `semver` can be used to parse the actual
 // version string.
@@ -1,22 +1,26 @@
 //! `ToolsetManifest` contains information about each dist package,
 //! such as its name, version, and what's included etc.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use rim_common::types::{TomlParser, ToolInfo, ToolkitManifest};
 use rim_common::utils::{self, HiddenProgress};
 use serde::de::DeserializeOwned;
 use tokio::sync::Mutex;
 use url::Url;
 
-use crate::components::{Component, ComponentType};
-use crate::core::custom_instructions;
+use crate::components::{self, Component, ComponentType};
+use crate::core::dependency_handler::DependencyHandler;
+use crate::core::{custom_instructions, signature};
 
 use super::AppInfo;
 
+/// The name that tools use to require the Rust toolchain, such as `requires = ["rust"]`.
+pub(crate) const RUST_TOOLCHAIN_DEPENDENCY: &str = "rust";
+
 /// Extension trait for [`ToolkitManifest`],
 /// offering extra functionalities for installation/uninstallation.
 pub trait ToolkitManifestExt
@@ -29,7 +33,15 @@ where
     /// Only use this during **manager** mode.
     fn load_from_install_dir() -> Result<Self> {
         let root = AppInfo::get_installed_dir();
-        Self::load(root.join(Self::FILENAME))
+        Self::load_validated(root.join(Self::FILENAME))
+    }
+
+    /// Load toolset manifest from `path`, then make sure the dependencies of its tools
+    /// are valid, check [`validate_dependencies`](ToolkitManifestExt::validate_dependencies).
+    fn load_validated<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let manifest = Self::load(path)?;
+        manifest.validate_dependencies()?;
+        Ok(manifest)
     }
 
     /// Get the tools that are only available in current target.
@@ -93,13 +105,20 @@ where
     ) -> Result<()>
     where
         F: Fn(String, Option<&str>) -> Result<String>;
+
+    /// Check the `requires`, `conflicts` and `obsoletes` of tools in current target,
+    /// return an error listing every problem found, including:
+    /// - Dependency cycles, with the full path of the cycle.
+    /// - Required tools that are not provided by this manifest.
+    /// - Tools that require something they also conflict with or obsolete.
+    fn validate_dependencies(&self) -> Result<()>;
 }
 
 impl ToolkitManifestExt for ToolkitManifest {
     fn rustup_bin(&self) -> Result<Option<PathBuf>> {
         let cur_target = env!("TARGET");
         let par_dir = self.package_root()?;
-        let rel_path = self.toolchain.rustup.get(cur_target);
+        let rel_path = self.toolchain.rustup.get(cur_target).and_then(|r| r.path());
 
         Ok(rel_path.map(|p| par_dir.join(p)))
     }
@@ -162,6 +181,24 @@ impl ToolkitManifestExt for ToolkitManifest {
             );
         }
 
+        for (target, is_optional) in self.toolchain_targets() {
+            let requires = self
+                .toolchain
+                .target_requires
+                .get(target)
+                .cloned()
+                .unwrap_or_default();
+            components.push(
+                Component::new(target)
+                    .with_description(self.get_tool_description(target))
+                    .with_category(tc_group)
+                    .optional(is_optional)
+                    .with_requires(requires)
+                    .with_type(ComponentType::ToolchainTarget)
+                    .with_version(Some(tc_channel)),
+            );
+        }
+
         for (tool_name, tool_info) in self.current_target_tools() {
             // filter out GUI tools on non-GUI environment
             if tool_info.is_gui_only() && !utils::has_desktop_environment() {
@@ -191,6 +228,7 @@ impl ToolkitManifestExt for ToolkitManifest {
                     .with_type(ComponentType::Tool),
             );
         }
+        components::resolve_required_by(&mut components);
 
         Ok(components)
     }
@@ -266,6 +304,67 @@ impl ToolkitManifestExt for ToolkitManifest {
         }
         Ok(())
     }
+
+    fn validate_dependencies(&self) -> Result<()> {
+        let tools = self.current_target_tools().collect::<Vec<_>>();
+        // tools could also require toolchain components
+        let known: HashSet<&str> = tools
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(
+                self.toolchain_components()
+                    .into_iter()
+                    .map(|(name, _)| name),
+            )
+            .chain(self.toolchain_targets().into_iter().map(|(name, _)| name))
+            .chain([RUST_TOOLCHAIN_DEPENDENCY])
+            .collect();
+
+        let mut problems = vec![];
+        if let Some(cycle) = tools.find_cycle() {
+            problems.push(t!("dependency_cycle", path = cycle.join(" -> ")).to_string());
+        }
+        for (name, info) in &tools {
+            for dep in info.dependencies() {
+                if !known.contains(dep.as_str()) {
+                    problems
+                        .push(t!("missing_dependency", name = name, dependency = dep).to_string());
+                    continue;
+                }
+                let dep_conflicts = tools
+                    .iter()
+                    .find(|(n, _)| *n == dep.as_str())
+                    .is_some_and(|(_, dep_info)| dep_info.conflicts().iter().any(|c| c == name));
+                if dep_conflicts || info.conflicts().contains(dep) || info.obsoletes().contains(dep)
+                {
+                    problems.push(
+                        t!("require_and_conflict", name = name, dependency = dep).to_string(),
+                    );
+                }
+            }
+        }
+
+        // a target could require tools as well, such as a linker
+        for (target, requires) in &self.toolchain.target_requires {
+            for dep in requires {
+                if !known.contains(dep.as_str()) {
+                    problems.push(
+                        t!("missing_dependency", name = target, dependency = dep).to_string(),
+                    );
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let problem_list = problems
+            .iter()
+            .map(|p| format!("\t{p}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        bail!("{}:\n{problem_list}", t!("invalid_tool_dependencies"));
+    }
 }
 
 /// Get the content of baked-in toolset manifest as `str`.
@@ -314,7 +413,7 @@ pub async fn get_toolkit_manifest(url: Option<Url>, insecure: bool) -> Result<To
     // ========== We don't have it yet, so, load the manifest and cache it ============
     let manifest = if let Some(url) = &url {
         if let Ok(path) = url.to_file_path() {
-            ToolkitManifest::load(path)?
+            ToolkitManifest::load_validated(path)?
         } else {
             debug!("downloading toolset manifest from {url}");
             let temp = utils::make_temp_file("toolset-manifest-", None)?;
@@ -324,17 +423,21 @@ pub async fn get_toolkit_manifest(url: Option<Url>, insecure: bool) -> Result<To
                 .insecure(insecure)
                 .download(url, temp.path())
                 .await?;
-            ToolkitManifest::load(temp.path())?
+            let content = std::fs::read(temp.path())?;
+            signature::verify_manifest("toolset manifest", url, &content, insecure).await?;
+            ToolkitManifest::load_validated(temp.path())?
         }
     } else {
         debug!("loading built-in toolset manifest");
         cfg_if::cfg_if! {
             if #[cfg(feature = "no-web")] {
-                ToolkitManifest::from_str(baked_in_manifest_raw(true))?.offline(true)
+                let manifest = ToolkitManifest::from_str(baked_in_manifest_raw(true))?.offline(true);
             } else {
-                ToolkitManifest::from_str(baked_in_manifest_raw(false))?
+                let manifest = ToolkitManifest::from_str(baked_in_manifest_raw(false))?;
             }
         }
+        manifest.validate_dependencies()?;
+        manifest
     };
     debug!("caching toolset manifest in memory");
     guard.insert(url, manifest.clone());
@@ -517,4 +620,138 @@ a = "0.1.0"
         assert_eq!(tools.next().unwrap().0, "a");
         assert_eq!(tools.next(), None);
     }
+
+    #[test]
+    fn validate_tool_dependencies() {
+        let valid = r#"
+[toolchain]
+channel = "1.0.0"
+components = ["rustfmt"]
+
+[tools.target.all]
+a = { version = "0.1.0", requires = ["b", "rustfmt"], obsoletes = ["old-a"] }
+b = { version = "0.1.0", conflicts = ["c"] }
+"#;
+        let manifest = ToolkitManifest::from_str(valid).unwrap();
+        assert!(manifest.validate_dependencies().is_ok());
+
+        let invalid = r#"
+[toolchain]
+channel = "1.0.0"
+
+[tools.target.all]
+a = { version = "0.1.0", requires = ["b"] }
+b = { version = "0.1.0", requires = ["c"] }
+c = { version = "0.1.0", requires = ["a"] }
+d = { version = "0.1.0", requires = ["missing"] }
+e = { version = "0.1.0", requires = ["f"], conflicts = ["f"] }
+f = { version = "0.1.0", conflicts = ["g"] }
+g = { version = "0.1.0", requires = ["f"] }
+"#;
+        let manifest = ToolkitManifest::from_str(invalid).unwrap();
+        let err = manifest.validate_dependencies().unwrap_err().to_string();
+        assert!(err.contains("a -> b -> c -> a"));
+        assert!(err.contains("'d'") && err.contains("'missing'"));
+        assert!(err.contains("'e'"));
+        assert!(err.contains("'g'"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(ToolkitManifest::FILENAME);
+        std::fs::write(&path, invalid).unwrap();
+        assert!(ToolkitManifest::load(&path).is_ok());
+        let err = ToolkitManifest::load_validated(&path)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a -> b -> c -> a"));
+    }
+
+    #[test]
+    fn components_with_required_by() {
+        let input = r#"
+[toolchain]
+channel = "1.0.0"
+components = ["rustfmt"]
+
+[tools.target.all]
+a = { version = "0.1.0", requires = ["b"] }
+b = { version = "0.1.0", requires = ["rustfmt"] }
+c = "0.1.0"
+"#;
+        let manifest = ToolkitManifest::from_str(input).unwrap();
+        let components = manifest.current_target_components(false).unwrap();
+        let required_by = |name: &str| {
+            components
+                .iter()
+                .find(|c| c.name == name)
+                .unwrap()
+                .required_by
+                .clone()
+        };
+
+        assert_eq!(required_by("rustfmt"), ["a", "b"]);
+        assert_eq!(required_by("b"), ["a"]);
+        assert!(required_by("a").is_empty());
+        assert!(required_by("c").is_empty());
+    }
+
+    #[test]
+    fn components_with_targets() {
+        let input = r#"
+[toolchain]
+channel = "1.0.0"
+targets = ["wasm32-unknown-unknown"]
+optional-targets = ["aarch64-unknown-linux-gnu"]
+
+[toolchain.target-requires]
+aarch64-unknown-linux-gnu = ["linker"]
+
+[tools.target.all]
+linker = "0.1.0"
+"#;
+        let manifest = ToolkitManifest::from_str(input).unwrap();
+        manifest.validate_dependencies().unwrap();
+
+        let components = manifest.current_target_components(false).unwrap();
+        let find = |name: &str| components.iter().find(|c| c.name == name).unwrap();
+
+        let wasm = find("wasm32-unknown-unknown");
+        assert_eq!(wasm.kind, ComponentType::ToolchainTarget);
+        assert!(!wasm.optional);
+        assert_eq!(wasm.version.as_deref(), Some("1.0.0"));
+        assert!(wasm.dependencies().is_empty());
+
+        let aarch64 = find("aarch64-unknown-linux-gnu");
+        assert!(aarch64.optional);
+        assert!(aarch64.tool_installer.is_none());
+        assert_eq!(aarch64.dependencies(), ["linker"]);
+        assert_eq!(find("linker").required_by, ["aarch64-unknown-linux-gnu"]);
+
+        let missing = r#"
+[toolchain]
+channel = "1.0.0"
+optional-targets = ["aarch64-unknown-linux-gnu"]
+
+[toolchain.target-requires]
+aarch64-unknown-linux-gnu = ["linker"]
+"#;
+        let manifest = ToolkitManifest::from_str(missing).unwrap();
+        let err = manifest.validate_dependencies().unwrap_err().to_string();
+        assert!(err.contains("'aarch64-unknown-linux-gnu'") && err.contains("'linker'"));
+    }
+
+    #[test]
+    fn bundled_manifests_are_valid() {
+        let bundled = [
+            include_str!("../../resources/toolkit-manifest/online/community.toml"),
+            include_str!("../../resources/toolkit-manifest/online/bisheng.toml"),
+            include_str!("../../resources/toolkit-manifest/online/test.toml"),
+            include_str!("../../resources/toolkit-manifest/offline/community.toml"),
+            include_str!("../../resources/toolkit-manifest/offline/bisheng.toml"),
+            include_str!("../../resources/toolkit-manifest/offline/test.toml"),
+        ];
+        for raw in bundled {
+            let manifest = ToolkitManifest::from_str(raw).unwrap();
+            manifest.validate_dependencies().unwrap();
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
     fs,
+    io::ErrorKind,
     path::{Path, PathBuf},
     str::FromStr,
     sync::LazyLock,
@@ -13,7 +14,12 @@ use rim_common::{
 
 use super::{
     directories::RimDir,
-    parser::{cargo_config::CargoConfig, cargo_manifest::CargoManifest, fingerprint::ToolRecord},
+    parser::{
+        cargo_config::CargoConfig,
+        cargo_manifest::CargoManifest,
+        crates_listing::CratesListing,
+        fingerprint::{FileRecord, ToolRecord},
+    },
     GlobalOpts, PathExt, CARGO_HOME,
 };
 use crate::{
@@ -50,6 +56,8 @@ pub(crate) struct Tool<'a> {
     pub(crate) kind: ToolKind,
     /// Additional args to run installer, currently only used for `cargo install`.
     install_args: Option<Vec<&'a str>>,
+    /// The files that were placed when installing this tool, used for uninstallation.
+    files: &'a [FileRecord],
 }
 
 /// Helper struct used for uninstallation, including basic [`Tool`] and it's dependencies list.
@@ -70,11 +78,13 @@ impl<'a> Tool<'a> {
             kind,
             path: PathExt::default(),
             install_args: None,
+            files: &[],
         }
     }
 
     setter!(with_path(self.path, path: impl Into<PathExt<'a>>) { path.into() });
     setter!(with_install_args(self.install_args, Option<Vec<&'a str>>));
+    setter!(with_files(self.files, &'a [FileRecord]));
 
     pub(crate) fn from_path(name: &str, path: &'a Path) -> Result<Self> {
         if !path.exists() {
@@ -165,7 +175,7 @@ impl<'a> Tool<'a> {
             }
             _ => Tool::new(name.into(), kind).with_path(tool_record.paths.clone()),
         };
-        Some(tool)
+        Some(tool.with_files(&tool_record.files))
     }
 
     pub(crate) fn install<T>(
@@ -182,12 +192,21 @@ impl<'a> Tool<'a> {
                     );
                 }
 
+                let before = CratesListing::load(config.cargo_home())?;
                 cargo_install_or_uninstall(
                     "install",
                     self.install_args.as_deref().unwrap_or(&[self.name()]),
                     config.cargo_home(),
                 )?;
-                return Ok(ToolRecord::cargo_tool().with_version(info.version()));
+                // record the binaries installed by cargo, so they can be verified later
+                let bins = CratesListing::load(config.cargo_home())?
+                    .installed_bins(self.name(), &before)
+                    .into_iter()
+                    .map(|bin| config.cargo_bin().join(bin))
+                    .collect::<Vec<_>>();
+                return Ok(ToolRecord::cargo_tool()
+                    .with_version(info.version())
+                    .with_files(FileRecord::collect(&bins)?));
             }
             ToolKind::Executables => {
                 let mut res = vec![];
@@ -234,8 +253,10 @@ impl<'a> Tool<'a> {
             }
         };
 
+        let files = FileRecord::collect(&paths)?;
         Ok(ToolRecord::new(self.kind)
             .with_paths(paths)
+            .with_files(files)
             .with_version(info.version())
             .with_dependencies(info.dependencies().to_vec()))
     }
@@ -256,16 +277,18 @@ impl<'a> Tool<'a> {
                 }
             }
             ToolKind::Custom => custom_instructions::uninstall(self.name(), config)?,
-            ToolKind::DirWithBin => uninstall_dir_with_bin_(config, self.path.single()?)?,
+            ToolKind::DirWithBin => {
+                uninstall_dir_with_bin_(config, self.path.single()?, self.files)?
+            }
             ToolKind::Plugin => Plugin::uninstall(self.path.single()?)?,
             ToolKind::Installer => {
                 // TODO: some installer have uninstall functionality but some may not,
                 // make a list of those and only execute it if it can be used for uninstallation
-                utils::remove(self.path.single()?)?;
+                remove_installed(self.path.single()?, self.files)?;
             }
-            ToolKind::Crate => uninstall_crate(self.name(), &self.path, config)?,
+            ToolKind::Crate => uninstall_crate(self.name(), &self.path, self.files, config)?,
             ToolKind::RuleSet => {
-                utils::remove(self.path.single()?)?;
+                remove_installed(self.path.single()?, self.files)?;
                 // make sure the linked toolchain under rustup home is "unlinked"
                 utils::remove(
                     config
@@ -274,9 +297,7 @@ impl<'a> Tool<'a> {
                         .join(RUNNER_TOOLCHAIN_NAME),
                 )?;
             }
-            ToolKind::Unknown => {
-                utils::remove(self.path.single()?)?;
-            }
+            ToolKind::Unknown => remove_installed(self.path.single()?, self.files)?,
         }
         Ok(())
     }
@@ -418,11 +439,16 @@ fn install_crate<T>(
     Ok(vec![crate_dir])
 }
 
-fn uninstall_crate<T: RimDir>(name: &str, path: &PathExt<'_>, config: T) -> Result<()> {
+fn uninstall_crate<T: RimDir>(
+    name: &str,
+    path: &PathExt<'_>,
+    files: &[FileRecord],
+    config: T,
+) -> Result<()> {
     let path = path.single()?;
 
     // remove the source code dir
-    utils::remove(path)?;
+    remove_installed(path, files)?;
 
     // update cargo config
     let mut cargo_config = CargoConfig::load_from_dir(config.cargo_home())?;
@@ -435,13 +461,64 @@ fn uninstall_crate<T: RimDir>(name: &str, path: &PathExt<'_>, config: T) -> Resu
 
 /// Uninstalling a tool with bin folder is as simple as removing the directory,
 /// and removing the `bin` dir from `PATH`.
-fn uninstall_dir_with_bin_<T: RimDir + Copy>(config: T, tool_path: &Path) -> Result<()> {
+fn uninstall_dir_with_bin_<T: RimDir + Copy>(
+    config: T,
+    tool_path: &Path,
+    files: &[FileRecord],
+) -> Result<()> {
     // Remove from `PATH` at first.
     let bin_dir = tool_path.join("bin");
     super::os::remove_from_path(config, &bin_dir)?;
 
-    fs::remove_dir_all(tool_path)?;
+    remove_installed(tool_path, files)?;
+
+    Ok(())
+}
+
+/// Remove the `files` that were recorded under `root` during installation,
+/// then remove the directories that become empty, including `root` itself.
+///
+/// Files that were not placed by the installation, such as the ones created by user,
+/// will be kept. But if there's no file recorded under `root`, which is the case of
+/// installations made by older versions, the whole `root` will be removed.
+fn remove_installed(root: &Path, files: &[FileRecord]) -> Result<()> {
+    let recorded = files
+        .iter()
+        .filter(|file| file.path.starts_with(root))
+        .collect::<Vec<_>>();
+    if recorded.is_empty() {
+        return utils::remove(root);
+    }
 
+    for file in recorded {
+        match fs::remove_file(&file.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(e)
+                    .with_context(|| format!("unable to remove file '{}'", file.path.display()));
+            }
+            _ => (),
+        }
+    }
+    remove_empty_dirs(root)?;
+    if root.exists() {
+        info!("{}", t!("unrecorded_files_kept", path = root.display()));
+    }
+    Ok(())
+}
+
+/// Remove `dir` and its sub directories if they are empty.
+fn remove_empty_dirs(dir: &Path) -> Result<()> {
+    let is_dir = fs::symlink_metadata(dir).is_ok_and(|meta| meta.is_dir());
+    if !is_dir {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        remove_empty_dirs(&entry?.path())?;
+    }
+    if fs::read_dir(dir)?.next().is_none() {
+        fs::remove_dir(dir)
+            .with_context(|| format!("unable to remove directory '{}'", dir.display()))?;
+    }
     Ok(())
 }
 
@@ -1,14 +1,18 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use rim_common::types::{TomlParser, ToolkitManifest};
 use rim_common::utils::{self, ProgressHandler};
 use std::{collections::HashMap, path::PathBuf};
 
 use super::{
     components::ToolchainComponent,
-    dependency_handler::DependencyHandler,
+    dependency_handler::{collect_dependents, DependencyHandler},
     directories::RimDir,
+    history::{History, Operation, Tracker},
+    lock::InstallationLock,
     parser::fingerprint::{InstallationRecord, ToolRecord},
     rustup::ToolchainInstaller,
     tools::ToolWithDeps,
+    ToolkitManifestExt,
 };
 use crate::core::{tools::Tool, GlobalOpts};
 
@@ -31,6 +35,11 @@ pub struct UninstallConfiguration<T> {
     pub(crate) install_dir: PathBuf,
     pub(crate) install_record: InstallationRecord,
     pub(crate) progress_handler: T,
+    /// Remove components even if other installed tools depend on them.
+    force: bool,
+    /// Prevents other processes from modifying the installation at the same time,
+    /// it's released before removing the config dir that contains the lock file.
+    lock: Option<InstallationLock>,
 }
 
 impl<T> RimDir for UninstallConfiguration<T> {
@@ -47,29 +56,41 @@ impl<T> RimDir for &UninstallConfiguration<T> {
 
 impl<T: ProgressHandler> UninstallConfiguration<T> {
     pub fn init(handler: T) -> Result<Self> {
+        let lock = InstallationLock::acquire()?;
         let install_record = InstallationRecord::load_from_config_dir()?;
         Ok(Self {
             install_dir: install_record.install_dir.clone(),
             install_record,
             progress_handler: handler,
+            force: false,
+            lock: Some(lock),
         })
     }
 
+    setter!(force(self.force, bool));
+
     pub(crate) fn inc_progress(&self, val: u64) -> Result<()> {
         self.progress_handler.update_master(Some(val))
     }
 
     pub fn uninstall(mut self, remove_self: bool) -> Result<()> {
+        let tracker = Tracker::start(Operation::Uninstall);
+        let result = self.uninstall_(remove_self);
+        tracker.finish(&result);
+        result
+    }
+
+    fn uninstall_(&mut self, remove_self: bool) -> Result<()> {
         self.progress_handler
             .start_master(t!("uninstalling").into(), utils::ProgressKind::Len(100))?;
 
         // remove all tools.
         info!("{}", t!("uninstalling_third_party_tools"));
-        self.remove_tools(InstallationRecord::load_from_config_dir()?.tools, 40)?;
+        self.remove_tools_(InstallationRecord::load_from_config_dir()?.tools, 40)?;
 
         // Remove rust toolchain via rustup.
         if self.install_record.rust.is_some() {
-            if let Err(e) = ToolchainInstaller::init(&self).uninstall(&mut self) {
+            if let Err(e) = ToolchainInstaller::init(&*self).uninstall(self) {
                 // if user has manually uninstall rustup, this will fails,
                 // then we can assume it has been removed.
                 // TODO: add an error type to indicate `rustup` cannot be found
@@ -93,7 +114,10 @@ impl<T: ProgressHandler> UninstallConfiguration<T> {
 
             info!("{}", t!("uninstall_self"));
             self.remove_self()?;
-            // remove persist config files
+            // remove persist config files, but keep the operation history
+            History::archive()?;
+            // the lock file cannot be removed while it's still opened on Windows
+            drop(self.lock.take());
             utils::remove(rim_common::dirs::rim_config_dir())?;
             info!("{}", t!("uninstall_self_residual_info"));
         } else {
@@ -107,17 +131,118 @@ impl<T: ProgressHandler> UninstallConfiguration<T> {
         Ok(())
     }
 
+    /// Get the names of installed tools and targets that depend on any of the given
+    /// components, either directly or indirectly, the given components themselves
+    /// are not included.
+    ///
+    /// The dependencies are read from the installation record, or from the toolkit
+    /// manifest in installation directory for tools installed by older versions.
+    /// The tools required by targets (such as a linker) are read from the manifest.
+    pub fn dependents_of(&self, names: &[&str]) -> Vec<String> {
+        let manifest = ToolkitManifest::load(self.install_dir.join(ToolkitManifest::FILENAME)).ok();
+        let tools = self.install_record.tools.iter().map(|(name, rec)| {
+            let dependencies = if rec.dependencies.is_empty() {
+                manifest
+                    .as_ref()
+                    .and_then(|m| m.current_target_tools().find(|(n, _)| *n == name.as_str()))
+                    .map(|(_, info)| info.dependencies().to_vec())
+                    .unwrap_or_default()
+            } else {
+                rec.dependencies.clone()
+            };
+            (name.as_str(), dependencies)
+        });
+        let targets = self
+            .install_record
+            .installed_toolchain_targets()
+            .iter()
+            .filter_map(|target| {
+                let requires = manifest.as_ref()?.toolchain.target_requires.get(target)?;
+                Some((target.as_str(), requires.clone()))
+            });
+        let nodes = tools.chain(targets).collect::<Vec<_>>();
+
+        let mut dependents = collect_dependents(
+            nodes.iter().map(|(name, deps)| (*name, deps.as_slice())),
+            names,
+        )
+        .into_iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+        dependents.sort();
+        dependents
+    }
+
+    /// Return error if other installed tools depend on any of the components with `names`,
+    /// unless [`force`](Self::force) is set.
+    fn ensure_not_required(&self, names: &[&str]) -> Result<()> {
+        if self.force || names.is_empty() {
+            return Ok(());
+        }
+        let dependents = self.dependents_of(names);
+        if !dependents.is_empty() {
+            bail!(t!(
+                "component_required_by",
+                list = names.join(", "),
+                dependents = dependents.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Uninstall a selection of toolchain components and tools, typically used in `component uninstall`.
+    ///
+    /// # Error
+    /// Return error if other installed tools depend on any of the components to remove,
+    /// unless [`force`](Self::force) is set.
+    pub fn remove_components(
+        &mut self,
+        components: &[ToolchainComponent],
+        tools: HashMap<String, ToolRecord>,
+    ) -> Result<()> {
+        let names = components
+            .iter()
+            .map(|c| c.name.as_str())
+            .chain(tools.keys().map(String::as_str))
+            .collect::<Vec<_>>();
+        self.ensure_not_required(&names)?;
+
+        let tracker = Tracker::start(Operation::RemoveComponents);
+        let result = self
+            .remove_toolchain_components_(components, 50)
+            .and_then(|_| self.remove_tools_(tools, 50));
+        tracker.finish(&result);
+        result
+    }
+
     /// Uninstall a selection of toolchain components
+    ///
+    /// # Error
+    /// Return error if other installed tools depend on any of the components,
+    /// unless [`force`](Self::force) is set.
     pub fn remove_toolchain_components(
         &mut self,
         components: &[ToolchainComponent],
         weight: u64,
+    ) -> Result<()> {
+        let names = components
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        self.ensure_not_required(&names)?;
+        self.remove_toolchain_components_(components, weight)
+    }
+
+    fn remove_toolchain_components_(
+        &mut self,
+        components: &[ToolchainComponent],
+        weight: u64,
     ) -> Result<()> {
         if components.is_empty() {
             return Ok(());
         }
 
-        ToolchainInstaller::init(&*self).remove_components(self, components)?;
+        ToolchainInstaller::init(&*self).remove_components(&*self, components)?;
 
         self.install_record.remove_component_record(components);
         self.install_record.write()?;
@@ -126,7 +251,17 @@ impl<T: ProgressHandler> UninstallConfiguration<T> {
     }
 
     /// Uninstall a selection of tools
+    ///
+    /// # Error
+    /// Return error if other installed tools depend on any of the given tools,
+    /// unless [`force`](Self::force) is set.
     pub fn remove_tools(&mut self, tools: HashMap<String, ToolRecord>, weight: u64) -> Result<()> {
+        let names = tools.keys().map(String::as_str).collect::<Vec<_>>();
+        self.ensure_not_required(&names)?;
+        self.remove_tools_(tools, weight)
+    }
+
+    fn remove_tools_(&mut self, tools: HashMap<String, ToolRecord>, weight: u64) -> Result<()> {
         let mut tools_to_uninstall = vec![];
         for (name, tool_detail) in &tools {
             let Some(tool) = Tool::from_installed(name, tool_detail) else {
@@ -175,3 +310,62 @@ impl<T: ProgressHandler> UninstallConfiguration<T> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rim_common::types::ToolKind;
+    use rim_common::utils::HiddenProgress;
+
+    #[test]
+    fn dependents_include_targets() {
+        let mut cache_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        cache_dir.push("tests");
+        cache_dir.push("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let root = tempfile::Builder::new().tempdir_in(&cache_dir).unwrap();
+
+        let manifest = ToolkitManifest::from_str(
+            r#"
+[toolchain]
+channel = "1.0.0"
+optional-targets = ["aarch64-unknown-linux-gnu"]
+
+[toolchain.target-requires]
+aarch64-unknown-linux-gnu = ["linker"]
+
+[tools.target.all]
+linker = "0.1.0"
+"#,
+        )
+        .unwrap();
+        manifest.write_to_dir(root.path()).unwrap();
+
+        let mut install_record = InstallationRecord {
+            install_dir: root.path().to_path_buf(),
+            ..Default::default()
+        };
+        install_record.add_rust_record(
+            "1.0.0",
+            &[ToolchainComponent::new("aarch64-unknown-linux-gnu").is_target(true)],
+        );
+        install_record.add_tool_record("linker", ToolRecord::new(ToolKind::Custom));
+        install_record.add_tool_record(
+            "app",
+            ToolRecord::new(ToolKind::Custom).with_dependencies(vec!["linker".into()]),
+        );
+        let config = UninstallConfiguration {
+            install_dir: root.path().to_path_buf(),
+            install_record,
+            progress_handler: HiddenProgress,
+            force: false,
+            lock: Some(InstallationLock::acquire().unwrap()),
+        };
+
+        assert_eq!(
+            config.dependents_of(&["linker"]),
+            ["aarch64-unknown-linux-gnu", "app"]
+        );
+        assert!(config.dependents_of(&["app"]).is_empty());
+    }
+}
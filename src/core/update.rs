@@ -11,8 +11,9 @@ use serde::Serialize;
 use url::Url;
 
 use super::directories::RimDir;
+use super::lock::InstallationLock;
 use super::parser::release_info::Releases;
-use super::{AppInfo, GlobalOpts};
+use super::{signature, AppInfo, GlobalOpts};
 use crate::toolkit;
 
 /// Caching the latest manager release info, reduce the number of time accessing the server.
@@ -59,6 +60,7 @@ impl<T: ProgressHandler + Clone + 'static> UpdateOpt<T> {
             );
             return Ok(false);
         }
+        let _lock = InstallationLock::acquire()?;
 
         #[cfg(not(feature = "gui"))]
         let cli = "-cli";
@@ -153,6 +155,8 @@ impl<T: ProgressHandler + Clone + 'static> UpdateOpt<T> {
             )
         };
         let raw = opt.insecure(self.insecure).read(&download_url).await?;
+        signature::verify_manifest("release info", &download_url, raw.as_bytes(), self.insecure)
+            .await?;
         let release_info = Releases::from_str(&raw)?;
 
         Ok(LATEST_RELEASE.get_or_init(|| release_info))
@@ -0,0 +1,105 @@
+//! Crates that are vendored for offline use, which replace the `crates-io` source
+//! in cargo configuration.
+//!
+//! Check [`VendoredCrates`](rim_common::types::VendoredCrates) for how they are listed
+//! in toolkit manifest.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use rim_common::types::TomlParser;
+use rim_common::utils;
+
+use super::parser::cargo_config::CargoConfig;
+
+/// Name of the cargo source that points to the vendored crates.
+pub(crate) const VENDORED_SOURCE_NAME: &str = "vendored-crates";
+
+/// The directory to store vendored crates, a.k.a. `<INSTALL_DIR>/vendor/`.
+pub(crate) fn vendored_crates_dir(install_dir: &Path) -> PathBuf {
+    install_dir.join("vendor")
+}
+
+/// Copy the crates in `src` into `dest`, where `src` should be a directory in the same
+/// layout as the output of `cargo vendor`. Crates that already exist in `dest` are skipped.
+///
+/// Return the names of the crates (with version) that were added.
+pub(crate) fn merge_crates(src: &Path, dest: &Path) -> Result<Vec<String>> {
+    let crates = crate_dirs_in(src)?;
+    if crates.is_empty() {
+        bail!(t!("no_vendored_crates", path = src.display()));
+    }
+
+    utils::ensure_dir(dest)?;
+    let mut added = vec![];
+    for dir in crates {
+        let Some(name) = dir.file_name().map(utils::to_string_lossy) else {
+            continue;
+        };
+        let target = dest.join(&name);
+        if target.exists() {
+            debug!("skipping existing vendored crate '{name}'");
+            continue;
+        }
+        utils::copy_as(&dir, &target)?;
+        added.push(name);
+    }
+    Ok(added)
+}
+
+/// Replace the `crates-io` source with the vendored crates in `dir`,
+/// by modifying the cargo configuration under `cargo_home`.
+pub(crate) fn use_vendored_source(cargo_home: &Path, dir: &Path) -> Result<()> {
+    let mut config = CargoConfig::load_from_dir(cargo_home)?;
+    config.add_directory_source(VENDORED_SOURCE_NAME, dir, true);
+    config.write_to_dir(cargo_home)
+}
+
+/// Get the crate directories in `dir`, each one of them must contain a `.cargo-checksum.json`
+/// file, which is required by cargo to use them as a directory source.
+fn crate_dirs_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        bail!(t!("path_not_dir", path = dir.display()));
+    }
+    let mut crates = utils::walk_dir(dir, false)?
+        .into_iter()
+        .filter(|path| path.join(".cargo-checksum.json").is_file())
+        .collect::<Vec<_>>();
+    crates.sort();
+    Ok(crates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_crate(root: &Path, name: &str) {
+        let dir = root.join(name);
+        utils::ensure_dir(&dir).unwrap();
+        utils::write_file(dir.join(".cargo-checksum.json"), "{}", false).unwrap();
+        utils::write_file(dir.join("Cargo.toml"), "", false).unwrap();
+    }
+
+    #[test]
+    fn merge_vendored_crates() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        fake_crate(src.path(), "serde-1.0.210");
+        fake_crate(src.path(), "tokio-1.40.0");
+        // not a crate
+        utils::ensure_dir(src.path().join("other")).unwrap();
+        fake_crate(dest.path(), "serde-1.0.210");
+
+        let added = merge_crates(src.path(), dest.path()).unwrap();
+        assert_eq!(added, ["tokio-1.40.0"]);
+        assert!(dest
+            .path()
+            .join("tokio-1.40.0")
+            .join("Cargo.toml")
+            .is_file());
+        assert!(!dest.path().join("other").exists());
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(merge_crates(empty.path(), dest.path()).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Verify the files of installed tools against the records made during installation.
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::parser::fingerprint::{FileState, InstallationRecord};
+
+/// The verification result of a single tool.
+#[derive(Debug, Serialize)]
+pub(crate) struct ToolReport {
+    pub(crate) name: String,
+    /// Whether the files of this tool were recorded, which is not the case for
+    /// the ones installed by older versions.
+    pub(crate) verifiable: bool,
+    /// Files that are missing or modified.
+    pub(crate) problems: Vec<FileProblem>,
+}
+
+impl ToolReport {
+    pub(crate) fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FileProblem {
+    pub(crate) path: PathBuf,
+    pub(crate) state: FileState,
+}
+
+/// Verify the files of installed tools with the given `names`,
+/// or all installed tools if `names` is empty.
+///
+/// # Error
+/// Return error if any of the `names` is not an installed tool.
+pub(crate) fn verify(record: &InstallationRecord, names: &[String]) -> Result<Vec<ToolReport>> {
+    let mut names = if names.is_empty() {
+        record.tools.keys().cloned().collect::<Vec<_>>()
+    } else {
+        let invalid = names
+            .iter()
+            .filter(|name| !record.tools.contains_key(*name))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !invalid.is_empty() {
+            bail!(t!("invalid_components", list = invalid.join(",")));
+        }
+        names.to_vec()
+    };
+    names.sort();
+    names.dedup();
+
+    let reports = names
+        .into_iter()
+        .map(|name| {
+            let files = &record.tools[&name].files;
+            let problems = files
+                .iter()
+                .filter_map(|file| {
+                    let state = file.state();
+                    (state != FileState::Intact).then(|| FileProblem {
+                        path: file.path.clone(),
+                        state,
+                    })
+                })
+                .collect();
+            ToolReport {
+                name,
+                verifiable: !files.is_empty(),
+                problems,
+            }
+        })
+        .collect();
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::parser::fingerprint::{FileRecord, ToolRecord};
+    use rim_common::types::ToolKind;
+
+    #[test]
+    fn verify_recorded_files() {
+        let root = tempfile::tempdir().unwrap();
+        let exe = root.path().join("a");
+        std::fs::write(&exe, "a").unwrap();
+        let files = FileRecord::collect(&[&exe]).unwrap();
+
+        let mut record = InstallationRecord::default();
+        record.add_tool_record(
+            "a",
+            ToolRecord::new(ToolKind::Executables).with_files(files),
+        );
+        record.add_tool_record("b", ToolRecord::cargo_tool());
+
+        let reports = verify(&record, &[]).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].verifiable && reports[0].is_intact());
+        assert!(!reports[1].verifiable);
+
+        std::fs::remove_file(&exe).unwrap();
+        let reports = verify(&record, &["a".into()]).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].problems[0].path, exe);
+        assert_eq!(reports[0].problems[0].state, FileState::Missing);
+
+        assert!(verify(&record, &["c".into()]).is_err());
+    }
+}
@@ -16,7 +16,9 @@ pub use core::install::{default_install_dir, EnvConfig, InstallConfiguration};
 pub use core::parser::fingerprint;
 pub use core::try_it::try_it;
 pub use core::uninstall::UninstallConfiguration;
-pub use core::{components, toolkit, update, AppInfo, GlobalOpts, Mode, ToolkitManifestExt};
+pub use core::{
+    components, lock, preflight, toolkit, update, AppInfo, GlobalOpts, Mode, ToolkitManifestExt,
+};
 pub use core::{
     default_cargo_registry, default_rustup_dist_server, default_rustup_update_root,
     get_toolkit_manifest,
@@ -131,6 +131,71 @@ fn extracting_normal_xz() {
     assert_normal_archive(&temp_dir.0);
 }
 
+#[rim_test]
+fn extracting_simple_bz2() {
+    let temp_dir = extract_to_temp("simple_bz2.tar.bz2", true);
+
+    assert!(temp_dir.0.join("aaa.txt").is_file());
+    assert!(temp_dir.0.join("bbb.txt").is_file());
+    assert!(temp_dir.0.join("ccc.txt").is_file());
+}
+
+#[rim_test]
+fn extracting_simple_zst() {
+    let temp_dir = extract_to_temp("simple_zst.tar.zst", true);
+
+    assert!(temp_dir.0.join("aaa.txt").is_file());
+    assert!(temp_dir.0.join("bbb.txt").is_file());
+    assert!(temp_dir.0.join("ccc.txt").is_file());
+}
+
+#[rim_test]
+fn extracting_simple_tar() {
+    let temp_dir = extract_to_temp("simple_tar.tar", true);
+
+    assert!(temp_dir.0.join("aaa.txt").is_file());
+    assert!(temp_dir.0.join("bbb.txt").is_file());
+    assert!(temp_dir.0.join("ccc.txt").is_file());
+}
+
+#[rim_test]
+fn extracting_gz_without_extension() {
+    let temp_dir = extract_to_temp("simple_gz_without_extension", true);
+
+    assert!(temp_dir.0.join("aaa.txt").is_file());
+    assert!(temp_dir.0.join("bbb.txt").is_file());
+    assert!(temp_dir.0.join("ccc.txt").is_file());
+}
+
+#[rim_test]
+fn extracting_compressed_single_file() {
+    for filename in ["single_file.txt.gz", "single_file.txt.xz"] {
+        let temp_dir = extract_to_temp(filename, false);
+
+        let content = std::fs::read_to_string(temp_dir.0.join("single_file.txt")).unwrap();
+        assert_eq!(content, "hello world\n");
+    }
+}
+
+#[rim_test]
+fn supported_extractable_files() {
+    let assets_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("assets");
+    for filename in [
+        "simple_bz2.tar.bz2",
+        "simple_zst.tar.zst",
+        "simple_tar.tar",
+        "single_file.txt.gz",
+        "simple_gz_without_extension",
+    ] {
+        assert!(utils::Extractable::is_supported(&assets_dir.join(filename)));
+    }
+    assert!(!utils::Extractable::is_supported(
+        &assets_dir.join("dir_to_walk")
+    ));
+}
+
 #[rim_test]
 fn extracting_xz_with_prefix() {
     let temp_dir = extract_to_temp("xz_with_prefixes.tar.xz", true);
@@ -150,3 +215,111 @@ fn extracting_zip_with_prefix() {
     let temp_dir = extract_to_temp("zip_with_prefixes.zip", true);
     assert_extracted_with_prefixes(&temp_dir.0);
 }
+
+/// Extract a malicious archive into a sub directory of a temp dir,
+/// and make sure it fails without writing anything outside of that sub directory.
+fn assert_rejected(filename: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("assets")
+        .join(filename);
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output_dir = temp_dir.path().join("output");
+
+    let result = utils::Extractable::load(&path, None)
+        .unwrap()
+        .extract_to(&output_dir);
+    assert!(result.is_err(), "'{filename}' should not be extracted");
+    assert_eq!(
+        utils::walk_dir(temp_dir.path(), false).unwrap(),
+        [output_dir]
+    );
+}
+
+#[rim_test]
+fn rejecting_entries_with_parent_dir() {
+    assert_rejected("malicious_parent_dir.tar.gz");
+    assert_rejected("malicious_parent_dir.zip");
+    assert_rejected("malicious_parent_dir.7z");
+}
+
+#[rim_test]
+fn rejecting_entries_with_absolute_path() {
+    assert_rejected("malicious_absolute_path.tar");
+    assert!(!Path::new("/tmp/rim_escaped_by_absolute_path.txt").exists());
+}
+
+#[rim_test]
+fn rejecting_links_to_outside() {
+    assert_rejected("malicious_symlink.tar.gz");
+    assert_rejected("malicious_symlink.zip");
+    assert_rejected("malicious_symlink_chain.tar");
+    assert_rejected("malicious_hard_link.tar");
+}
+
+#[cfg(unix)]
+#[rim_test]
+fn extracting_safe_symlinks() {
+    let temp_dir = extract_to_temp("safe_symlink.tar.gz", true);
+
+    let link = temp_dir.0.join("bin").join("tool");
+    assert_eq!(std::fs::read_link(link).unwrap(), Path::new("tool-1.0"));
+    assert!(temp_dir.0.join("lib").join("tool-1.0").is_file());
+}
+
+#[cfg(unix)]
+#[rim_test]
+fn preserving_unix_modes() {
+    use std::os::unix::fs::PermissionsExt;
+
+    for filename in ["zip_with_executable.zip", "7z_with_executable.7z"] {
+        let temp_dir = extract_to_temp(filename, true);
+        let mode_of = |name: &str| {
+            let meta = std::fs::metadata(temp_dir.0.join(name)).unwrap();
+            meta.permissions().mode() & 0o777
+        };
+        assert_eq!(mode_of("run.sh"), 0o755, "{filename}");
+        assert_eq!(mode_of("readme.txt"), 0o644, "{filename}");
+    }
+
+    // the setuid bit is not kept
+    let temp_dir = extract_to_temp("tar_with_setuid.tar", false);
+    let meta = std::fs::metadata(temp_dir.0.join("run.sh")).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o7777, 0o755);
+}
+
+#[rim_test]
+fn extraction_limits() {
+    let assets_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("assets");
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let archive = assets_dir.join("gz_with_sub_folders.tar.gz");
+    assert!(utils::Extractable::load(&archive, None)
+        .unwrap()
+        .max_entries(3)
+        .extract_to(temp_dir.path())
+        .is_err());
+
+    // decompressed size of this file is 12 bytes
+    let single_file = assets_dir.join("single_file.txt.xz");
+    assert!(utils::Extractable::load(&single_file, None)
+        .unwrap()
+        .max_size(5)
+        .extract_to(temp_dir.path())
+        .is_err());
+    assert!(utils::Extractable::load(&single_file, None)
+        .unwrap()
+        .max_size(12)
+        .extract_to(temp_dir.path())
+        .is_ok());
+
+    // the header of this sparse file says it has 1 byte of data, but it expands to 1 MiB
+    let sparse = assets_dir.join("sparse_file.tar");
+    assert!(utils::Extractable::load(&sparse, None)
+        .unwrap()
+        .max_size(1024)
+        .extract_to(temp_dir.path())
+        .is_err());
+}
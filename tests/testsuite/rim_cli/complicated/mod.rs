@@ -5,7 +5,7 @@ use rim_test_support::{
     process::{mocked_dist_server, TestProcess},
     rim_test,
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 macro_rules! assert_files {
     ($($root:ident.$bin:expr),+) => {
@@ -123,6 +123,15 @@ fn uninstall_using_linked_manager() {
     }
 }
 
+/// Return the path to the `rim` executable of the given installation.
+fn installed_rim(process: &TestProcess) -> PathBuf {
+    process
+        .default_install_dir()
+        .join("cargo")
+        .join("bin")
+        .join(exe!("rim"))
+}
+
 fn list_component_output(process: &TestProcess, rim: &Path) -> String {
     let list_comp_output = process
         .rim_command(&rim)
@@ -197,6 +206,44 @@ llvm-tools"
     );
 }
 
+#[rim_test]
+fn hold_components() {
+    let process = super::default_install(true);
+    let rim = process
+        .default_install_dir()
+        .join("cargo")
+        .join("bin")
+        .join(exe!("rim"));
+    let hold = |args: &[&str]| {
+        process
+            .rim_command(&rim)
+            .args(["component"])
+            .args(args)
+            .status()
+            .unwrap()
+            .success()
+    };
+
+    // toolchain components can only be held along with the toolchain
+    assert!(!hold(&["hold", "clippy"]));
+    assert!(!hold(&["hold", "llvm-tools"]));
+    assert!(hold(&["hold", "Minimal"]));
+
+    let output = process
+        .rim_command(&rim)
+        .args(["list", "--installed", "component"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|ln| ln == "Minimal (held)"));
+    assert!(stdout.lines().any(|ln| ln == "clippy (held)"));
+
+    assert!(hold(&["unhold", "Minimal"]));
+    let output = list_component_output(&process, &rim);
+    assert!(!output.contains("held"));
+}
+
 #[rim_test]
 fn install_with_specific_components() {
     let process = TestProcess::combined();
@@ -254,7 +301,8 @@ fn linked_rim_install_then_update() {
     let list_output = process
         .rim_command(&rim)
         .env("RIM_DIST_SERVER", mocked_dist_server().rim.as_str())
-        .args(["list", "toolkit"])
+        // the mocked server doesn't sign its manifests
+        .args(["--allow-unsigned", "list", "toolkit"])
         .output()
         .unwrap();
     assert!(list_output.status.success());
@@ -328,3 +376,697 @@ llvm-tools
 rust-docs"
     );
 }
+
+#[rim_test]
+fn list_components_in_json() {
+    let process = super::default_install(true);
+    let install_dir = process.default_install_dir();
+    let rim = install_dir.join("cargo").join("bin").join(exe!("rim"));
+
+    let output = process
+        .rim_command(&rim)
+        .args(["--format", "json", "list", "component"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    // log messages are printed on stderr, so stdout must be a valid JSON document
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["schema_version"], 1);
+    assert_eq!(doc["command"], "list-component");
+
+    let components = doc["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|comp| {
+            (
+                comp["name"].as_str().unwrap(),
+                comp["installed"].as_bool().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        components,
+        [
+            ("Minimal", true),
+            ("clippy", true),
+            ("rustfmt", true),
+            ("rust-src", true),
+            ("llvm-tools", false),
+            ("rust-docs", false),
+        ]
+    );
+}
+
+/// Run `apply --dry-run` then return the planned toolkit meta and steps.
+fn apply_plan(process: &TestProcess, rim: &Path, lockfile: &Path) -> serde_json::Value {
+    let output = process
+        .rim_command(rim)
+        .args(["--format", "json", "apply", "--dry-run"])
+        .arg(lockfile)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    doc["data"].clone()
+}
+
+#[rim_test]
+fn apply_lockfile() {
+    let process = super::default_install(true);
+    let install_dir = process.default_install_dir();
+    let rim = install_dir.join("cargo").join("bin").join(exe!("rim"));
+    let record_path = process.config_dir().join("install-record.toml");
+    let record: toml::Table = std::fs::read_to_string(&record_path)
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    // lock the installed toolchain with an extra component, and keep the installed tools
+    let channel = record["rust"]["version"].as_str().unwrap();
+    let components = record["rust"]["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap())
+        .chain(["llvm-tools"])
+        .map(|c| format!("{c:?}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let tools = record
+        .get("tools")
+        .and_then(|t| t.as_table())
+        .map(|t| {
+            t.keys()
+                .map(|name| format!("{name:?} = {{ path = \".\" }}\n"))
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+    let lock = format!(
+        "[toolkit]\nname = \"Locked Toolkit\"\nversion = \"9.9.9\"\n\n\
+        [rust]\nchannel = {channel:?}\ncomponents = [{components}]\n\n\
+        [tools]\n{tools}"
+    );
+    let lockfile = process.root().join("rim.lock");
+    std::fs::write(&lockfile, lock).unwrap();
+
+    let plan = apply_plan(&process, &rim, &lockfile);
+    assert_eq!(plan["toolkit"]["version"], "9.9.9");
+    let steps = plan["steps"].as_array().unwrap();
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0]["name"], "llvm-tools");
+    assert_eq!(steps[0]["action"], "install");
+
+    let status = process
+        .rim_command(&rim)
+        .args(["-y", "apply"])
+        .arg(&lockfile)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // the installation should match the lockfile now
+    let plan = apply_plan(&process, &rim, &lockfile);
+    assert!(plan["toolkit"].is_null());
+    assert!(plan["steps"].as_array().unwrap().is_empty());
+}
+
+#[rim_test]
+fn failed_update_is_reverted() {
+    let process = super::default_install(true);
+    let rim = installed_rim(&process);
+    let record_path = process.config_dir().join("install-record.toml");
+    let record_before = std::fs::read_to_string(&record_path).unwrap();
+    let record: toml::Table = record_before.parse().unwrap();
+    let channel = record["rust"]["version"].as_str().unwrap();
+    let other_channel = if channel == "1.82.0" {
+        "1.81.0"
+    } else {
+        "1.82.0"
+    };
+
+    // keep the installed tools, the toolchain is switched before the new tool fails to install
+    let tools = record
+        .get("tools")
+        .and_then(|t| t.as_table())
+        .map(|t| {
+            t.keys()
+                .map(|name| format!("{name:?} = {{ path = \".\" }}\n"))
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+    let lockfile = process.root().join("rim.lock");
+    std::fs::write(
+        &lockfile,
+        format!(
+            "[rust]\nchannel = {other_channel:?}\n\n\
+            [tools]\n{tools}broken-tool = {{ path = \"missing-tool.zip\" }}\n"
+        ),
+    )
+    .unwrap();
+    let status = process
+        .rim_command(&rim)
+        .args(["-y", "apply"])
+        .arg(&lockfile)
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    assert_eq!(
+        std::fs::read_to_string(&record_path).unwrap(),
+        record_before
+    );
+    let rustup_home = process.default_install_dir().join("rustup");
+    let settings = std::fs::read_to_string(rustup_home.join("settings.toml")).unwrap();
+    assert!(settings.contains(&format!("default_toolchain = \"{channel}")));
+    let toolchains = std::fs::read_dir(rustup_home.join("toolchains"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    assert!(toolchains.iter().any(|tc| tc.starts_with(channel)));
+    assert!(!toolchains.iter().any(|tc| tc.starts_with(other_channel)));
+}
+
+#[rim_test]
+fn export_installed_manifest() {
+    let process = super::default_install(true);
+    let install_dir = process.default_install_dir();
+    let rim = install_dir.join("cargo").join("bin").join(exe!("rim"));
+    let record: toml::Table =
+        std::fs::read_to_string(process.config_dir().join("install-record.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+
+    let output = process.rim_command(&rim).arg("export").output().unwrap();
+    assert!(output.status.success());
+    // log messages are printed on stderr, so stdout must be a valid manifest
+    let manifest: toml::Table = String::from_utf8(output.stdout).unwrap().parse().unwrap();
+    assert_eq!(manifest.get("name"), record.get("name"));
+    assert_eq!(manifest.get("version"), record.get("version"));
+    assert_eq!(manifest["rust"]["channel"], record["rust"]["version"]);
+    assert_eq!(manifest["rust"]["components"], record["rust"]["components"]);
+
+    // the same manifest should be written to a file with `--output`
+    let exported = process.root().join("exported.toml");
+    let status = process
+        .rim_command(&rim)
+        .args(["export", "--output"])
+        .arg(&exported)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let written: toml::Table = std::fs::read_to_string(&exported).unwrap().parse().unwrap();
+    assert_eq!(written, manifest);
+}
+
+#[rim_test]
+fn verify_installed_files() {
+    let process = TestProcess::combined();
+    let hello = install_with_local_tool(&process);
+    let rim = installed_rim(&process);
+    let verify_hello = || {
+        let output = process
+            .rim_command(&rim)
+            .args(["--format", "json", "verify", "hello"])
+            .output()
+            .unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(doc["command"], "verify");
+        let report = doc["data"][0].clone();
+        assert_eq!(report["name"], "hello");
+        assert_eq!(report["verifiable"], true);
+        (output.status.success(), report["problems"].clone())
+    };
+
+    let (success, problems) = verify_hello();
+    assert!(success);
+    assert!(problems.as_array().unwrap().is_empty());
+
+    std::fs::write(&hello, "modified").unwrap();
+    let (success, problems) = verify_hello();
+    assert!(!success);
+    assert_eq!(problems[0]["path"], hello.to_str().unwrap());
+    assert_eq!(problems[0]["state"], "modified");
+
+    std::fs::remove_file(&hello).unwrap();
+    let (success, problems) = verify_hello();
+    assert!(!success);
+    assert_eq!(problems[0]["state"], "missing");
+
+    let output = process
+        .rim_command(&rim)
+        .args(["verify", "not-installed"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+/// Install a toolkit with a local executable named `hello`,
+/// and return the path to the installed copy of it.
+fn install_with_local_tool(process: &TestProcess) -> PathBuf {
+    let tool = process.root().join(exe!("hello"));
+    std::fs::write(&tool, "hello").unwrap();
+
+    let manifest = process.root().join("local-tool-manifest.toml");
+    std::fs::write(
+        &manifest,
+        format!(
+            r#"
+[toolchain]
+channel = "1.86.0"
+profile = "minimal"
+
+[tools.target.all.hello]
+required = true
+path = '{}'
+"#,
+            tool.display()
+        ),
+    )
+    .unwrap();
+    process
+        .command()
+        .args(["-y", "--manifest"])
+        .arg(&manifest)
+        .assert()
+        .success();
+
+    let hello = process
+        .default_install_dir()
+        .join("cargo")
+        .join("bin")
+        .join(exe!("hello"));
+    assert!(hello.is_file());
+    hello
+}
+
+#[rim_test]
+fn repair_dry_run_changes_nothing() {
+    let process = TestProcess::combined();
+    let hello = install_with_local_tool(&process);
+    let rim = installed_rim(&process);
+    std::fs::remove_file(&hello).unwrap();
+
+    let output = process
+        .rim_command(&rim)
+        .args(["--format", "json", "repair", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["command"], "repair");
+    assert_eq!(doc["data"]["tools"], serde_json::json!(["hello"]));
+    assert_eq!(doc["data"]["toolchain"], false);
+    assert!(!hello.exists());
+}
+
+#[rim_test]
+fn repair_damaged_tool() {
+    let process = TestProcess::combined();
+    let hello = install_with_local_tool(&process);
+    let rim = installed_rim(&process);
+
+    // both missing and modified files are repaired
+    let damages: [fn(&Path); 2] = [
+        |path| std::fs::remove_file(path).unwrap(),
+        |path| std::fs::write(path, "modified").unwrap(),
+    ];
+    for damage in damages {
+        damage(&hello);
+        let status = process
+            .rim_command(&rim)
+            .args(["-y", "repair"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(std::fs::read_to_string(&hello).unwrap(), "hello");
+    }
+
+    let output = process
+        .rim_command(&rim)
+        .args(["--format", "json", "repair", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["data"]["tools"], serde_json::json!([]));
+}
+
+#[rim_test]
+fn json_output_requires_yes_to_confirm() {
+    let process = super::default_install(true);
+    let rim = process
+        .default_install_dir()
+        .join("cargo")
+        .join("bin")
+        .join(exe!("rim"));
+
+    let output = process
+        .rim_command(&rim)
+        .args(["--format", "json", "uninstall"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(rim.is_file());
+    assert!(process.config_dir().join("install-record.toml").is_file());
+}
+
+#[rim_test]
+fn record_operation_history() {
+    let process = super::default_install(true);
+    let rim = process
+        .default_install_dir()
+        .join("cargo")
+        .join("bin")
+        .join(exe!("rim"));
+
+    let output = process
+        .rim_command(&rim)
+        .args(["--format", "json", "history"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["command"], "history");
+    let entries = doc["data"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["operation"], "install");
+    assert_eq!(entries[0]["result"], "success");
+
+    // there's nothing to go back to before the installation
+    let output = process
+        .rim_command(&rim)
+        .args(["undo", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[rim_test]
+fn undo_component_removal() {
+    let process = TestProcess::combined();
+    let hello = install_with_local_tool(&process);
+    let rim = installed_rim(&process);
+
+    let status = process
+        .rim_command(&rim)
+        .args(["-y", "component", "remove", "hello"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(!hello.exists());
+
+    let status = process
+        .rim_command(&rim)
+        .args(["-y", "undo"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(hello.is_file());
+
+    let output = process
+        .rim_command(&rim)
+        .args(["--format", "json", "history"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entries = doc["data"].as_array().unwrap();
+    let last = entries.last().unwrap();
+    assert_eq!(last["operation"], "undo");
+    assert_eq!(last["result"], "success");
+    // only the removed tool is kept to be restored
+    let removal = &entries[entries.len() - 2];
+    assert_eq!(removal["undone-by"], last["id"]);
+    assert!(removal["before"]["rust"].is_null());
+    assert_eq!(
+        removal["before"]["tools"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>(),
+        ["hello"]
+    );
+}
+
+#[rim_test]
+fn pin_toolkit_version() {
+    let process = super::default_install(true);
+    let rim = installed_rim(&process);
+    let record: toml::Table =
+        std::fs::read_to_string(process.config_dir().join("install-record.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+    let version = record["version"].as_str().unwrap();
+    let conf_path = process.config_dir().join("configuration.toml");
+
+    // pinning to the installed version doesn't need to switch
+    let status = process
+        .rim_command(&rim)
+        .args(["pin", version])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let conf: toml::Table = std::fs::read_to_string(&conf_path)
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(conf["update"]["pinned-toolkit"].as_str(), Some(version));
+
+    // switching to another version is rejected while pinned
+    let status = process
+        .rim_command(&rim)
+        .args(["switch", "0.0.1"])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let status = process.rim_command(&rim).arg("unpin").status().unwrap();
+    assert!(status.success());
+    let conf: toml::Table = std::fs::read_to_string(&conf_path)
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(conf["update"].get("pinned-toolkit").is_none());
+}
+
+#[rim_test]
+fn manage_download_cache() {
+    let process = super::default_install(true);
+    let rim = process
+        .default_install_dir()
+        .join("cargo")
+        .join("bin")
+        .join(exe!("rim"));
+
+    let downloads = process.cache_dir().join("downloads");
+    for (key, last_used) in [
+        ("old", "2000-01-01T00:00:00Z"),
+        ("new", "2999-01-01T00:00:00Z"),
+    ] {
+        let entry_dir = downloads.join(key);
+        std::fs::create_dir_all(&entry_dir).unwrap();
+        std::fs::write(entry_dir.join(format!("{key}.zip")), "hello").unwrap();
+        let entry = format!(
+            r#"
+url = "https://example.com/{key}.zip"
+sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+filename = "{key}.zip"
+size = 5
+created = "{last_used}"
+last-used = "{last_used}"
+"#
+        );
+        std::fs::write(entry_dir.join("entry.toml"), entry).unwrap();
+    }
+
+    let output = process
+        .rim_command(&rim)
+        .args(["cache", "list"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("old.zip"));
+    assert!(stdout.contains("new.zip"));
+
+    let status = process
+        .rim_command(&rim)
+        .args(["cache", "prune", "--older-than", "30d"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(!downloads.join("old").exists());
+    assert!(downloads.join("new").join("new.zip").is_file());
+
+    let status = process
+        .rim_command(&rim)
+        .args(["cache", "clean"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(!downloads.join("new").exists());
+}
+
+/// Run `doctor` with JSON output then return the status of the given check, and whether it was fixed.
+fn doctor_check_result(
+    process: &TestProcess,
+    rim: &Path,
+    check: &str,
+    fix: bool,
+) -> (String, bool) {
+    let mut cmd = process.rim_command(rim);
+    cmd.args(["--format", "json", "doctor"]);
+    if fix {
+        cmd.arg("--fix");
+    }
+    let output = cmd.output().unwrap();
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["command"], "doctor");
+
+    let diag = doc["data"]["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|diag| diag["check"] == check)
+        .unwrap();
+    (
+        diag["status"].as_str().unwrap().to_string(),
+        diag["fixed"].as_bool().unwrap(),
+    )
+}
+
+#[rim_test]
+fn doctor_fixes_missing_cargo_config() {
+    let process = super::default_install(true);
+    let install_dir = process.default_install_dir();
+    let rim = installed_rim(&process);
+
+    assert_eq!(
+        doctor_check_result(&process, &rim, "toolchain", false),
+        ("pass".to_string(), false)
+    );
+
+    let cargo_config = install_dir.join("cargo").join("config.toml");
+    std::fs::remove_file(&cargo_config).unwrap();
+    assert_eq!(
+        doctor_check_result(&process, &rim, "cargo-config", false),
+        ("warn".to_string(), false)
+    );
+    assert!(!cargo_config.exists());
+
+    assert_eq!(
+        doctor_check_result(&process, &rim, "cargo-config", true),
+        ("warn".to_string(), true)
+    );
+    assert!(cargo_config.is_file());
+}
+
+#[rim_test]
+fn add_vendored_crates() {
+    let process = super::default_install(true);
+    let install_dir = process.default_install_dir();
+    let rim = installed_rim(&process);
+
+    // a directory in the same layout as the output of `cargo vendor --versioned-dirs`
+    let vendor = process.root().join("vendor");
+    for name in ["serde-1.0.210", "tokio-1.40.0"] {
+        let dir = vendor.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".cargo-checksum.json"), "{}").unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+    }
+
+    let status = process
+        .rim_command(&rim)
+        .args(["crates", "add"])
+        .arg(&vendor)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let vendored = install_dir.join("vendor");
+    assert!(vendored.join("serde-1.0.210").join("Cargo.toml").is_file());
+    assert!(vendored.join("tokio-1.40.0").join("Cargo.toml").is_file());
+
+    let config: toml::Table =
+        std::fs::read_to_string(install_dir.join("cargo").join("config.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+    assert_eq!(
+        config["source"]["crates-io"]["replace-with"].as_str(),
+        Some("vendored-crates")
+    );
+    assert_eq!(
+        config["source"]["vendored-crates"]["directory"].as_str(),
+        Some(vendored.display().to_string().replace('\\', "/").as_str())
+    );
+
+    // a directory without any crate in it
+    let empty = process.root().join("empty");
+    std::fs::create_dir_all(&empty).unwrap();
+    let status = process
+        .rim_command(&rim)
+        .args(["crates", "add"])
+        .arg(&empty)
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[rim_test]
+fn switch_to_older_toolkit() {
+    let process = TestProcess::combined();
+    let rim_server = mocked_dist_server().rim.to_file_path().unwrap();
+    // the toolkit of the mocked server, with a local tool that the older toolkit doesn't have
+    let tool = process.root().join(exe!("hello"));
+    std::fs::write(&tool, "hello").unwrap();
+    let mut manifest =
+        std::fs::read_to_string(rim_server.join("dist").join("stable-1.86.0.toml")).unwrap();
+    manifest.push_str(&format!(
+        "\n[tools.target.all.hello]\nrequired = true\npath = '{}'\n",
+        tool.display()
+    ));
+    let manifest_path = process.root().join("stable-1.86.0.toml");
+    std::fs::write(&manifest_path, manifest).unwrap();
+    process
+        .command()
+        .args(["-y", "--manifest"])
+        .arg(&manifest_path)
+        .assert()
+        .success();
+
+    let install_dir = process.default_install_dir();
+    let hello = install_dir.join("cargo").join("bin").join(exe!("hello"));
+    assert!(hello.is_file());
+
+    let rim = installed_rim(&process);
+    let status = process
+        .rim_command(&rim)
+        .env("RIM_DIST_SERVER", mocked_dist_server().rim.as_str())
+        // the mocked server doesn't sign its manifests
+        .args(["-y", "--allow-unsigned", "switch", "--rustup-dist-server"])
+        .arg(mocked_dist_server().rustup.as_str())
+        .arg("1.82.0")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let record: toml::Table =
+        std::fs::read_to_string(process.config_dir().join("install-record.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+    assert_eq!(record["version"].as_str(), Some("stable-1.82.0"));
+    assert_eq!(record["rust"]["version"].as_str(), Some("1.82.0"));
+    // the tool is not provided by the older toolkit
+    assert!(record.get("tools").and_then(|t| t.get("hello")).is_none());
+    assert!(!hello.exists());
+    let toolchains = install_dir.join("rustup").join("toolchains");
+    assert!(toolchains
+        .join(format!("1.82.0-{}", env!("TARGET")))
+        .is_dir());
+}
@@ -184,6 +184,84 @@ export CARGO_HOME=/path/to/cargo
         .all(|f| { std::fs::read_to_string(f).unwrap() == legacy_content }));
 }
 
+#[cfg(unix)]
+#[rim_test]
+fn failed_installation_is_reverted() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let process = TestProcess::installer();
+    // an installer that fails after leaving a marker behind, tools that don't require rust
+    // are installed after the install dir, env vars and cargo config are all set up.
+    let marker = process.root().join("installer-was-run");
+    let installer = process.root().join("failing-installer.sh");
+    std::fs::write(
+        &installer,
+        format!("#!/bin/sh\ntouch '{}'\nexit 1\n", marker.display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(&installer, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let manifest = process.root().join("broken-manifest.toml");
+    std::fs::write(
+        &manifest,
+        format!(
+            r#"
+[toolchain]
+channel = "1.86.0"
+profile = "minimal"
+
+[tools.target.all.broken-tool]
+required = true
+kind = "installer"
+path = '{}'
+"#,
+            installer.display()
+        ),
+    )
+    .unwrap();
+
+    process
+        .command()
+        .args(["-y", "--manifest"])
+        .arg(&manifest)
+        .assert()
+        .failure();
+
+    assert!(marker.is_file());
+    assert!(!process.default_install_dir().exists());
+    assert!(!process.config_dir().join("install-record.toml").exists());
+    #[cfg(target_os = "linux")]
+    for rc in [".profile", ".bash_profile", ".bash_login", ".bashrc"] {
+        assert!(!process.home_dir().join(rc).exists());
+    }
+}
+
+#[cfg(unix)]
+#[rim_test]
+fn preflight_failure_changes_nothing() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let process = TestProcess::installer();
+    let readonly = process.root().join("readonly");
+    std::fs::create_dir(&readonly).unwrap();
+    std::fs::set_permissions(&readonly, std::fs::Permissions::from_mode(0o555)).unwrap();
+    // permissions are not enforced for privileged users
+    if std::fs::write(readonly.join("probe"), "").is_ok() {
+        return;
+    }
+
+    process
+        .command()
+        .arg("--prefix")
+        .arg(readonly.join("rust"))
+        .arg("-y")
+        .assert()
+        .failure();
+
+    assert!(!process.config_dir().join("operation.lock").exists());
+    assert!(!process.config_dir().join("install-record.toml").exists());
+}
+
 #[rim_test]
 fn install_record_created() {
     let process = default_install(false);